@@ -0,0 +1,313 @@
+use super::error::{ErrorCliente, ResultadoCliente};
+use super::pago::{EstadoPago, PagoFallido, PagoNuevo};
+use common::dns::{DNS, NODOS_ALGLOBO};
+use common::error::ErrorApp;
+use common::protocolo_pagos::{MensajePago, ProtocoloPagos};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Tiempo máximo por defecto para completar un pedido, incluyendo la
+/// transacción del pago
+const TIMEOUT_DEFAULT: Duration = Duration::from_secs(60);
+/// Tiempo de espera antes de volver a buscar al líder cuando ningún nodo
+/// atiende el pedido
+const ESPERA_LIDER: Duration = Duration::from_millis(500);
+/// Tiempo de espera entre consultas del estado de un pago pendiente
+const ESPERA_CONSULTA: Duration = Duration::from_millis(500);
+
+/// ClienteAlglobo implementa el envío de pagos a AlGlobo y la consulta de su
+/// estado. Los pedidos se envían al líder, que se descubre con las
+/// direcciones de los nodos del cluster: se prueba primero el último líder
+/// conocido y luego cada nodo, de mayor a menor id, siguiendo las
+/// redirecciones al líder de los nodos que no lo son. Un pago o un reintento
+/// que ya se envió a un nodo no se reenvía a otro, ya que pudo haberse
+/// aceptado.
+pub struct ClienteAlglobo {
+    nodos: BTreeMap<usize, String>,
+    lider: Option<usize>,
+    timeout: Duration,
+}
+
+impl Default for ClienteAlglobo {
+    fn default() -> Self {
+        ClienteAlglobo::local()
+    }
+}
+
+impl ClienteAlglobo {
+    /// Devuelve una instancia de ClienteAlglobo para un cluster local, con
+    /// las direcciones por defecto de todos sus nodos.
+    pub fn local() -> Self {
+        ClienteAlglobo::new(
+            (0..NODOS_ALGLOBO)
+                .map(|id| (id, DNS::direccion_pagos(&id)))
+                .collect(),
+        )
+    }
+
+    /// Devuelve una instancia de ClienteAlglobo para los nodos dados.
+    /// Recibe la dirección en la que cada nodo atiende los pedidos de pago,
+    /// por el id del nodo. Los nodos a los que se redirige y que no están
+    /// entre los dados se saltean.
+    pub fn new(nodos: BTreeMap<usize, String>) -> Self {
+        ClienteAlglobo {
+            nodos,
+            lider: None,
+            timeout: TIMEOUT_DEFAULT,
+        }
+    }
+
+    /// Devuelve el cliente con el tiempo máximo dado para completar cada
+    /// pedido.
+    pub fn con_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Devuelve el id del último líder conocido, si hay uno.
+    pub fn get_lider(&self) -> Option<usize> {
+        self.lider
+    }
+
+    /// Envía el pago y devuelve el id que le asignó el líder, sin esperar el
+    /// resultado de su transacción, que se puede consultar con `consultar` o
+    /// esperar con `esperar`.
+    /// Devuelve error si el nodo cierra la conexión luego de recibir el pago,
+    /// sin reenviarlo a otro nodo.
+    pub fn enviar(&mut self, pago: &PagoNuevo) -> ResultadoCliente<usize> {
+        let limite = Instant::now() + self.timeout;
+        let (_, respuesta) = self.pedir(&pago.mensaje(), limite)?;
+        ClienteAlglobo::aceptado(respuesta)
+    }
+
+    /// Envía el pago y espera el resultado de su transacción. Devuelve el id
+    /// que le asignó el líder y su estado final. Si el líder cae antes de
+    /// terminar la transacción, se consulta el estado al nuevo líder. Si cae
+    /// antes de aceptar el pago, como en `enviar`, devuelve error.
+    pub fn pagar(&mut self, pago: &PagoNuevo) -> ResultadoCliente<(usize, EstadoPago)> {
+        let limite = Instant::now() + self.timeout;
+        let (protocolo, respuesta) = self.pedir(&pago.mensaje(), limite)?;
+        let id_pago = ClienteAlglobo::aceptado(respuesta)?;
+        Ok((id_pago, self.resultado(protocolo, id_pago, limite)?))
+    }
+
+    /// Reintenta el pago fallido de id dado y espera el resultado de su
+    /// transacción. Devuelve su estado final.
+    /// Devuelve error si el pago no está entre los fallidos, o si el nodo
+    /// cierra la conexión luego de recibir el pedido, sin reenviarlo a otro
+    /// nodo.
+    pub fn reintentar(&mut self, id_pago: usize) -> ResultadoCliente<EstadoPago> {
+        let limite = Instant::now() + self.timeout;
+        let (protocolo, respuesta) = self.pedir(&MensajePago::REINTENTAR { id_pago }, limite)?;
+        ClienteAlglobo::aceptado(respuesta)?;
+        self.resultado(protocolo, id_pago, limite)
+    }
+
+    /// Devuelve el estado del pago de id dado.
+    pub fn consultar(&mut self, id_pago: usize) -> ResultadoCliente<EstadoPago> {
+        let limite = Instant::now() + self.timeout;
+        self.consultar_hasta(id_pago, limite)
+    }
+
+    /// Consulta el estado del pago de id dado hasta que deja de estar
+    /// pendiente y lo devuelve.
+    pub fn esperar(&mut self, id_pago: usize) -> ResultadoCliente<EstadoPago> {
+        let limite = Instant::now() + self.timeout;
+        self.esperar_hasta(id_pago, limite)
+    }
+
+    /// Devuelve los pagos fallidos, junto al motivo por el cual fallaron si
+    /// se conoce.
+    pub fn listar_fallidos(&mut self) -> ResultadoCliente<Vec<PagoFallido>> {
+        let limite = Instant::now() + self.timeout;
+        // Si el líder deja de serlo durante el listado, se vuelve a pedir
+        loop {
+            let (mut protocolo, respuesta) = self.pedir(&MensajePago::LISTAR, limite)?;
+            let mut respuesta = Some(respuesta);
+            let mut fallidos = Vec::new();
+            loop {
+                match respuesta {
+                    Some(MensajePago::PAGO_FALLIDO {
+                        id_pago,
+                        monto_aerolinea,
+                        monto_hotel,
+                        datos,
+                        motivo,
+                    }) => fallidos.push(PagoFallido {
+                        id_pago,
+                        monto_aerolinea,
+                        monto_hotel,
+                        datos,
+                        motivo,
+                    }),
+                    Some(MensajePago::FIN) => return Ok(fallidos),
+                    Some(MensajePago::RECHAZADO { motivo }) => {
+                        return Err(ErrorCliente::Rechazado(motivo))
+                    }
+                    Some(MensajePago::LIDER { .. }) | Some(MensajePago::SIN_LIDER) | None => break,
+                    Some(otro) => return Err(ErrorCliente::Protocolo(otro.codificar())),
+                }
+                respuesta = ClienteAlglobo::recibir(&mut protocolo, limite)?;
+            }
+        }
+    }
+
+    /// Envía el pedido al líder y devuelve la conexión con el líder y su
+    /// primera respuesta. Los nodos que no responden se saltean, y si ningún
+    /// nodo atiende el pedido se vuelve a buscar al líder hasta el límite
+    /// dado.
+    /// Devuelve error si se envió un pago o un reintento y la conexión se
+    /// cerró antes de la respuesta, en lugar de reenviarlo a otro nodo.
+    fn pedir(
+        &mut self,
+        pedido: &MensajePago,
+        limite: Instant,
+    ) -> ResultadoCliente<(ProtocoloPagos, MensajePago)> {
+        while Instant::now() < limite {
+            let candidatos = self
+                .lider
+                .iter()
+                .chain(self.nodos.keys().rev())
+                .copied()
+                .collect::<Vec<usize>>();
+            let mut redirigido = false;
+            for nodo in candidatos {
+                let direccion = match self.nodos.get(&nodo) {
+                    Some(direccion) => direccion,
+                    None => continue,
+                };
+                let mut protocolo = match ProtocoloPagos::conectar(direccion.clone()) {
+                    Ok(protocolo) => protocolo,
+                    Err(_) => continue,
+                };
+                if protocolo.enviar(pedido).is_err() {
+                    continue;
+                }
+                match ClienteAlglobo::recibir(&mut protocolo, limite) {
+                    // Si redirige a un líder nuevo se lo prueba primero
+                    Ok(Some(MensajePago::LIDER { id_lider })) => {
+                        if self.lider != Some(id_lider) {
+                            self.lider = Some(id_lider);
+                            redirigido = true;
+                            break;
+                        }
+                    }
+                    Ok(Some(MensajePago::SIN_LIDER)) => {}
+                    // El nodo pudo haber aceptado el pago antes de caer
+                    Ok(None) | Err(ErrorCliente::App(_))
+                        if matches!(
+                            pedido,
+                            MensajePago::PAGAR { .. } | MensajePago::REINTENTAR { .. }
+                        ) =>
+                    {
+                        return Err(ErrorCliente::SinConfirmacion)
+                    }
+                    Ok(None) | Err(ErrorCliente::App(_)) => {}
+                    Ok(Some(respuesta)) => {
+                        self.lider = Some(nodo);
+                        return Ok((protocolo, respuesta));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if !redirigido {
+                self.lider = None;
+                thread::sleep(ESPERA_LIDER);
+            }
+        }
+        Err(ErrorCliente::SinLider)
+    }
+
+    /// Espera el resultado de la transacción del pago de id dado por la
+    /// conexión con el líder y lo devuelve. Si el líder deja de serlo o cae
+    /// antes de informarlo, se consulta el estado al nuevo líder.
+    fn resultado(
+        &mut self,
+        mut protocolo: ProtocoloPagos,
+        id_pago: usize,
+        limite: Instant,
+    ) -> ResultadoCliente<EstadoPago> {
+        match ClienteAlglobo::recibir(&mut protocolo, limite) {
+            Ok(Some(MensajePago::LIDER { .. })) | Ok(Some(MensajePago::SIN_LIDER)) | Ok(None) => {
+                self.esperar_hasta(id_pago, limite)
+            }
+            Ok(Some(respuesta)) => ClienteAlglobo::estado(respuesta),
+            Err(ErrorCliente::Timeout(_)) => Err(ErrorCliente::Timeout(Some(id_pago))),
+            Err(ErrorCliente::App(_)) => self.esperar_hasta(id_pago, limite),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Consulta el estado del pago de id dado hasta que deja de estar
+    /// pendiente o hasta el límite dado.
+    fn esperar_hasta(&mut self, id_pago: usize, limite: Instant) -> ResultadoCliente<EstadoPago> {
+        loop {
+            let estado = match self.consultar_hasta(id_pago, limite) {
+                Err(ErrorCliente::Timeout(_)) | Err(ErrorCliente::SinLider) => {
+                    return Err(ErrorCliente::Timeout(Some(id_pago)))
+                }
+                resultado => resultado?,
+            };
+            if estado.es_final() {
+                return Ok(estado);
+            }
+            if Instant::now() + ESPERA_CONSULTA >= limite {
+                return Err(ErrorCliente::Timeout(Some(id_pago)));
+            }
+            thread::sleep(ESPERA_CONSULTA);
+        }
+    }
+
+    /// Devuelve el estado del pago de id dado, consultándolo hasta el límite
+    /// dado.
+    fn consultar_hasta(&mut self, id_pago: usize, limite: Instant) -> ResultadoCliente<EstadoPago> {
+        let (_, respuesta) = self.pedir(&MensajePago::ESTADO { id_pago }, limite)?;
+        ClienteAlglobo::estado(respuesta)
+    }
+
+    /// Recibe un mensaje de la conexión dada esperando a lo sumo hasta el
+    /// límite dado. Devuelve None si la conexión se cerró.
+    fn recibir(
+        protocolo: &mut ProtocoloPagos,
+        limite: Instant,
+    ) -> ResultadoCliente<Option<MensajePago>> {
+        let restante = limite.saturating_duration_since(Instant::now());
+        if restante.is_zero() {
+            return Err(ErrorCliente::Timeout(None));
+        }
+        match protocolo.recibir(Some(restante)) {
+            Err(ErrorApp::ErrorIO(e))
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut =>
+            {
+                Err(ErrorCliente::Timeout(None))
+            }
+            resultado => Ok(resultado?),
+        }
+    }
+
+    /// Devuelve el id del pago aceptado por el líder en la respuesta dada.
+    /// Devuelve error si el líder rechazó el pedido.
+    fn aceptado(respuesta: MensajePago) -> ResultadoCliente<usize> {
+        match respuesta {
+            MensajePago::ACEPTADO { id_pago } => Ok(id_pago),
+            MensajePago::RECHAZADO { motivo } => Err(ErrorCliente::Rechazado(motivo)),
+            otro => Err(ErrorCliente::Protocolo(otro.codificar())),
+        }
+    }
+
+    /// Devuelve el estado de un pago informado por el líder en la respuesta
+    /// dada.
+    /// Devuelve error si el líder rechazó el pedido.
+    fn estado(respuesta: MensajePago) -> ResultadoCliente<EstadoPago> {
+        match respuesta {
+            MensajePago::CONFIRMADO { .. } => Ok(EstadoPago::Confirmado),
+            MensajePago::FALLIDO { motivo, .. } => Ok(EstadoPago::Fallido(motivo)),
+            MensajePago::PENDIENTE { .. } => Ok(EstadoPago::Pendiente),
+            MensajePago::DESCONOCIDO { .. } => Ok(EstadoPago::Desconocido),
+            MensajePago::RECHAZADO { motivo } => Err(ErrorCliente::Rechazado(motivo)),
+            otro => Err(ErrorCliente::Protocolo(otro.codificar())),
+        }
+    }
+}
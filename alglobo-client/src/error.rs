@@ -0,0 +1,57 @@
+use common::error::ErrorApp;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// ErrorCliente enumera los errores de los pedidos a AlGlobo.
+/// # Variantes
+/// SinLider: ningún nodo del cluster atendió el pedido como líder dentro del
+/// timeout, porque no hay nodos activos o hay una elección en curso.
+/// Rechazado: el líder rechazó el pedido. Contiene el motivo.
+/// Timeout: no se conoció la respuesta dentro del timeout. Si el pago ya se
+/// había aceptado contiene su id, con el que se puede consultar su estado.
+/// SinConfirmacion: el pago o su reintento se envió a un nodo que cerró la
+/// conexión antes de responder, por lo que pudo haberse aceptado. No se
+/// reenvía para no ejecutarlo dos veces.
+/// Protocolo: el nodo respondió un mensaje inesperado. Contiene el mensaje.
+/// App: error de comunicación con el nodo o de decodificación de mensajes.
+#[derive(Debug)]
+pub enum ErrorCliente {
+    SinLider,
+    Rechazado(String),
+    Timeout(Option<usize>),
+    SinConfirmacion,
+    Protocolo(String),
+    App(ErrorApp),
+}
+
+/// Tipo de resultado de los pedidos a AlGlobo
+pub type ResultadoCliente<T> = std::result::Result<T, ErrorCliente>;
+
+impl From<ErrorApp> for ErrorCliente {
+    fn from(err: ErrorApp) -> ErrorCliente {
+        ErrorCliente::App(err)
+    }
+}
+
+impl Display for ErrorCliente {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ErrorCliente::SinLider => write!(f, "No se encontro un lider de AlGlobo"),
+            ErrorCliente::Rechazado(motivo) => write!(f, "Pedido rechazado: {}", motivo),
+            ErrorCliente::Timeout(Some(id_pago)) => write!(
+                f,
+                "No se conocio el resultado del pago de id {} a tiempo",
+                id_pago
+            ),
+            ErrorCliente::Timeout(None) => write!(f, "No se recibio respuesta a tiempo"),
+            ErrorCliente::SinConfirmacion => write!(
+                f,
+                "El pago se envio pero no se confirmo su recepcion, pudo haberse aceptado"
+            ),
+            ErrorCliente::Protocolo(mensaje) => write!(f, "Respuesta inesperada: {}", mensaje),
+            ErrorCliente::App(inner) => inner.fmt(f),
+        }
+    }
+}
+
+impl Error for ErrorCliente {}
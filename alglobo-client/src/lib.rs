@@ -0,0 +1,3 @@
+pub mod cliente;
+pub mod error;
+pub mod pago;
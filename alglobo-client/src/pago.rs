@@ -0,0 +1,116 @@
+use common::monto::Monto;
+use common::motivo_aborto::MotivoAborto;
+use common::protocolo_pagos::MensajePago;
+use std::collections::BTreeMap;
+
+/// PagoNuevo representa un pago a enviar a AlGlobo. Contiene el monto a pagar
+/// de la aerolínea y del hotel, y opcionalmente los datos de la reserva, con
+/// los nombres que valida el líder: el id del cliente, el vuelo, la estadía
+/// en el hotel y metadatos libres.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PagoNuevo {
+    monto_aerolinea: Monto,
+    monto_hotel: Monto,
+    datos: BTreeMap<String, String>,
+}
+
+impl PagoNuevo {
+    /// Devuelve una instancia de PagoNuevo, sin datos de la reserva.
+    /// Recibe el monto de la aerolinea y el monto del hotel.
+    pub fn new(monto_aerolinea: Monto, monto_hotel: Monto) -> Self {
+        PagoNuevo {
+            monto_aerolinea,
+            monto_hotel,
+            datos: BTreeMap::new(),
+        }
+    }
+
+    /// Devuelve el pago con el id del cliente dado.
+    pub fn con_cliente(self, cliente: &str) -> Self {
+        self.con_dato("cliente", cliente)
+    }
+
+    /// Devuelve el pago con el vuelo dado. La fecha tiene el formato
+    /// `AAAA-MM-DD`.
+    pub fn con_vuelo(self, numero: &str, fecha: &str) -> Self {
+        self.con_dato("vuelo", numero)
+            .con_dato("fecha_vuelo", fecha)
+    }
+
+    /// Devuelve el pago con la estadía en el hotel dada. Las fechas tienen el
+    /// formato `AAAA-MM-DD`, con el check-out posterior al check-in.
+    pub fn con_estadia(self, hotel: &str, check_in: &str, check_out: &str) -> Self {
+        self.con_dato("hotel", hotel)
+            .con_dato("check_in", check_in)
+            .con_dato("check_out", check_out)
+    }
+
+    /// Devuelve el pago con el metadato dado.
+    pub fn con_metadato(self, nombre: &str, valor: &str) -> Self {
+        self.con_dato(&format!("meta.{}", nombre), valor)
+    }
+
+    /// Devuelve el monto de la aerolínea.
+    pub fn get_monto_aerolinea(&self) -> Monto {
+        self.monto_aerolinea
+    }
+
+    /// Devuelve el monto del hotel.
+    pub fn get_monto_hotel(&self) -> Monto {
+        self.monto_hotel
+    }
+
+    /// Devuelve los datos de la reserva por nombre.
+    pub fn get_datos(&self) -> &BTreeMap<String, String> {
+        &self.datos
+    }
+
+    /// Devuelve el mensaje con el que se envía el pago.
+    pub(crate) fn mensaje(&self) -> MensajePago {
+        MensajePago::PAGAR {
+            monto_aerolinea: self.monto_aerolinea,
+            monto_hotel: self.monto_hotel,
+            datos: self.datos.clone(),
+        }
+    }
+
+    fn con_dato(mut self, nombre: &str, valor: &str) -> Self {
+        self.datos.insert(nombre.to_string(), valor.to_string());
+        self
+    }
+}
+
+/// EstadoPago representa el estado de un pago enviado a AlGlobo.
+/// # Variantes
+/// Confirmado: la transacción del pago se commiteó.
+/// Fallido: la transacción del pago se abortó, por el motivo que contiene. El
+/// pago se puede reintentar.
+/// Pendiente: la transacción del pago no terminó.
+/// Desconocido: el líder no conoce el pago, por ejemplo porque el líder que
+/// lo aceptó cayó antes de registrarlo.
+#[derive(Clone, PartialEq, Debug)]
+pub enum EstadoPago {
+    Confirmado,
+    Fallido(MotivoAborto),
+    Pendiente,
+    Desconocido,
+}
+
+impl EstadoPago {
+    /// Devuelve verdadero si el estado no va a cambiar salvo que se
+    /// reintente el pago, es decir, si no está pendiente.
+    pub fn es_final(&self) -> bool {
+        *self != EstadoPago::Pendiente
+    }
+}
+
+/// PagoFallido representa un pago fallido de AlGlobo, con sus montos, los
+/// datos de su reserva y el motivo del abort, si se conoce.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PagoFallido {
+    pub id_pago: usize,
+    pub monto_aerolinea: Monto,
+    pub monto_hotel: Monto,
+    pub datos: BTreeMap<String, String>,
+    pub motivo: Option<MotivoAborto>,
+}
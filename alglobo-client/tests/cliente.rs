@@ -0,0 +1,141 @@
+use alglobo_client::cliente::ClienteAlglobo;
+use alglobo_client::error::ErrorCliente;
+use alglobo_client::pago::{EstadoPago, PagoNuevo};
+use common::monto::{Moneda, Monto};
+use common::protocolo_pagos::{MensajePago, ProtocoloPagos};
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// NodoFalso atiende los pedidos de pago como un nodo de AlGlobo, con las
+/// respuestas que devuelve la función dada para cada pedido. Sin respuestas,
+/// cierra la conexión luego de recibir el pedido, como un nodo que cae.
+struct NodoFalso {
+    direccion: String,
+    pedidos: Arc<Mutex<Vec<MensajePago>>>,
+}
+
+impl NodoFalso {
+    fn iniciar(responder: fn(&MensajePago) -> Vec<MensajePago>) -> NodoFalso {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let direccion = listener.local_addr().unwrap().to_string();
+        let pedidos = Arc::new(Mutex::new(Vec::new()));
+        let recibidos = pedidos.clone();
+        thread::spawn(move || {
+            for conexion in listener.incoming() {
+                let mut protocolo = ProtocoloPagos::new(conexion.unwrap()).unwrap();
+                if let Ok(Some(pedido)) = protocolo.recibir(None) {
+                    recibidos.lock().unwrap().push(pedido.clone());
+                    for respuesta in responder(&pedido) {
+                        let _ = protocolo.enviar(&respuesta);
+                    }
+                }
+            }
+        });
+        NodoFalso { direccion, pedidos }
+    }
+
+    fn pedidos(&self) -> Vec<MensajePago> {
+        self.pedidos.lock().unwrap().clone()
+    }
+}
+
+/// Devuelve un cliente para los nodos dados, con ids consecutivos desde 0.
+fn cliente(nodos: &[&NodoFalso]) -> ClienteAlglobo {
+    ClienteAlglobo::new(
+        nodos
+            .iter()
+            .enumerate()
+            .map(|(id, nodo)| (id, nodo.direccion.clone()))
+            .collect::<BTreeMap<usize, String>>(),
+    )
+    .con_timeout(Duration::from_secs(5))
+}
+
+fn pago() -> PagoNuevo {
+    PagoNuevo::new(
+        Monto::new(1000, Moneda::POR_DEFECTO),
+        Monto::new(500, Moneda::POR_DEFECTO),
+    )
+}
+
+fn redirigir_al_nodo_0(_: &MensajePago) -> Vec<MensajePago> {
+    vec![MensajePago::LIDER { id_lider: 0 }]
+}
+
+fn cerrar(_: &MensajePago) -> Vec<MensajePago> {
+    Vec::new()
+}
+
+fn atender(pedido: &MensajePago) -> Vec<MensajePago> {
+    match pedido {
+        MensajePago::PAGAR { .. } => vec![MensajePago::ACEPTADO { id_pago: 7 }],
+        MensajePago::REINTENTAR { id_pago } => vec![
+            MensajePago::ACEPTADO { id_pago: *id_pago },
+            MensajePago::CONFIRMADO { id_pago: *id_pago },
+        ],
+        MensajePago::ESTADO { id_pago } => vec![MensajePago::CONFIRMADO { id_pago: *id_pago }],
+        _ => vec![MensajePago::FIN],
+    }
+}
+
+#[test]
+fn pedido_sigue_la_redireccion_al_lider() {
+    let lider = NodoFalso::iniciar(atender);
+    let seguidor = NodoFalso::iniciar(redirigir_al_nodo_0);
+    let mut cliente = cliente(&[&lider, &seguidor]);
+
+    assert_eq!(cliente.enviar(&pago()).unwrap(), 7);
+    assert_eq!(cliente.get_lider(), Some(0));
+    assert_eq!(seguidor.pedidos().len(), 1);
+    assert_eq!(lider.pedidos().len(), 1);
+
+    // El líder conocido se prueba primero
+    assert_eq!(cliente.reintentar(3).unwrap(), EstadoPago::Confirmado);
+    assert_eq!(seguidor.pedidos().len(), 1);
+}
+
+#[test]
+fn pago_sin_respuesta_no_se_reenvia() {
+    let lider = NodoFalso::iniciar(atender);
+    let caido = NodoFalso::iniciar(cerrar);
+    let mut cliente = cliente(&[&lider, &caido]);
+
+    assert!(matches!(
+        cliente.enviar(&pago()),
+        Err(ErrorCliente::SinConfirmacion)
+    ));
+    assert_eq!(caido.pedidos().len(), 1);
+    assert!(lider.pedidos().is_empty());
+}
+
+#[test]
+fn reintento_sin_respuesta_no_se_reenvia() {
+    let lider = NodoFalso::iniciar(atender);
+    let caido = NodoFalso::iniciar(cerrar);
+    let mut cliente = cliente(&[&lider, &caido]);
+
+    assert!(matches!(
+        cliente.reintentar(3),
+        Err(ErrorCliente::SinConfirmacion)
+    ));
+    assert_eq!(
+        caido.pedidos(),
+        vec![MensajePago::REINTENTAR { id_pago: 3 }]
+    );
+    assert!(lider.pedidos().is_empty());
+}
+
+#[test]
+fn consulta_sin_respuesta_se_reenvia_a_otro_nodo() {
+    let lider = NodoFalso::iniciar(atender);
+    let caido = NodoFalso::iniciar(cerrar);
+    let mut cliente = cliente(&[&lider, &caido]);
+
+    assert_eq!(cliente.consultar(3).unwrap(), EstadoPago::Confirmado);
+    assert_eq!(caido.pedidos().len(), 1);
+    assert_eq!(lider.pedidos(), vec![MensajePago::ESTADO { id_pago: 3 }]);
+    assert_eq!(cliente.get_lider(), Some(0));
+}
@@ -1,22 +1,43 @@
-mod model;
+use alglobo::model::almacenamiento;
+use alglobo::model::aplicacion::{Aplicacion, Entrada};
+use alglobo::model::bandeja::Bandeja;
+use alglobo::model::comando::Comando;
+use alglobo::model::config::Config;
+use alglobo::model::eleccion_lider::EleccionLider;
+use alglobo::model::formato_entrada::TipoFormato;
+use alglobo::model::log::Log;
+use alglobo::model::parser::Parser;
+use alglobo::model::servidor_pagos::ServidorPagos;
 use common::error::Resultado;
-use model::aplicacion::Aplicacion;
-use model::comando::Comando;
-use model::eleccion_lider::EleccionLider;
-use model::parser::Parser;
+use std::path::Path;
 use std::sync::mpsc::channel;
 
-fn procesar(id: usize, path_pagos: String) -> Resultado<()> {
-    let parseador = Parser::new(path_pagos)?;
+fn procesar(id: usize, path_pagos: String, formato: Option<TipoFormato>) -> Resultado<()> {
+    let config = Config::get_config(Config::ruta_config())?;
+    // No se inicia el nodo si el estado persistido tiene corrupción
+    Log::verificar(&config)?;
+    // Si el path es un directorio se procesan los archivos que llegan al mismo
+    let entrada = if Path::new(&path_pagos).is_dir() {
+        Entrada::Bandeja(Bandeja::new(&path_pagos, formato)?)
+    } else {
+        let formato = formato.unwrap_or_else(|| TipoFormato::desde_ruta(Path::new(&path_pagos)));
+        Entrada::Archivo(Parser::new(path_pagos, formato)?)
+    };
     let lider = EleccionLider::new(id)?;
     let (enviador, receptor) = channel::<Comando>();
-    let app = Aplicacion::new(id, lider, parseador, receptor)?;
+    // Los pagos recibidos por socket llegan como comandos
+    ServidorPagos::iniciar(id, lider.vista(), enviador.clone())?;
+    let app = Aplicacion::new(id, lider, entrada, receptor, config.clone())?;
 
     loop {
         let mut entrada = String::new();
         let _ = std::io::stdin().read_line(&mut entrada);
         entrada = entrada.replace("\n", "");
 
+        if entrada.split(' ').next() == Some("L") {
+            listar_fallidos(&config);
+            continue;
+        }
         if let Ok(comando) = Comando::decodificar(&entrada) {
             if let Err(e) = enviador.send(comando.clone()) {
                 println!("{}", e);
@@ -34,12 +55,36 @@ fn procesar(id: usize, path_pagos: String) -> Resultado<()> {
     Ok(())
 }
 
+/// Muestra los pagos fallidos junto al motivo por el cual fallaron, para
+/// decidir si tiene sentido reintentarlos.
+fn listar_fallidos(config: &Config) {
+    let fallidos = match almacenamiento::abrir_lectura(config).and_then(|a| a.listar_fallidos()) {
+        Ok(f) => f,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    if fallidos.is_empty() {
+        println!("[Aplicacion]: No hay pagos fallidos");
+    }
+    for (pago, motivo) in fallidos {
+        match motivo {
+            Some(m) => println!("[Aplicacion]: Pago de id {}: {}", pago.get_id(), m),
+            None => println!(
+                "[Aplicacion]: Pago de id {}: motivo desconocido",
+                pago.get_id()
+            ),
+        }
+    }
+}
+
 fn main() {
     println!("NODO DE ALGLOBO");
     let path_pagos = match std::env::args().nth(1) {
         Some(path) => path,
         None => {
-            println!("Se debe indicar un path a un archivo de pagos");
+            println!("Se debe indicar un path a un archivo de pagos o a una bandeja de entrada");
             return;
         }
     };
@@ -55,7 +100,28 @@ fn main() {
         }
     };
 
-    if let Err(err) = procesar(id, path_pagos) {
+    // El formato del archivo de pagos se deduce de su extension salvo que se
+    // indique con --formato
+    let formato = match std::env::args().nth(3).as_deref() {
+        Some("--formato") => match std::env::args()
+            .nth(4)
+            .ok_or(())
+            .and_then(|f| TipoFormato::decodificar(&f).map_err(|_| ()))
+        {
+            Ok(f) => Some(f),
+            Err(_) => {
+                println!("Se debe indicar un formato: csv, csv-encabezado, jsonl o tsv");
+                return;
+            }
+        },
+        Some(_) => {
+            println!("Uso: alglobo <archivo de pagos o bandeja> <id> [--formato <formato>]");
+            return;
+        }
+        None => None,
+    };
+
+    if let Err(err) = procesar(id, path_pagos, formato) {
         println!("{}", err)
     }
 }
@@ -1,9 +1,24 @@
+pub mod almacenamiento;
+pub mod almacenamiento_archivo;
+pub mod almacenamiento_memoria;
+pub mod almacenamiento_sqlite;
 pub mod aplicacion;
+pub mod bandeja;
+pub mod cadena;
 pub mod comando;
 pub mod config;
+pub mod cotizaciones;
 pub mod coordinador_transaccion;
 pub mod eleccion_lider;
+pub mod estado_transaccion;
+pub mod formato_csv_encabezado;
+pub mod formato_delimitado;
+pub mod formato_entrada;
+pub mod formato_jsonl;
 pub mod log;
+pub mod log_segmentado;
 pub mod pago;
 pub mod parser;
 pub mod parser_fallidos;
+pub mod servidor_pagos;
+pub mod wal;
@@ -0,0 +1,106 @@
+use common::error::Resultado;
+use common::id_transaccion::IdTransaccion;
+use common::motivo_aborto::MotivoAborto;
+use serde::{Deserialize, Serialize};
+
+use super::almacenamiento_archivo::AlmacenamientoArchivo;
+use super::almacenamiento_memoria::AlmacenamientoMemoria;
+use super::almacenamiento_sqlite::AlmacenamientoSqlite;
+use super::config::Config;
+use super::log::Transaccion;
+use super::pago::Pago;
+
+/// TipoAlmacenamiento representa los backends de persistencia disponibles.
+/// # Variantes
+/// Archivo: log segmentado, snapshot y archivo csv de fallidos.
+/// Memoria: estado en memoria de cada instancia, que no sobrevive a un
+/// reinicio ni se comparte con las demás instancias del log del proceso. Solo
+/// se usa en los tests, por lo que la configuración leída de un archivo no lo
+/// admite.
+/// Sqlite: base de datos SQLite embebida.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoAlmacenamiento {
+    Archivo,
+    Memoria,
+    Sqlite,
+}
+
+/// Almacenamiento abstrae la persistencia del estado de las transacciones y
+/// de los pagos fallidos.
+pub trait Almacenamiento: Send + Sync {
+    /// Recupera el estado de las transacciones persistido, reparando lo que
+    /// haya quedado incompleto por una caída. Debe llamarse antes de operar
+    /// con transacciones.
+    fn recuperar(&mut self) -> Resultado<()>;
+
+    /// Verifica que el estado persistido se pueda recuperar, sin
+    /// modificarlo. Devuelve error si está corrupto.
+    fn verificar(&self) -> Resultado<()>;
+
+    /// Persiste el nuevo estado de una transacción.
+    fn insertar(&mut self, transaccion: &Transaccion) -> Resultado<()>;
+
+    /// Devuelve el último estado de la transacción de id dado, si existe.
+    fn obtener(&self, id: IdTransaccion) -> Resultado<Option<Transaccion>>;
+
+    /// Devuelve las transacciones que no fueron finalizadas, ordenadas por id.
+    fn transacciones_pendientes(&self) -> Resultado<Vec<Transaccion>>;
+
+    /// Devuelve la última transacción insertada.
+    fn ultima_transaccion(&self) -> Resultado<Option<Transaccion>>;
+
+    /// Devuelve el mayor id de transacción insertado, o el id por defecto si
+    /// no hay ninguno.
+    fn id_maximo(&self) -> Resultado<IdTransaccion>;
+
+    /// Devuelve verdadero si alguna transacción del pago de id dado llegó a
+    /// commitearse.
+    fn pago_confirmado(&self, id_pago: usize) -> Resultado<bool>;
+
+    /// Devuelve los registros de todas las transacciones, incluyendo los
+    /// archivados, en el orden en que se insertaron. Los almacenamientos que
+    /// solo conservan el último estado devuelven un registro por transacción.
+    /// No requiere recuperar el estado.
+    fn historial(&self) -> Resultado<Vec<Transaccion>>;
+
+    /// Devuelve la mayor época de liderazgo registrada o presente en los
+    /// registros de las transacciones, o 0 si no hay ninguna.
+    fn epoca_maxima(&self) -> Resultado<u32>;
+
+    /// Persiste la época de liderazgo en la que un nodo abrió el log.
+    fn registrar_epoca(&mut self, epoca: u32) -> Resultado<()>;
+
+    /// Sincroniza a disco lo insertado desde la última sincronización.
+    fn sincronizar(&mut self) -> Resultado<()>;
+
+    /// Persiste un pago fallido junto al motivo del abort.
+    fn escribir_fallido(&mut self, pago: &Pago, motivo: Option<&MotivoAborto>) -> Resultado<()>;
+
+    /// Quita el pago fallido de id dado y lo devuelve, si existe.
+    fn tomar_fallido(&mut self, id_pago: usize) -> Resultado<Option<Pago>>;
+
+    /// Devuelve los pagos fallidos junto al motivo por el cual fallaron.
+    fn listar_fallidos(&self) -> Resultado<Vec<(Pago, Option<MotivoAborto>)>>;
+}
+
+/// Devuelve el almacenamiento indicado por la configuración.
+pub fn crear(config: &Config) -> Resultado<Box<dyn Almacenamiento>> {
+    Ok(match config.almacenamiento {
+        TipoAlmacenamiento::Archivo => Box::new(AlmacenamientoArchivo::new(config)),
+        TipoAlmacenamiento::Memoria => Box::new(AlmacenamientoMemoria::new()),
+        TipoAlmacenamiento::Sqlite => Box::new(AlmacenamientoSqlite::new(config)?),
+    })
+}
+
+/// Devuelve el almacenamiento indicado por la configuración abierto para
+/// leer el estado persistido sin modificarlo. El almacenamiento en archivo
+/// no escribe hasta recuperar su estado, por lo que se abre igual que para
+/// operar.
+pub fn abrir_lectura(config: &Config) -> Resultado<Box<dyn Almacenamiento>> {
+    Ok(match config.almacenamiento {
+        TipoAlmacenamiento::Archivo => Box::new(AlmacenamientoArchivo::new(config)),
+        TipoAlmacenamiento::Memoria => Box::new(AlmacenamientoMemoria::new()),
+        TipoAlmacenamiento::Sqlite => Box::new(AlmacenamientoSqlite::abrir_lectura()?),
+    })
+}
@@ -0,0 +1,591 @@
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::id_transaccion::IdTransaccion;
+use common::motivo_aborto::MotivoAborto;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use super::almacenamiento::Almacenamiento;
+use super::cadena::{self, Checkpoint, Firmante, Hash, VerificacionCadena, HASH_INICIAL, TAM_HASH};
+use super::config::Config;
+use super::estado_transaccion::EstadoTransaccion;
+use super::log::{LectorRegistro, Transaccion};
+use super::log_segmentado::LogSegmentado;
+use super::pago::Pago;
+use super::parser_fallidos::ParserFallidos;
+use super::wal::{PoliticaDurabilidad, Wal};
+
+/// Prefijo del registro de encabezado de un snapshot del log.
+const ENCABEZADO_SNAPSHOT: &[u8] = b"SNAPSHOT";
+
+/// AlmacenamientoArchivo persiste las transacciones en binario en un
+/// LogSegmentado y los pagos fallidos en un archivo csv. En memoria solo se
+/// mantienen las transacciones no finalizadas y los ids de los pagos
+/// confirmados; el resto de las transacciones se buscan por id en los
+/// segmentos.
+/// Periódicamente se toma un snapshot del estado vivo (la última transacción
+/// y las transacciones no finalizadas), con los registros en el mismo
+/// formato binario que los segmentos, y los segmentos anteriores se mueven
+/// a un directorio de archivo, de forma que la memoria y el tiempo de
+/// recuperación no crezcan indefinidamente.
+/// Cada registro incluye el hash del anterior, formando una cadena que se
+/// rompe si se edita el historial, y periódicamente se escribe un checkpoint
+/// de la cadena firmado con la clave local del nodo.
+pub struct AlmacenamientoArchivo {
+    segmentos: Option<LogSegmentado>,
+    fallidos: Option<ParserFallidos>,
+    pendientes: HashMap<IdTransaccion, Transaccion>,
+    ultima_trans: Option<Transaccion>,
+    id_maximo: IdTransaccion,
+    pagos_confirmados: PagosConfirmados,
+    generacion: u64,
+    durabilidad: PoliticaDurabilidad,
+    tam_segmento: u64,
+    registros_por_snapshot: usize,
+    registros_desde_snapshot: usize,
+    ultimo_hash: Hash,
+    checkpoints: Option<Wal>,
+    firmante: Option<Firmante>,
+    ruta_clave: Option<String>,
+    registros_por_checkpoint: usize,
+    registros_desde_checkpoint: usize,
+    epoca: u32,
+}
+
+/// PagosConfirmados es el conjunto de ids de los pagos confirmados,
+/// guardado como rangos de ids consecutivos. Los pagos de cada archivo de
+/// entrada tienen ids consecutivos, por lo que el conjunto ocupa según la
+/// cantidad de huecos entre los ids confirmados y no según la cantidad de
+/// pagos.
+#[derive(Clone, Default)]
+struct PagosConfirmados {
+    /// Primer id de cada rango junto al último
+    rangos: BTreeMap<usize, usize>,
+}
+
+impl PagosConfirmados {
+    /// Devuelve verdadero si el pago de id dado fue confirmado.
+    fn contiene(&self, id: usize) -> bool {
+        self.rangos
+            .range(..=id)
+            .next_back()
+            .is_some_and(|(_, ultimo)| id <= *ultimo)
+    }
+
+    /// Agrega el id dado, uniendo los rangos que quedan contiguos.
+    fn insertar(&mut self, id: usize) {
+        if self.contiene(id) {
+            return;
+        }
+        let primero = match self.rangos.range(..id).next_back() {
+            Some((primero, ultimo)) if *ultimo + 1 == id => *primero,
+            _ => id,
+        };
+        let ultimo = id
+            .checked_add(1)
+            .and_then(|siguiente| self.rangos.remove(&siguiente))
+            .unwrap_or(id);
+        self.rangos.insert(primero, ultimo);
+    }
+
+    /// Agrega a los bytes dados la cantidad de rangos y el primer y el
+    /// último id de cada uno.
+    fn codificar(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&(self.rangos.len() as u64).to_le_bytes());
+        for (primero, ultimo) in &self.rangos {
+            bytes.extend_from_slice(&(*primero as u64).to_le_bytes());
+            bytes.extend_from_slice(&(*ultimo as u64).to_le_bytes());
+        }
+    }
+
+    /// Lee los rangos codificados por `codificar`.
+    /// Devuelve error si el registro es inválido.
+    fn decodificar(lector: &mut LectorRegistro) -> Resultado<Self> {
+        let cantidad = lector.u64()?;
+        let rangos = (0..cantidad)
+            .map(|_| Ok((lector.u64()? as usize, lector.u64()? as usize)))
+            .collect::<Resultado<BTreeMap<usize, usize>>>()?;
+        Ok(PagosConfirmados { rangos })
+    }
+}
+
+/// EncabezadoSnapshot contiene el estado del log que no corresponde a las
+/// transacciones pendientes: la generación del snapshot, el id máximo de
+/// transacción, los pagos confirmados y el hash del último registro.
+struct EncabezadoSnapshot {
+    generacion: u64,
+    id_maximo: IdTransaccion,
+    pagos: PagosConfirmados,
+    ultimo_hash: Hash,
+}
+
+impl EncabezadoSnapshot {
+    /// Convierte el encabezado a binario y lo devuelve: el prefijo del
+    /// encabezado, la generación, el id máximo, el hash del último registro
+    /// y los rangos de los pagos confirmados.
+    fn codificar(&self) -> Vec<u8> {
+        let mut bytes = ENCABEZADO_SNAPSHOT.to_vec();
+        bytes.extend_from_slice(&self.generacion.to_le_bytes());
+        bytes.extend_from_slice(&self.id_maximo.a_numero().to_le_bytes());
+        bytes.extend_from_slice(&self.ultimo_hash);
+        self.pagos.codificar(&mut bytes);
+        bytes
+    }
+
+    /// Convierte el registro de encabezado de un snapshot a
+    /// EncabezadoSnapshot y lo devuelve.
+    /// Devuelve error si el registro es inválido.
+    fn decodificar(bytes: &[u8]) -> Resultado<Self> {
+        let mut lector = LectorRegistro::new(bytes);
+        if lector.leer(ENCABEZADO_SNAPSHOT.len())? != ENCABEZADO_SNAPSHOT {
+            return Err(ErrorApp::Interno(ErrorInterno::new(
+                "Snapshot corrupto: encabezado invalido",
+            )));
+        }
+        let generacion = lector.u64()?;
+        let id_maximo = IdTransaccion::desde_numero(lector.u64()?);
+        let mut ultimo_hash = [0; TAM_HASH];
+        ultimo_hash.copy_from_slice(lector.leer(TAM_HASH)?);
+        let pagos = PagosConfirmados::decodificar(&mut lector)?;
+
+        Ok(EncabezadoSnapshot {
+            generacion,
+            id_maximo,
+            pagos,
+            ultimo_hash,
+        })
+    }
+}
+
+impl AlmacenamientoArchivo {
+    /// Devuelve una instancia de AlmacenamientoArchivo con la política de
+    /// durabilidad, el tamaño de segmento y la frecuencia de snapshots y de
+    /// checkpoints de la configuración. Los archivos se abren al usarse por
+    /// primera vez.
+    pub fn new(config: &Config) -> Self {
+        AlmacenamientoArchivo {
+            segmentos: None,
+            fallidos: None,
+            pendientes: HashMap::new(),
+            ultima_trans: None,
+            id_maximo: IdTransaccion::default(),
+            pagos_confirmados: PagosConfirmados::default(),
+            generacion: 0,
+            durabilidad: config.durabilidad,
+            tam_segmento: config.tam_segmento,
+            registros_por_snapshot: config.registros_por_snapshot,
+            registros_desde_snapshot: 0,
+            ultimo_hash: HASH_INICIAL,
+            checkpoints: None,
+            firmante: None,
+            ruta_clave: config.ruta_clave.clone(),
+            registros_por_checkpoint: config.registros_por_checkpoint,
+            registros_desde_checkpoint: 0,
+            epoca: 0,
+        }
+    }
+
+    /// Agrega la transacción al segmento activo, encadenada al último
+    /// registro, y escribe un checkpoint si hay una clave para firmarlo y se
+    /// alcanzó la cantidad de registros por checkpoint.
+    fn escribir(&mut self, transaccion: &Transaccion) -> Resultado<()> {
+        let registro = transaccion.codificar(&self.ultimo_hash);
+        self.segmentos()?.agregar(transaccion.id, &registro)?;
+        self.ultimo_hash = cadena::hash(&registro);
+        self.registros_desde_checkpoint += 1;
+
+        if self.firmante.is_some()
+            && self.registros_por_checkpoint > 0
+            && self.registros_desde_checkpoint >= self.registros_por_checkpoint
+        {
+            self.escribir_checkpoint(transaccion.id)?;
+        }
+        Ok(())
+    }
+
+    /// Escribe un checkpoint firmado del último registro, que debe ser el de
+    /// la transacción dada. Antes se sincronizan los segmentos, de forma que
+    /// un checkpoint nunca refiera a un registro que no llegó a disco.
+    fn escribir_checkpoint(&mut self, id_transaccion: IdTransaccion) -> Resultado<()> {
+        self.segmentos()?.sincronizar()?;
+        let (checkpoints, firmante) = match (&mut self.checkpoints, &self.firmante) {
+            (Some(c), Some(f)) => (c, f),
+            _ => {
+                return Err(ErrorApp::Interno(ErrorInterno::new(
+                    "El estado de las transacciones no fue recuperado",
+                )))
+            }
+        };
+        let checkpoint = firmante.firmar(id_transaccion, self.ultimo_hash);
+        checkpoints.escribir(checkpoint.codificar().as_bytes())?;
+        checkpoints.sincronizar()?;
+        self.registros_desde_checkpoint = 0;
+        Ok(())
+    }
+
+    /// Lee los registros binarios de los segmentos archivados y vigentes, en
+    /// orden.
+    fn leer_registros() -> Resultado<Vec<Vec<u8>>> {
+        let mut registros = LogSegmentado::leer(Path::new(&Config::ruta_archivo_logs()))?;
+        registros.append(&mut LogSegmentado::leer(Path::new(&Config::ruta_logs()))?);
+        Ok(registros)
+    }
+
+    /// Recorre la cadena de registros de los segmentos archivados y vigentes
+    /// y verifica los checkpoints firmados con la clave de la configuración,
+    /// sin modificar el log. Devuelve error si no hay una clave configurada o
+    /// no se pueden leer los registros o la clave.
+    pub fn verificar_cadena(config: &Config) -> Resultado<VerificacionCadena> {
+        let ruta_clave = config.ruta_clave.as_ref().ok_or_else(|| {
+            ErrorApp::Interno(ErrorInterno::new(
+                "No hay una clave configurada para verificar los checkpoints",
+            ))
+        })?;
+        let firmante = Firmante::cargar(ruta_clave)?;
+        let checkpoints = AlmacenamientoArchivo::leer_texto(Config::ruta_checkpoints())?
+            .iter()
+            .map(|c| Checkpoint::decodificar(c))
+            .collect::<Resultado<Vec<Checkpoint>>>()?;
+        cadena::verificar(
+            &AlmacenamientoArchivo::leer_registros()?,
+            &checkpoints,
+            &firmante,
+        )
+    }
+
+    /// Devuelve el log segmentado, o error si no se recuperó el estado.
+    fn segmentos(&mut self) -> Resultado<&mut LogSegmentado> {
+        self.segmentos.as_mut().ok_or_else(|| {
+            ErrorApp::Interno(ErrorInterno::new(
+                "El estado de las transacciones no fue recuperado",
+            ))
+        })
+    }
+
+    /// Devuelve el archivo de fallidos, abriéndolo si es necesario.
+    fn fallidos(&mut self) -> Resultado<&mut ParserFallidos> {
+        if self.fallidos.is_none() {
+            self.fallidos = Some(ParserFallidos::new()?);
+        }
+        Ok(self
+            .fallidos
+            .as_mut()
+            .expect("Archivo de fallidos no abierto"))
+    }
+
+    /// Migra a los segmentos los registros del archivo de logs en el formato
+    /// de texto anterior, si existe, y luego lo mueve al directorio de
+    /// archivo. Si la migración se interrumpe, se vuelve a realizar al
+    /// reiniciar, lo cual no altera el estado.
+    fn migrar_log_anterior(&mut self) -> Resultado<()> {
+        let ruta = Config::ruta_log_anterior();
+        if !Path::new(&ruta).exists() {
+            return Ok(());
+        }
+        let registros = AlmacenamientoArchivo::leer_texto(&ruta)?;
+        for registro in &registros {
+            let transaccion = AlmacenamientoArchivo::parsear_registro_anterior(registro)?;
+            self.escribir(&transaccion)?;
+            self.aplicar(transaccion)?;
+        }
+        self.segmentos()?.sincronizar()?;
+
+        fs::create_dir_all(Config::ruta_archivo_logs())?;
+        fs::rename(
+            &ruta,
+            Path::new(&Config::ruta_archivo_logs()).join("estado-anterior.log"),
+        )?;
+        println!(
+            "[Log]: Se migraron {} registros del log en formato anterior",
+            registros.len()
+        );
+        Ok(())
+    }
+
+    /// Lee los registros de texto del archivo de la ruta dada, que puede
+    /// estar en formato WAL o en texto plano.
+    fn leer_texto<P: AsRef<Path>>(ruta: P) -> Resultado<Vec<String>> {
+        Wal::leer(ruta)?
+            .into_iter()
+            .map(|r| Ok(String::from_utf8(r)?))
+            .collect()
+    }
+
+    /// Actualiza el estado en memoria con una transaccion del log. Solo el
+    /// registro del prepare incluye el pago, por lo que los registros
+    /// siguientes conservan el de la transacción pendiente, cuyo estado
+    /// continúan.
+    /// Devuelve error si el estado de la transacción pendiente no puede pasar
+    /// al del registro.
+    fn aplicar(&mut self, mut transaccion: Transaccion) -> Resultado<()> {
+        if let Some(pendiente) = self.pendientes.get(&transaccion.id) {
+            transaccion.continuar(pendiente).map_err(|e| {
+                ErrorApp::Interno(ErrorInterno::new(&format!("Log corrupto: {}", e)))
+            })?;
+            if transaccion.pago.is_none() {
+                transaccion.pago = pendiente.get_pago();
+            }
+            if transaccion.version_cotizaciones.is_none() {
+                transaccion.version_cotizaciones = pendiente.version_cotizaciones;
+            }
+            if transaccion.archivo.is_none() {
+                transaccion.archivo = pendiente.archivo.clone();
+            }
+        }
+        self.id_maximo = self.id_maximo.max(transaccion.id);
+        if let Some(sello) = &transaccion.sello {
+            self.epoca = self.epoca.max(sello.epoca);
+        }
+        if transaccion.estado() == EstadoTransaccion::Commit {
+            self.pagos_confirmados.insertar(transaccion.id_pago);
+        }
+        self.ultima_trans = Some(transaccion.clone());
+        if transaccion.estado() == EstadoTransaccion::Finalize {
+            self.pendientes.remove(&transaccion.id);
+        } else {
+            self.pendientes.insert(transaccion.id, transaccion);
+        }
+        Ok(())
+    }
+
+    /// Toma un snapshot del estado vivo del log y mueve los segmentos
+    /// cubiertos por el mismo al directorio de archivo. El snapshot indica el
+    /// último segmento que cubre, por lo que si el proceso se interrumpe
+    /// antes de archivarlos se archivan al recuperar el log. Los pagos
+    /// confirmados se conservan todos, para no volver a ejecutar un pago
+    /// cuyas transacciones ya fueron archivadas.
+    fn compactar(&mut self) -> Resultado<()> {
+        let generacion = self.segmentos()?.sellar()?;
+        let id_ultima = self.ultima_trans.as_ref().map(|t| t.id);
+        let encabezado = EncabezadoSnapshot {
+            generacion,
+            id_maximo: self.id_maximo,
+            pagos: self.pagos_confirmados.clone(),
+            ultimo_hash: self.ultimo_hash,
+        };
+
+        // Los registros del snapshot no forman parte de la cadena
+        let mut registros = vec![encabezado.codificar()];
+        registros.extend(
+            self.transacciones_pendientes()?
+                .iter()
+                .filter(|t| Some(t.id) != id_ultima)
+                .map(|t| t.codificar(&HASH_INICIAL)),
+        );
+        if let Some(ultima) = &self.ultima_trans {
+            registros.push(ultima.codificar(&HASH_INICIAL));
+        }
+        Wal::reescribir(Path::new(&Config::ruta_snapshot()), &registros)?;
+        self.segmentos()?.archivar(generacion)?;
+
+        self.generacion = generacion;
+        self.registros_desde_snapshot = 0;
+        println!(
+            "[Log]: Snapshot tomado con {} transacciones pendientes, segmentos hasta el {} archivados",
+            self.pendientes.len(),
+            generacion
+        );
+
+        Ok(())
+    }
+
+    /// Inicializa las variables internas a partir de los registros de un
+    /// snapshot. Un snapshot vacío corresponde a un log nunca compactado.
+    fn leer_snapshot(&mut self, registros: &[Vec<u8>]) -> Resultado<()> {
+        if let Some((encabezado, transacciones)) = registros.split_first() {
+            let encabezado = EncabezadoSnapshot::decodificar(encabezado)?;
+            self.generacion = encabezado.generacion;
+            self.id_maximo = encabezado.id_maximo;
+            self.pagos_confirmados = encabezado.pagos;
+            self.ultimo_hash = encabezado.ultimo_hash;
+            for registro in transacciones {
+                self.aplicar(Transaccion::decodificar(registro)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Recibe un registro del log en el formato de texto anterior,
+    /// `id,id_pago,id_pago_prox,ESTADO`, y devuelve la transaccion
+    /// correspondiente.
+    /// Devuelve error si el registro es invalido.
+    fn parsear_registro_anterior(registro: &str) -> Resultado<Transaccion> {
+        match registro.split(',').collect::<Vec<&str>>()[..] {
+            [id, id_pago, id_pago_prox, estado] => Ok(Transaccion::new(
+                IdTransaccion::desde_numero(id.parse::<u64>()?),
+                id_pago.parse::<usize>()?,
+                id_pago_prox.parse::<usize>()?,
+                EstadoTransaccion::decodificar(estado)?,
+            )),
+            _ => Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Log corrupto: registro invalido '{}'",
+                registro
+            )))),
+        }
+    }
+}
+
+impl Almacenamiento for AlmacenamientoArchivo {
+    /// Carga el último snapshot y aplica los registros de los segmentos
+    /// posteriores al mismo. Si existe un archivo de logs en un formato
+    /// anterior, sus registros se migran a los segmentos. Si se configuró una
+    /// clave para firmar checkpoints y el archivo no existe, se genera.
+    fn recuperar(&mut self) -> Resultado<()> {
+        let snapshot = Wal::leer(Config::ruta_snapshot())?;
+        let generacion = match snapshot.first() {
+            Some(encabezado) => EncabezadoSnapshot::decodificar(encabezado)?.generacion,
+            None => 0,
+        };
+        let (segmentos, registros) = LogSegmentado::abrir(
+            Path::new(&Config::ruta_logs()),
+            Path::new(&Config::ruta_archivo_logs()),
+            self.durabilidad,
+            self.tam_segmento,
+            generacion,
+        )?;
+        self.segmentos = Some(segmentos);
+        self.registros_desde_snapshot = registros.len();
+
+        self.leer_snapshot(&snapshot)?;
+        for registro in &registros {
+            self.aplicar(Transaccion::decodificar(registro)?)?;
+        }
+        if let Some(ultimo) = registros.last() {
+            self.ultimo_hash = cadena::hash(ultimo);
+        }
+        self.checkpoints =
+            Some(Wal::abrir(Path::new(&Config::ruta_checkpoints()), self.durabilidad)?.0);
+        self.firmante = match &self.ruta_clave {
+            Some(ruta) => Some(Firmante::cargar_o_crear(ruta)?),
+            None => None,
+        };
+        self.migrar_log_anterior()
+    }
+
+    /// Verifica el snapshot, los segmentos del log y el archivo de logs en
+    /// formato anterior, si existe.
+    fn verificar(&self) -> Resultado<()> {
+        let snapshot = Wal::leer(Config::ruta_snapshot())?;
+        if let Some((encabezado, transacciones)) = snapshot.split_first() {
+            EncabezadoSnapshot::decodificar(encabezado)?;
+            for registro in transacciones {
+                Transaccion::decodificar(registro)?;
+            }
+        }
+        for registro in AlmacenamientoArchivo::leer_texto(Config::ruta_log_anterior())? {
+            AlmacenamientoArchivo::parsear_registro_anterior(&registro)?;
+        }
+        for registro in LogSegmentado::leer(Path::new(&Config::ruta_logs()))? {
+            Transaccion::decodificar(&registro)?;
+        }
+        Ok(())
+    }
+
+    /// Agrega la transacción al segmento activo y compacta el log si se
+    /// alcanzó la cantidad de registros por snapshot.
+    fn insertar(&mut self, transaccion: &Transaccion) -> Resultado<()> {
+        self.escribir(transaccion)?;
+        self.aplicar(transaccion.clone())?;
+        self.registros_desde_snapshot += 1;
+
+        if self.registros_por_snapshot > 0
+            && self.registros_desde_snapshot >= self.registros_por_snapshot
+        {
+            if let Err(e) = self.compactar() {
+                println!("[Log]: No se pudo compactar el log: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Las transacciones finalizadas se buscan en los segmentos del log.
+    fn obtener(&self, id: IdTransaccion) -> Resultado<Option<Transaccion>> {
+        if id > self.id_maximo {
+            return Ok(None);
+        }
+        if let Some(t) = self.pendientes.get(&id) {
+            return Ok(Some(t.clone()));
+        }
+        if let Some(t) = self.ultima_trans.as_ref().filter(|t| t.id == id) {
+            return Ok(Some(t.clone()));
+        }
+        match &self.segmentos {
+            Some(segmentos) => segmentos
+                .buscar(id)?
+                .map(|r| Transaccion::decodificar(&r))
+                .transpose(),
+            None => Ok(None),
+        }
+    }
+
+    fn transacciones_pendientes(&self) -> Resultado<Vec<Transaccion>> {
+        let mut pendientes = self
+            .pendientes
+            .values()
+            .cloned()
+            .collect::<Vec<Transaccion>>();
+        pendientes.sort_by_key(|t| t.id);
+        Ok(pendientes)
+    }
+
+    fn ultima_transaccion(&self) -> Resultado<Option<Transaccion>> {
+        Ok(self.ultima_trans.clone())
+    }
+
+    fn id_maximo(&self) -> Resultado<IdTransaccion> {
+        Ok(self.id_maximo)
+    }
+
+    fn pago_confirmado(&self, id_pago: usize) -> Resultado<bool> {
+        Ok(self.pagos_confirmados.contiene(id_pago))
+    }
+
+    /// Lee los registros del archivo de logs en formato anterior, si no fue
+    /// migrado, y los de los segmentos archivados y vigentes.
+    fn historial(&self) -> Resultado<Vec<Transaccion>> {
+        let mut transacciones = AlmacenamientoArchivo::leer_texto(Config::ruta_log_anterior())?
+            .iter()
+            .map(|r| AlmacenamientoArchivo::parsear_registro_anterior(r))
+            .collect::<Resultado<Vec<Transaccion>>>()?;
+        for registro in AlmacenamientoArchivo::leer_registros()? {
+            transacciones.push(Transaccion::decodificar(&registro)?);
+        }
+        Ok(transacciones)
+    }
+
+    fn epoca_maxima(&self) -> Resultado<u32> {
+        let persistida = match AlmacenamientoArchivo::leer_texto(Config::ruta_epoca())?.last() {
+            Some(epoca) => epoca.parse::<u32>()?,
+            None => 0,
+        };
+        Ok(self.epoca.max(persistida))
+    }
+
+    /// La época se persiste en un archivo aparte, ya que un líder puede no
+    /// llegar a escribir registros en su época.
+    fn registrar_epoca(&mut self, epoca: u32) -> Resultado<()> {
+        Wal::reescribir(
+            Path::new(&Config::ruta_epoca()),
+            &[epoca.to_string().into_bytes()],
+        )?;
+        self.epoca = self.epoca.max(epoca);
+        Ok(())
+    }
+
+    fn sincronizar(&mut self) -> Resultado<()> {
+        self.segmentos()?.sincronizar()
+    }
+
+    fn escribir_fallido(&mut self, pago: &Pago, motivo: Option<&MotivoAborto>) -> Resultado<()> {
+        self.fallidos()?.escribir_fallido(pago.clone(), motivo);
+        Ok(())
+    }
+
+    fn tomar_fallido(&mut self, id_pago: usize) -> Resultado<Option<Pago>> {
+        self.fallidos()?.parsear(id_pago)
+    }
+
+    fn listar_fallidos(&self) -> Resultado<Vec<(Pago, Option<MotivoAborto>)>> {
+        ParserFallidos::listar()
+    }
+}
@@ -0,0 +1,126 @@
+use common::error::Resultado;
+use common::id_transaccion::IdTransaccion;
+use common::motivo_aborto::MotivoAborto;
+use std::collections::{HashMap, HashSet};
+
+use super::almacenamiento::Almacenamiento;
+use super::estado_transaccion::EstadoTransaccion;
+use super::log::Transaccion;
+use super::pago::Pago;
+
+/// AlmacenamientoMemoria mantiene el estado de las transacciones y los pagos
+/// fallidos en memoria, sin persistirlos. Está pensado para pruebas, ya que
+/// el estado se pierde al finalizar el proceso o cambiar de líder.
+#[derive(Default)]
+pub struct AlmacenamientoMemoria {
+    transacciones: HashMap<IdTransaccion, Transaccion>,
+    ultima_trans: Option<Transaccion>,
+    pagos_confirmados: HashSet<usize>,
+    fallidos: Vec<(Pago, Option<MotivoAborto>)>,
+    epoca: u32,
+}
+
+impl AlmacenamientoMemoria {
+    /// Devuelve una instancia de AlmacenamientoMemoria vacía.
+    pub fn new() -> Self {
+        AlmacenamientoMemoria::default()
+    }
+}
+
+impl Almacenamiento for AlmacenamientoMemoria {
+    fn recuperar(&mut self) -> Resultado<()> {
+        Ok(())
+    }
+
+    fn verificar(&self) -> Resultado<()> {
+        Ok(())
+    }
+
+    /// Si la transacción no incluye el pago, se conserva el de su estado
+    /// anterior.
+    fn insertar(&mut self, transaccion: &Transaccion) -> Resultado<()> {
+        let mut transaccion = transaccion.clone();
+        if transaccion.pago.is_none() {
+            transaccion.pago = self
+                .transacciones
+                .get(&transaccion.id)
+                .and_then(|t| t.get_pago());
+        }
+        if transaccion.estado() == EstadoTransaccion::Commit {
+            self.pagos_confirmados.insert(transaccion.id_pago);
+        }
+        self.ultima_trans = Some(transaccion.clone());
+        self.transacciones.insert(transaccion.id, transaccion);
+        Ok(())
+    }
+
+    fn obtener(&self, id: IdTransaccion) -> Resultado<Option<Transaccion>> {
+        Ok(self.transacciones.get(&id).cloned())
+    }
+
+    fn transacciones_pendientes(&self) -> Resultado<Vec<Transaccion>> {
+        let mut pendientes = self
+            .transacciones
+            .values()
+            .filter(|t| t.estado() != EstadoTransaccion::Finalize)
+            .cloned()
+            .collect::<Vec<Transaccion>>();
+        pendientes.sort_by_key(|t| t.id);
+        Ok(pendientes)
+    }
+
+    fn ultima_transaccion(&self) -> Resultado<Option<Transaccion>> {
+        Ok(self.ultima_trans.clone())
+    }
+
+    fn id_maximo(&self) -> Resultado<IdTransaccion> {
+        Ok(self.transacciones.keys().max().copied().unwrap_or_default())
+    }
+
+    fn pago_confirmado(&self, id_pago: usize) -> Resultado<bool> {
+        Ok(self.pagos_confirmados.contains(&id_pago))
+    }
+
+    fn historial(&self) -> Resultado<Vec<Transaccion>> {
+        let mut transacciones = self
+            .transacciones
+            .values()
+            .cloned()
+            .collect::<Vec<Transaccion>>();
+        transacciones.sort_by_key(|t| t.id);
+        Ok(transacciones)
+    }
+
+    fn epoca_maxima(&self) -> Resultado<u32> {
+        Ok(self.epoca)
+    }
+
+    fn registrar_epoca(&mut self, epoca: u32) -> Resultado<()> {
+        self.epoca = epoca;
+        Ok(())
+    }
+
+    fn sincronizar(&mut self) -> Resultado<()> {
+        Ok(())
+    }
+
+    fn escribir_fallido(&mut self, pago: &Pago, motivo: Option<&MotivoAborto>) -> Resultado<()> {
+        self.fallidos.push((pago.clone(), motivo.cloned()));
+        Ok(())
+    }
+
+    fn tomar_fallido(&mut self, id_pago: usize) -> Resultado<Option<Pago>> {
+        let pago = self
+            .fallidos
+            .iter()
+            .rev()
+            .find(|(p, _)| p.get_id() == id_pago)
+            .map(|(p, _)| p.clone());
+        self.fallidos.retain(|(p, _)| p.get_id() != id_pago);
+        Ok(pago)
+    }
+
+    fn listar_fallidos(&self) -> Resultado<Vec<(Pago, Option<MotivoAborto>)>> {
+        Ok(self.fallidos.clone())
+    }
+}
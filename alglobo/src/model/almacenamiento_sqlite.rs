@@ -0,0 +1,550 @@
+use common::campos;
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::id_transaccion::IdTransaccion;
+use common::monto::{Moneda, Monto};
+use common::motivo_aborto::{CausaAborto, MotivoAborto};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::almacenamiento::Almacenamiento;
+use super::config::Config;
+use super::estado_transaccion::EstadoTransaccion;
+use super::log::{Sello, Transaccion};
+use super::pago::Pago;
+use super::wal::PoliticaDurabilidad;
+
+/// Columnas de la tabla de transacciones, en el orden que espera
+/// `leer_transaccion`.
+const COLUMNAS_TRANSACCION: &str = "id, id_pago, id_pago_prox, estado, participantes, \
+    creacion, deadline, centavos_aerolinea, moneda_aerolinea, centavos_hotel, moneda_hotel, \
+    escritura, desde_prepare, nodo, epoca, offset_prox, version_cotizaciones, datos, archivo, \
+    causa, descripcion, lote";
+
+/// Columnas de la tabla de pagos fallidos, en el orden que espera
+/// `leer_fallido`.
+const COLUMNAS_FALLIDO: &str = "id_pago, centavos_aerolinea, moneda_aerolinea, centavos_hotel, \
+    moneda_hotel, causa, descripcion, datos";
+
+/// AlmacenamientoSqlite persiste el estado de las transacciones y los pagos
+/// fallidos en una base de datos SQLite embebida. Cada inserción se realiza
+/// dentro de una transacción de la base de datos. Los montos se guardan en
+/// centavos junto a su moneda, y los datos de la reserva de los pagos
+/// codificados en la columna `datos`.
+pub struct AlmacenamientoSqlite {
+    conexion: Mutex<Connection>,
+}
+
+impl AlmacenamientoSqlite {
+    /// Abre la base de datos de la configuración, creando las tablas si no
+    /// existen. La política de durabilidad se traduce al modo de
+    /// sincronización de SQLite.
+    pub fn new(config: &Config) -> Resultado<Self> {
+        let conexion = Connection::open(Config::ruta_sqlite()).map_err(error_sqlite)?;
+        let sincronizacion = match config.durabilidad {
+            PoliticaDurabilidad::PorRegistro => "FULL",
+            PoliticaDurabilidad::Grupal => "NORMAL",
+            PoliticaDurabilidad::Ninguna => "OFF",
+        };
+        conexion
+            .execute_batch(&format!(
+                "PRAGMA journal_mode = WAL;
+                PRAGMA synchronous = {};
+                CREATE TABLE IF NOT EXISTS transacciones (
+                    id INTEGER PRIMARY KEY,
+                    id_pago INTEGER NOT NULL,
+                    id_pago_prox INTEGER NOT NULL,
+                    estado TEXT NOT NULL,
+                    participantes TEXT NOT NULL,
+                    creacion INTEGER NOT NULL,
+                    deadline INTEGER NOT NULL,
+                    secuencia INTEGER NOT NULL,
+                    escritura INTEGER,
+                    desde_prepare INTEGER,
+                    nodo INTEGER,
+                    epoca INTEGER,
+                    offset_prox INTEGER,
+                    centavos_aerolinea INTEGER,
+                    moneda_aerolinea TEXT,
+                    centavos_hotel INTEGER,
+                    moneda_hotel TEXT,
+                    version_cotizaciones INTEGER,
+                    datos TEXT,
+                    archivo TEXT,
+                    causa TEXT,
+                    descripcion TEXT,
+                    lote INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS transacciones_estado ON transacciones (estado);
+                CREATE INDEX IF NOT EXISTS transacciones_secuencia ON transacciones (secuencia);
+                CREATE TABLE IF NOT EXISTS pagos_confirmados (
+                    id_pago INTEGER PRIMARY KEY
+                );
+                CREATE TABLE IF NOT EXISTS fallidos (
+                    orden INTEGER PRIMARY KEY AUTOINCREMENT,
+                    id_pago INTEGER NOT NULL,
+                    centavos_aerolinea INTEGER NOT NULL,
+                    moneda_aerolinea TEXT NOT NULL,
+                    centavos_hotel INTEGER NOT NULL,
+                    moneda_hotel TEXT NOT NULL,
+                    causa TEXT,
+                    descripcion TEXT,
+                    datos TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS epoca (
+                    id INTEGER PRIMARY KEY CHECK (id = 0),
+                    valor INTEGER NOT NULL
+                );",
+                sincronizacion
+            ))
+            .map_err(error_sqlite)?;
+
+        Ok(AlmacenamientoSqlite {
+            conexion: Mutex::new(conexion),
+        })
+    }
+
+    /// Abre la base de datos de la configuración en modo de solo lectura,
+    /// sin crear las tablas ni cambiar su configuración, para inspeccionarla
+    /// mientras un nodo la usa. Devuelve error si la base de datos no existe.
+    pub fn abrir_lectura() -> Resultado<Self> {
+        let ruta = Config::ruta_sqlite();
+        if !Path::new(&ruta).exists() {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "No existe la base de datos {}",
+                ruta
+            ))));
+        }
+        let conexion = Connection::open_with_flags(&ruta, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(error_sqlite)?;
+        Ok(AlmacenamientoSqlite {
+            conexion: Mutex::new(conexion),
+        })
+    }
+
+    /// Devuelve la conexión a la base de datos.
+    fn conexion(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conexion
+            .lock()
+            .expect("Error al tomar lock de la conexion a SQLite")
+    }
+
+    /// Consulta las transacciones que cumplen la condición dada.
+    fn consultar(&self, condicion: &str) -> Resultado<Vec<Transaccion>> {
+        let conexion = self.conexion();
+        let mut consulta = conexion
+            .prepare(&format!(
+                "SELECT {} FROM transacciones {}",
+                COLUMNAS_TRANSACCION, condicion
+            ))
+            .map_err(error_sqlite)?;
+        let filas = consulta
+            .query_map([], leer_transaccion)
+            .map_err(error_sqlite)?;
+        filas
+            .map(|t| t.map_err(error_sqlite)?)
+            .collect::<Resultado<Vec<Transaccion>>>()
+    }
+}
+
+impl Almacenamiento for AlmacenamientoSqlite {
+    /// SQLite recupera por sí misma las transacciones de la base de datos
+    /// interrumpidas por una caída.
+    fn recuperar(&mut self) -> Resultado<()> {
+        Ok(())
+    }
+
+    fn verificar(&self) -> Resultado<()> {
+        let resultado: String = self
+            .conexion()
+            .query_row("PRAGMA quick_check", [], |fila| fila.get(0))
+            .map_err(error_sqlite)?;
+        if resultado != "ok" {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Base de datos corrupta: {}",
+                resultado
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Si la transacción no incluye el pago, se conservan los montos de su
+    /// estado anterior.
+    fn insertar(&mut self, transaccion: &Transaccion) -> Resultado<()> {
+        let mut conexion = self.conexion();
+        let tx = conexion.transaction().map_err(error_sqlite)?;
+        tx.execute(
+            "INSERT INTO transacciones
+                (id, id_pago, id_pago_prox, estado, participantes, creacion, deadline,
+                centavos_aerolinea, moneda_aerolinea, centavos_hotel, moneda_hotel, escritura,
+                desde_prepare, nodo, epoca, offset_prox, version_cotizaciones, datos, archivo,
+                causa, descripcion, lote, secuencia)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                ?18, ?19, ?20, ?21, ?22, (SELECT IFNULL(MAX(secuencia), 0) + 1 FROM transacciones))
+            ON CONFLICT (id) DO UPDATE SET
+                estado = excluded.estado,
+                participantes = excluded.participantes,
+                centavos_aerolinea = IFNULL(excluded.centavos_aerolinea, centavos_aerolinea),
+                moneda_aerolinea = IFNULL(excluded.moneda_aerolinea, moneda_aerolinea),
+                centavos_hotel = IFNULL(excluded.centavos_hotel, centavos_hotel),
+                moneda_hotel = IFNULL(excluded.moneda_hotel, moneda_hotel),
+                escritura = excluded.escritura,
+                desde_prepare = excluded.desde_prepare,
+                nodo = excluded.nodo,
+                epoca = excluded.epoca,
+                offset_prox = IFNULL(excluded.offset_prox, offset_prox),
+                version_cotizaciones = IFNULL(excluded.version_cotizaciones, version_cotizaciones),
+                datos = IFNULL(excluded.datos, datos),
+                archivo = IFNULL(excluded.archivo, archivo),
+                causa = IFNULL(excluded.causa, causa),
+                descripcion = IFNULL(excluded.descripcion, descripcion),
+                lote = excluded.lote,
+                secuencia = excluded.secuencia",
+            params![
+                transaccion.id.a_numero() as i64,
+                transaccion.id_pago as i64,
+                transaccion.id_pago_prox as i64,
+                transaccion.estado().codificar(),
+                transaccion
+                    .participantes
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<String>>()
+                    .join(";"),
+                transaccion.creacion as i64,
+                transaccion.deadline as i64,
+                transaccion
+                    .pago
+                    .as_ref()
+                    .map(|p| p.get_monto_aerolinea().centavos() as i64),
+                transaccion
+                    .pago
+                    .as_ref()
+                    .map(|p| p.get_monto_aerolinea().moneda().codigo().to_string()),
+                transaccion
+                    .pago
+                    .as_ref()
+                    .map(|p| p.get_monto_hotel().centavos() as i64),
+                transaccion
+                    .pago
+                    .as_ref()
+                    .map(|p| p.get_monto_hotel().moneda().codigo().to_string()),
+                transaccion.sello.map(|s| s.escritura as i64),
+                transaccion.sello.map(|s| s.desde_prepare as i64),
+                transaccion.sello.map(|s| s.nodo as i64),
+                transaccion.sello.map(|s| s.epoca as i64),
+                transaccion.offset_prox.map(|o| o as i64),
+                transaccion.version_cotizaciones.map(|v| v as i64),
+                transaccion
+                    .pago
+                    .as_ref()
+                    .map(|p| campos::codificar(&p.get_datos())),
+                transaccion.archivo,
+                transaccion
+                    .motivo_aborto
+                    .as_ref()
+                    .map(|m| m.causa.codificar()),
+                transaccion
+                    .motivo_aborto
+                    .as_ref()
+                    .map(|m| m.descripcion.clone()),
+                transaccion.lote as i64,
+            ],
+        )
+        .map_err(error_sqlite)?;
+        if transaccion.estado() == EstadoTransaccion::Commit {
+            tx.execute(
+                "INSERT OR IGNORE INTO pagos_confirmados (id_pago) VALUES (?1)",
+                params![transaccion.id_pago as i64],
+            )
+            .map_err(error_sqlite)?;
+        }
+        tx.commit().map_err(error_sqlite)
+    }
+
+    fn obtener(&self, id: IdTransaccion) -> Resultado<Option<Transaccion>> {
+        Ok(self
+            .consultar(&format!("WHERE id = {}", id.a_numero()))?
+            .pop())
+    }
+
+    fn transacciones_pendientes(&self) -> Resultado<Vec<Transaccion>> {
+        self.consultar("WHERE estado != 'FINALIZE' ORDER BY id")
+    }
+
+    fn ultima_transaccion(&self) -> Resultado<Option<Transaccion>> {
+        Ok(self.consultar("ORDER BY secuencia DESC LIMIT 1")?.pop())
+    }
+
+    fn id_maximo(&self) -> Resultado<IdTransaccion> {
+        let id: i64 = self
+            .conexion()
+            .query_row("SELECT IFNULL(MAX(id), 0) FROM transacciones", [], |fila| {
+                fila.get(0)
+            })
+            .map_err(error_sqlite)?;
+        Ok(IdTransaccion::desde_numero(id as u64))
+    }
+
+    fn pago_confirmado(&self, id_pago: usize) -> Resultado<bool> {
+        Ok(self
+            .conexion()
+            .query_row(
+                "SELECT 1 FROM pagos_confirmados WHERE id_pago = ?1",
+                params![id_pago as i64],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(error_sqlite)?
+            .is_some())
+    }
+
+    /// Solo se conserva el último estado de cada transacción.
+    fn historial(&self) -> Resultado<Vec<Transaccion>> {
+        self.consultar("ORDER BY id")
+    }
+
+    fn epoca_maxima(&self) -> Resultado<u32> {
+        let epoca: i64 = self
+            .conexion()
+            .query_row(
+                "SELECT MAX(
+                    IFNULL((SELECT valor FROM epoca WHERE id = 0), 0),
+                    IFNULL((SELECT MAX(epoca) FROM transacciones), 0)
+                )",
+                [],
+                |fila| fila.get(0),
+            )
+            .map_err(error_sqlite)?;
+        Ok(epoca as u32)
+    }
+
+    fn registrar_epoca(&mut self, epoca: u32) -> Resultado<()> {
+        self.conexion()
+            .execute(
+                "INSERT OR REPLACE INTO epoca (id, valor) VALUES (0, ?1)",
+                params![epoca as i64],
+            )
+            .map_err(error_sqlite)?;
+        Ok(())
+    }
+
+    /// Cada inserción ya se sincroniza al commitear su transacción según el
+    /// modo de sincronización de la base de datos.
+    fn sincronizar(&mut self) -> Resultado<()> {
+        Ok(())
+    }
+
+    fn escribir_fallido(&mut self, pago: &Pago, motivo: Option<&MotivoAborto>) -> Resultado<()> {
+        let (aerolinea, hotel) = (pago.get_monto_aerolinea(), pago.get_monto_hotel());
+        self.conexion()
+            .execute(
+                "INSERT INTO fallidos (id_pago, centavos_aerolinea, moneda_aerolinea,
+                    centavos_hotel, moneda_hotel, causa, descripcion, datos)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    pago.get_id() as i64,
+                    aerolinea.centavos() as i64,
+                    aerolinea.moneda().codigo(),
+                    hotel.centavos() as i64,
+                    hotel.moneda().codigo(),
+                    motivo.map(|m| m.causa.codificar()),
+                    motivo.map(|m| m.descripcion.clone()),
+                    campos::codificar(&pago.get_datos()),
+                ],
+            )
+            .map_err(error_sqlite)?;
+        Ok(())
+    }
+
+    fn tomar_fallido(&mut self, id_pago: usize) -> Resultado<Option<Pago>> {
+        let mut conexion = self.conexion();
+        let tx = conexion.transaction().map_err(error_sqlite)?;
+        let pago = tx
+            .query_row(
+                &format!(
+                    "SELECT {} FROM fallidos WHERE id_pago = ?1 ORDER BY orden DESC LIMIT 1",
+                    COLUMNAS_FALLIDO
+                ),
+                params![id_pago as i64],
+                leer_fallido,
+            )
+            .optional()
+            .map_err(error_sqlite)?
+            .transpose()?
+            .map(|(pago, _)| pago);
+        tx.execute(
+            "DELETE FROM fallidos WHERE id_pago = ?1",
+            params![id_pago as i64],
+        )
+        .map_err(error_sqlite)?;
+        tx.commit().map_err(error_sqlite)?;
+        Ok(pago)
+    }
+
+    fn listar_fallidos(&self) -> Resultado<Vec<(Pago, Option<MotivoAborto>)>> {
+        let conexion = self.conexion();
+        let mut consulta = conexion
+            .prepare(&format!(
+                "SELECT {} FROM fallidos ORDER BY orden",
+                COLUMNAS_FALLIDO
+            ))
+            .map_err(error_sqlite)?;
+        let filas = consulta
+            .query_map([], leer_fallido)
+            .map_err(error_sqlite)?;
+        filas
+            .map(|f| f.map_err(error_sqlite)?)
+            .collect::<Resultado<Vec<(Pago, Option<MotivoAborto>)>>>()
+    }
+}
+
+/// Convierte un error de SQLite a ErrorApp.
+fn error_sqlite(error: rusqlite::Error) -> ErrorApp {
+    ErrorApp::Interno(ErrorInterno::new(&format!("Error de SQLite: {}", error)))
+}
+
+/// Lee una transaccion de una fila con las columnas `COLUMNAS_TRANSACCION`.
+fn leer_transaccion(fila: &Row) -> rusqlite::Result<Resultado<Transaccion>> {
+    let estado = match EstadoTransaccion::decodificar(&fila.get::<_, String>(3)?) {
+        Ok(e) => e,
+        Err(e) => return Ok(Err(e)),
+    };
+    let participantes = match fila
+        .get::<_, String>(4)?
+        .split(';')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()
+    {
+        Ok(p) => p,
+        Err(e) => return Ok(Err(e.into())),
+    };
+
+    let mut transaccion = Transaccion::new(
+        IdTransaccion::desde_numero(fila.get::<_, i64>(0)? as u64),
+        fila.get::<_, i64>(1)? as usize,
+        fila.get::<_, i64>(2)? as usize,
+        estado,
+    );
+    transaccion.participantes = participantes;
+    transaccion.restaurar_creacion(fila.get::<_, i64>(5)? as u64);
+    transaccion.deadline = fila.get::<_, i64>(6)? as u64;
+    match (leer_monto(fila, 7, 8)?, leer_monto(fila, 9, 10)?) {
+        (Some(Ok(monto_aerolinea)), Some(Ok(monto_hotel))) => {
+            let pago = Pago::new(transaccion.id_pago, monto_aerolinea, monto_hotel);
+            match leer_datos(fila, 17, pago)? {
+                Ok(pago) => transaccion.pago = Some(pago),
+                Err(e) => return Ok(Err(e)),
+            }
+        }
+        (Some(Err(e)), _) | (_, Some(Err(e))) => return Ok(Err(e)),
+        _ => {}
+    }
+    if let (Some(escritura), Some(desde_prepare), Some(nodo), Some(epoca)) = (
+        fila.get::<_, Option<i64>>(11)?,
+        fila.get::<_, Option<i64>>(12)?,
+        fila.get::<_, Option<i64>>(13)?,
+        fila.get::<_, Option<i64>>(14)?,
+    ) {
+        transaccion.sello = Some(Sello {
+            escritura: escritura as u64,
+            desde_prepare: desde_prepare as u64,
+            nodo: nodo as usize,
+            epoca: epoca as u32,
+        });
+    }
+    transaccion.offset_prox = fila.get::<_, Option<i64>>(15)?.map(|o| o as u64);
+    transaccion.version_cotizaciones = fila.get::<_, Option<i64>>(16)?.map(|v| v as u32);
+    transaccion.archivo = fila.get::<_, Option<String>>(18)?;
+    transaccion.lote = fila.get::<_, i64>(21)? as u32;
+    transaccion.motivo_aborto = match leer_motivo(fila, 19, 20)? {
+        Ok(motivo) => motivo,
+        Err(e) => return Ok(Err(e)),
+    };
+    Ok(Ok(transaccion))
+}
+
+/// Lee un pago fallido y el motivo por el cual falló, si se conoce, de una
+/// fila con las columnas `COLUMNAS_FALLIDO`.
+fn leer_fallido(fila: &Row) -> rusqlite::Result<Resultado<(Pago, Option<MotivoAborto>)>> {
+    let montos = match (leer_monto(fila, 1, 2)?, leer_monto(fila, 3, 4)?) {
+        (Some(Ok(aerolinea)), Some(Ok(hotel))) => (aerolinea, hotel),
+        (Some(Err(e)), _) | (_, Some(Err(e))) => return Ok(Err(e)),
+        _ => {
+            return Ok(Err(ErrorApp::Interno(ErrorInterno::new(
+                "Pago fallido sin montos",
+            ))))
+        }
+    };
+    let pago = match leer_datos(
+        fila,
+        7,
+        Pago::new(fila.get::<_, i64>(0)? as usize, montos.0, montos.1),
+    )? {
+        Ok(pago) => pago,
+        Err(e) => return Ok(Err(e)),
+    };
+    let motivo = match leer_motivo(fila, 5, 6)? {
+        Ok(motivo) => motivo,
+        Err(e) => return Ok(Err(e)),
+    };
+    Ok(Ok((pago, motivo)))
+}
+
+/// Lee el motivo de un abort de una fila a partir de las columnas dadas de
+/// causa y de descripción, si la causa no está vacía.
+fn leer_motivo(
+    fila: &Row,
+    causa: usize,
+    descripcion: usize,
+) -> rusqlite::Result<Resultado<Option<MotivoAborto>>> {
+    let causa = match fila.get::<_, Option<String>>(causa)? {
+        Some(causa) => match CausaAborto::decodificar(&causa) {
+            Ok(causa) => causa,
+            Err(e) => return Ok(Err(e)),
+        },
+        None => return Ok(Ok(None)),
+    };
+    let descripcion = fila.get::<_, Option<String>>(descripcion)?;
+    Ok(Ok(Some(MotivoAborto::new(
+        causa,
+        &descripcion.unwrap_or_default(),
+    ))))
+}
+
+/// Agrega al pago dado los datos de la reserva de la columna dada de una
+/// fila, si los tiene, y lo devuelve.
+fn leer_datos(fila: &Row, columna: usize, pago: Pago) -> rusqlite::Result<Resultado<Pago>> {
+    let datos = match fila.get::<_, Option<String>>(columna)? {
+        Some(datos) => match campos::decodificar(&datos) {
+            Ok(datos) => datos,
+            Err(e) => return Ok(Err(e)),
+        },
+        None => return Ok(Ok(pago)),
+    };
+    Ok(pago.con_datos(datos).map_err(|e| {
+        ErrorApp::Interno(ErrorInterno::new(&format!(
+            "Base de datos corrupta: datos de reserva invalidos: {}",
+            e
+        )))
+    }))
+}
+
+/// Lee un monto de una fila a partir de las columnas dadas de centavos y de
+/// moneda, si no están vacías.
+fn leer_monto(
+    fila: &Row,
+    centavos: usize,
+    moneda: usize,
+) -> rusqlite::Result<Option<Resultado<Monto>>> {
+    Ok(match (
+        fila.get::<_, Option<i64>>(centavos)?,
+        fila.get::<_, Option<String>>(moneda)?,
+    ) {
+        (Some(centavos), Some(moneda)) => {
+            Some(Moneda::decodificar(&moneda).map(|m| Monto::new(centavos as u64, m)))
+        }
+        _ => None,
+    })
+}
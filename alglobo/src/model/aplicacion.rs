@@ -1,25 +1,101 @@
 use std::sync::{Arc, RwLock};
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use super::coordinador_transaccion::CoordinadorTransaccion;
+use super::bandeja::Bandeja;
+use super::coordinador_transaccion::{CoordinadorTransaccion, ResultadoTransaccion};
 use super::parser::Parser;
 use super::{
-    comando::Comando,
+    comando::{Comando, PedidoPago},
+    config::Config,
     eleccion_lider::EleccionLider,
     log::{Log, Transaccion},
-    parser_fallidos::ParserFallidos,
+    pago::Pago,
 };
 use common::error::Resultado;
-use std::sync::mpsc::Receiver;
+use common::motivo_aborto::{CausaAborto, MotivoAborto};
+use common::protocolo_pagos::MensajePago;
+use common::reloj::Reloj;
+use std::sync::mpsc::{Receiver, Sender};
 
 /// Aplicacion implementa el flujo principal de un nodo lider de alglobo.
 pub struct Aplicacion {
     handle: JoinHandle<()>,
 }
 
+/// Tiempo de espera entre revisiones de la bandeja de entrada cuando no
+/// tiene archivos pendientes
+const ESPERA_BANDEJA: Duration = Duration::from_millis(500);
+
+/// Bit que marca el id de un pago recibido por socket, `BIT_PAGO_SOCKET |
+/// IdTransaccion`, que así nunca coincide con el de un pago de un archivo de
+/// entrada, `lote * LIMITE_ID_PAGO + id`, con el id en el archivo menor a
+/// [`LIMITE_ID_PAGO`](super::formato_entrada::LIMITE_ID_PAGO) (2^32) y el
+/// lote menor a 2^31.
+const BIT_PAGO_SOCKET: usize = 1 << 63;
+
+/// Motivo por el cual se rechaza un pago que ya fue confirmado por otra
+/// transacción.
+const MOTIVO_DUPLICADO: &str = "el pago ya fue confirmado por otra transaccion";
+
+/// Entrada representa el origen de los pagos a procesar.
+/// # Variantes
+/// Archivo: un único archivo de entrada, a cuyo fin se pasa a atender solo
+/// los reintentos de fallidos
+/// Bandeja: un directorio del que se procesan los archivos que van llegando
+pub enum Entrada {
+    Archivo(Parser),
+    Bandeja(Bandeja),
+}
+
+impl Entrada {
+    /// Devuelve el parser del archivo del que se leen los pagos, si hay uno.
+    fn parser(&mut self) -> Option<&mut Parser> {
+        match self {
+            Entrada::Archivo(parser) => Some(parser),
+            Entrada::Bandeja(bandeja) => bandeja.parser(),
+        }
+    }
+
+    /// Devuelve el nombre del archivo de la bandeja que se está procesando,
+    /// que se registra en las transacciones junto al offset del pago
+    /// siguiente.
+    fn archivo(&self) -> Option<String> {
+        match self {
+            Entrada::Archivo(_) => None,
+            Entrada::Bandeja(bandeja) => bandeja.archivo_actual(),
+        }
+    }
+
+    /// Devuelve el lote del último archivo abierto de la bandeja, que se
+    /// registra en las transacciones para asignar el de los siguientes. Un
+    /// único archivo de entrada no tiene lote.
+    fn lote(&self) -> u32 {
+        match self {
+            Entrada::Archivo(_) => 0,
+            Entrada::Bandeja(bandeja) => bandeja.lote(),
+        }
+    }
+
+    /// Retoma la lectura desde el pago siguiente a la última transacción del
+    /// log, en el archivo y el lote de la bandeja que registra.
+    fn reanudar(&mut self, ultima: &Transaccion) -> Resultado<()> {
+        match self {
+            Entrada::Archivo(parser) => parser.reanudar(ultima.id_pago_prox, ultima.offset_prox),
+            Entrada::Bandeja(bandeja) => bandeja.reanudar(
+                ultima.archivo.as_deref(),
+                ultima.lote,
+                ultima.id_pago_prox,
+                ultima.offset_prox,
+            ),
+        }
+    }
+}
+
 /// EstadoApp representa el estado de la aplicacion.
 /// # Variantes
-/// FinEntrada: simboliza el fin del archivo de entrada
+/// FinEntrada: simboliza el fin del archivo de entrada. Una bandeja de
+/// entrada no tiene fin
 /// CambioLider: simboliza un cambio de lider
 /// Finalizar: simboliza la finalizacion del proceso
 pub enum EstadoApp {
@@ -33,11 +109,14 @@ impl Aplicacion {
     pub fn new(
         id: usize,
         lider: EleccionLider,
-        parseador: Parser,
+        entrada: Entrada,
         receptor: Receiver<Comando>,
+        config: Config,
     ) -> Resultado<Aplicacion> {
         Ok(Aplicacion {
-            handle: thread::spawn(move || Aplicacion::procesar(id, lider, parseador, receptor)),
+            handle: thread::spawn(move || {
+                Aplicacion::procesar(id, lider, entrada, receptor, config)
+            }),
         })
     }
 
@@ -45,21 +124,28 @@ impl Aplicacion {
     fn procesar(
         id: usize,
         mut lider: EleccionLider,
-        mut parseador: Parser,
+        mut entrada: Entrada,
         mut receptor: Receiver<Comando>,
+        config: Config,
     ) {
         let mut estado = EstadoApp::CambioLider;
 
         while lider.bloquear_si_no_soy_lider() {
             match estado {
                 EstadoApp::CambioLider => {
-                    match Aplicacion::procesar_lider(&lider, &mut parseador, &mut receptor, id) {
+                    match Aplicacion::procesar_lider(
+                        &lider,
+                        &mut entrada,
+                        &mut receptor,
+                        id,
+                        &config,
+                    ) {
                         Ok(r) => estado = r,
                         Err(e) => println!("{}", e),
                     }
                 }
                 EstadoApp::FinEntrada => {
-                    match Aplicacion::procesar_fallidos(&lider, &mut receptor, id) {
+                    match Aplicacion::procesar_fallidos(&lider, &mut receptor, id, &config) {
                         Ok(r) => estado = r,
                         Err(e) => println!("{}", e),
                     }
@@ -73,83 +159,261 @@ impl Aplicacion {
         }
     }
 
-    /// Proceso para cuando el nodo es lider
+    /// Proceso para cuando el nodo es lider. Con una bandeja de entrada, al
+    /// terminar cada archivo se pasa al siguiente, y si no quedan se espera
+    /// a que lleguen mientras se atienden los comandos.
     fn procesar_lider(
         lider: &EleccionLider,
-        parseador: &mut Parser,
+        entrada: &mut Entrada,
         receptor: &mut Receiver<Comando>,
         id: usize,
+        config: &Config,
     ) -> Resultado<EstadoApp> {
-        let log = Arc::new(RwLock::new(Log::new()?));
-        let mut coordinador = CoordinadorTransaccion::new(id, log.clone())?;
-        let mut parser_fallidos = ParserFallidos::new()?;
-        let mut inicio_lider = true;
-        let mut transaccion;
+        let log = Arc::new(RwLock::new(Log::new(config, id)?));
+        let mut coordinador = CoordinadorTransaccion::new(id, log.clone(), config)?;
         let mut prox_pago = 1;
+        let mut incumplimientos_sla = 0;
 
-        while lider.soy_lider() {
-            //Este if inicio_lider se puede sacar fuera del while, porque ya sabemos que es lider
-            if inicio_lider {
-                inicio_lider = false;
-                transaccion = match log
+        let ultima_transaccion = log
+            .read()
+            .expect("Error al tomar lock del log en Aplicacion")
+            .ultima_transaccion()?;
+        match ultima_transaccion {
+            Some(ultima) => {
+                prox_pago = ultima.id_pago_prox;
+                entrada.reanudar(&ultima)?;
+                let mut pendientes = log
                     .read()
                     .expect("Error al tomar lock del log en Aplicacion")
-                    .ultima_transaccion()
-                {
-                    Some(t) => t,
-                    None => {
-                        println!("[Aplicacion] No se encontraron transacciones previas en el archivo de log");
+                    .transacciones_pendientes()?;
+                // Las transacciones migradas del log de texto anterior no
+                // incluyen el pago, por lo que se busca en el archivo de
+                // entrada
+                for transaccion in pendientes.iter_mut().filter(|t| t.pago.is_none()) {
+                    let pago = entrada.parser().map(|p| p.buscar(transaccion.id_pago));
+                    transaccion.pago = match pago {
+                        Some(Ok(Some(p))) => Some(p),
+                        _ => panic!(
+                            "[Aplicacion] El log de transacciones no matchea con el archivo de entrada"
+                        ),
+                    };
+                }
+                incumplimientos_sla +=
+                    Aplicacion::procesar_transacciones(&mut coordinador, &log, &mut pendientes).0;
+            }
+            None => {
+                println!(
+                    "[Aplicacion] No se encontraron transacciones previas en el archivo de log"
+                )
+            }
+        }
+
+        while lider.soy_lider() {
+            if let Ok(comando) = receptor.try_recv() {
+                let offset_prox = entrada.parser().map(|p| p.offset());
+                let prox = (prox_pago, offset_prox, entrada.archivo(), entrada.lote());
+                match Aplicacion::atender_comando(comando, &mut coordinador, &log, prox, config) {
+                    Some(incumplimientos) => incumplimientos_sla += incumplimientos,
+                    None => return Ok(EstadoApp::Finalizar),
+                }
+                continue;
+            }
+
+            let (archivo, lote) = (entrada.archivo(), entrada.lote());
+            let mut transacciones = match entrada.parser() {
+                Some(parseador) => Aplicacion::armar_lote(&log, parseador, &mut prox_pago, config)?,
+                None => Vec::new(),
+            };
+            if transacciones.is_empty() {
+                match entrada {
+                    Entrada::Archivo(parseador) => {
+                        Aplicacion::informar_fin(parseador, incumplimientos_sla);
+                        return Ok(EstadoApp::FinEntrada);
+                    }
+                    Entrada::Bandeja(bandeja) => {
+                        Aplicacion::avanzar_bandeja(
+                            bandeja,
+                            &log,
+                            &mut prox_pago,
+                            &mut incumplimientos_sla,
+                        )?;
                         continue;
                     }
-                };
-                prox_pago = transaccion.id_pago_prox;
-                transaccion.pago = match parseador.parsear(Some(transaccion.id_pago)).ok() {
-                    Some(Some(p)) => Some(p),
-                    _ => panic!(
-                        "[Aplicacion] El log de transacciones no matchea con el archivo de entrada"
-                    ),
-                };
-            } else if let Ok(comando) = receptor.try_recv() {
-                let id_reintento = match comando {
-                    Comando::Finalizar => return Ok(EstadoApp::Finalizar),
-                    Comando::Reintentar { id } => id,
-                };
-                transaccion = match Aplicacion::procesar_comando(
-                    id_reintento,
-                    &mut parser_fallidos,
-                    &log,
-                    prox_pago,
-                ) {
-                    Ok(Some(t)) => t,
-                    _ => continue,
-                };
-            } else {
-                transaccion = log
-                    .read()
-                    .expect("Error al tomar lock del log en Aplicacion")
-                    .nueva_transaccion(prox_pago, prox_pago + 1);
-                transaccion.pago = match parseador.parsear(Some(prox_pago)).ok() {
-                    Some(None) => return Ok(EstadoApp::FinEntrada),
-                    Some(p) => p,
-                    _ => panic!("[Aplicacion] Error al parsear del archivo de entrada"),
-                };
-                prox_pago += 1;
+                }
+            }
+            for transaccion in transacciones.iter_mut() {
+                transaccion.archivo = archivo.clone();
+                transaccion.lote = lote;
+            }
+            //Procesar transacciones
+            let (incumplimientos, resultados) =
+                Aplicacion::procesar_transacciones(&mut coordinador, &log, &mut transacciones);
+            incumplimientos_sla += incumplimientos;
+            if let Some(parseador) = entrada.parser() {
+                Aplicacion::rechazar_duplicados(parseador, &transacciones, &resultados);
+            }
+        }
+
+        Ok(EstadoApp::CambioLider)
+    }
+
+    /// Agrega al reporte de rechazados del archivo de entrada los pagos de
+    /// las transacciones descartadas por haberse confirmado el pago en otra
+    /// transacción.
+    fn rechazar_duplicados(
+        parseador: &mut Parser,
+        transacciones: &[Transaccion],
+        resultados: &[ResultadoTransaccion],
+    ) {
+        for (transaccion, resultado) in transacciones.iter().zip(resultados) {
+            if let ResultadoTransaccion::Duplicado = resultado {
+                if let Err(e) = parseador.rechazar_pago(transaccion.id_pago, MOTIVO_DUPLICADO) {
+                    println!(
+                        "[Aplicacion]: No se pudo rechazar el pago de id {}: {}",
+                        transaccion.id_pago, e
+                    );
+                }
+            }
+        }
+    }
+
+    /// Informa el fin de un archivo de entrada, con la cantidad de
+    /// transacciones que superaron su SLA y de líneas rechazadas.
+    fn informar_fin(parseador: &Parser, incumplimientos_sla: usize) {
+        println!(
+            "[Aplicacion]: Fin del archivo de entrada, {} transacciones superaron su SLA",
+            incumplimientos_sla
+        );
+        if parseador.rechazados() > 0 {
+            println!(
+                "[Aplicacion]: Se rechazaron {} lineas del archivo de entrada, ver {}",
+                parseador.rechazados(),
+                parseador.ruta_rechazados().display()
+            );
+        }
+    }
+
+    /// Termina el archivo de la bandeja en proceso, si hay uno, y abre el
+    /// siguiente, desde cuyo primer pago continúa la lectura. Si no quedan
+    /// archivos pendientes, espera antes de volver a revisar la bandeja.
+    fn avanzar_bandeja(
+        bandeja: &mut Bandeja,
+        log: &Arc<RwLock<Log>>,
+        prox_pago: &mut usize,
+        incumplimientos_sla: &mut usize,
+    ) -> Resultado<()> {
+        if let Some(parseador) = bandeja.parser() {
+            Aplicacion::informar_fin(parseador, *incumplimientos_sla);
+            *incumplimientos_sla = 0;
+            let log = log
+                .read()
+                .expect("Error al tomar lock del log en Aplicacion");
+            bandeja.terminar(|id| log.pago_confirmado(&id))?;
+        }
+        if !bandeja.siguiente()? {
+            thread::sleep(ESPERA_BANDEJA);
+            return Ok(());
+        }
+        if let Some(id) = bandeja.parser().and_then(|p| p.proximo_id()) {
+            *prox_pago = id;
+        }
+        Ok(())
+    }
+
+    /// Arma un lote de hasta `tam_lote` transacciones con los proximos pagos
+    /// del archivo de entrada, en el orden del archivo, esperando a lo sumo
+    /// `espera_lote_ms` por ellos. Las transacciones registran el offset y el
+    /// id del pago siguiente al lote, o el siguiente al último id leído si no
+    /// quedan pagos.
+    /// Devuelve un lote vacío si se llegó al final del archivo de entrada, y
+    /// error si no se pudo leer el log.
+    fn armar_lote(
+        log: &Arc<RwLock<Log>>,
+        parseador: &mut Parser,
+        prox_pago: &mut usize,
+        config: &Config,
+    ) -> Resultado<Vec<Transaccion>> {
+        let espera = Duration::from_millis(config.espera_lote_ms);
+        let inicio = Instant::now();
+        let mut pagos = Vec::new();
+
+        while pagos.len() < config.tam_lote.max(1)
+            && (pagos.is_empty() || inicio.elapsed() < espera)
+        {
+            match parseador.parsear() {
+                Ok(Some(pago)) => {
+                    *prox_pago = parseador.proximo_id().unwrap_or(pago.get_id() + 1);
+                    pagos.push(pago);
+                }
+                Ok(None) => break,
+                Err(_) => panic!("[Aplicacion] Error al parsear del archivo de entrada"),
             }
-            //Procesar transaccion
-            if coordinador.submit(&mut transaccion).is_err() {
-                //Agregar a la lista de falladas
+        }
+
+        let ids_pago = pagos.iter().map(Pago::get_id).collect::<Vec<usize>>();
+        let mut transacciones = log
+            .read()
+            .expect("Error al tomar lock del log en Aplicacion")
+            .nuevas_transacciones(&ids_pago, *prox_pago, Duration::from_millis(config.sla_ms))?;
+        for (transaccion, pago) in transacciones.iter_mut().zip(pagos) {
+            transaccion.pago = Some(pago);
+            transaccion.offset_prox = Some(parseador.offset());
+        }
+
+        Ok(transacciones)
+    }
+
+    /// Procesa un lote de transacciones y agrega a la lista de fallidos los
+    /// pagos de las transacciones abortadas. Las que quedaron pendientes en
+    /// el log no se agregan, ya que se retoman al recuperarlo. Reporta las
+    /// transacciones que terminaron luego de su deadline y devuelve la
+    /// cantidad de ellas junto al resultado de cada transaccion.
+    fn procesar_transacciones(
+        coordinador: &mut CoordinadorTransaccion,
+        log: &Arc<RwLock<Log>>,
+        transacciones: &mut [Transaccion],
+    ) -> (usize, Vec<ResultadoTransaccion>) {
+        let resultados = coordinador.submit_lote(transacciones);
+        let mut incumplimientos_sla = 0;
+
+        for (transaccion, resultado) in transacciones.iter().zip(&resultados) {
+            if transaccion.vencida() {
+                incumplimientos_sla += 1;
                 println!(
-                    "[Aplicacion]: El pago de id {} ha fallado",
-                    &transaccion.id_pago
+                    "[Aplicacion]: La transaccion {} del pago de id {} superó su SLA por {} ms",
+                    transaccion.id,
+                    transaccion.id_pago,
+                    Reloj::ahora_ms().saturating_sub(transaccion.deadline)
                 );
+            }
+
+            match resultado {
+                ResultadoTransaccion::Commit => {}
+                ResultadoTransaccion::Abort(motivo) => {
+                    //Agregar a la lista de falladas
+                    println!(
+                        "[Aplicacion]: El pago de id {} ha fallado: {}",
+                        &transaccion.id_pago, motivo
+                    );
 
-                if let Some(p) = transaccion.get_pago() {
-                    parser_fallidos.escribir_fallido(p)
+                    if let Some(p) = transaccion.get_pago() {
+                        log.write()
+                            .expect("Error al tomar lock del log en Aplicacion")
+                            .escribir_fallido(&p, Some(motivo))
+                    }
                 }
+                ResultadoTransaccion::Pendiente(e) => println!(
+                    "[Aplicacion]: El pago de id {} queda pendiente en el log: {}",
+                    &transaccion.id_pago, e
+                ),
+                ResultadoTransaccion::Duplicado => println!(
+                    "[Aplicacion]: Se rechaza el pago de id {}: {}",
+                    &transaccion.id_pago, MOTIVO_DUPLICADO
+                ),
             }
         }
 
-        Ok(EstadoApp::CambioLider)
+        (incumplimientos_sla, resultados)
     }
 
     /// Proceso para cuando el archivo de entrada finalizo
@@ -157,42 +421,29 @@ impl Aplicacion {
         lider: &EleccionLider,
         receptor: &mut Receiver<Comando>,
         id: usize,
+        config: &Config,
     ) -> Resultado<EstadoApp> {
-        let log = Arc::new(RwLock::new(Log::new()?));
-        let mut coordinador = CoordinadorTransaccion::new(id, log.clone())?;
-        let mut parser_fallidos = ParserFallidos::new()?;
-        let mut transaccion;
-        let prox_pago = log
+        let log = Arc::new(RwLock::new(Log::new(config, id)?));
+        let mut coordinador = CoordinadorTransaccion::new(id, log.clone(), config)?;
+        let prox = log
             .read()
             .expect("Error al tomar lock del log en Aplicacion")
-            .ultima_transaccion()
-            .map(|t| t.id_pago_prox)
-            .unwrap_or(1);
+            .ultima_transaccion()?
+            .map_or((1, None, None, 0), |t| {
+                (t.id_pago_prox, t.offset_prox, t.archivo, t.lote)
+            });
 
         while lider.soy_lider() {
             if let Ok(comando) = receptor.recv() {
-                let id_reintento = match comando {
-                    Comando::Finalizar => return Ok(EstadoApp::Finalizar),
-                    Comando::Reintentar { id } => id,
-                };
-                transaccion = match Aplicacion::procesar_comando(
-                    id_reintento,
-                    &mut parser_fallidos,
+                let atendido = Aplicacion::atender_comando(
+                    comando,
+                    &mut coordinador,
                     &log,
-                    prox_pago,
-                ) {
-                    Ok(Some(t)) => t,
-                    _ => continue,
-                };
-                if coordinador.submit(&mut transaccion).is_err() {
-                    //Agregar a la lista de falladas
-                    println!(
-                        "[Aplicacion]: El pago de id {} ha fallado",
-                        &transaccion.id_pago
-                    );
-                    if let Some(p) = transaccion.get_pago() {
-                        parser_fallidos.escribir_fallido(p)
-                    }
+                    prox.clone(),
+                    config,
+                );
+                if atendido.is_none() {
+                    return Ok(EstadoApp::Finalizar);
                 }
             }
         }
@@ -200,19 +451,89 @@ impl Aplicacion {
         Ok(EstadoApp::CambioLider)
     }
 
-    /// Procesa un comando recibido de la entrada estandar
+    /// Atiende un comando recibido de la entrada estandar o por socket,
+    /// respondiendo al cliente si el comando tiene un canal de respuesta. Las
+    /// transacciones de los reintentos y de los pagos nuevos registran el
+    /// proximo pago a leer del archivo de entrada dado.
+    /// Devuelve None si se pide finalizar, o la cantidad de transacciones que
+    /// superaron su SLA.
+    fn atender_comando(
+        comando: Comando,
+        coordinador: &mut CoordinadorTransaccion,
+        log: &Arc<RwLock<Log>>,
+        prox: (usize, Option<u64>, Option<String>, u32),
+        config: &Config,
+    ) -> Option<usize> {
+        match comando {
+            Comando::Finalizar => None,
+            Comando::Listar { respuesta } => {
+                Aplicacion::listar_fallidos(log, &respuesta);
+                Some(0)
+            }
+            Comando::Consultar { id, respuesta } => {
+                let _ = respuesta.send(Aplicacion::consultar(log, id));
+                Some(0)
+            }
+            Comando::Reintentar { id, respuesta } => {
+                let transaccion = match Aplicacion::procesar_comando(id, log, prox, config) {
+                    Ok(Some(t)) => t,
+                    resultado => {
+                        let motivo = match resultado {
+                            Err(e) => e.to_string(),
+                            _ => format!("No se encontro el pago fallido de id {}", id),
+                        };
+                        if let Some(respuesta) = respuesta {
+                            let _ = respuesta.send(MensajePago::RECHAZADO { motivo });
+                        }
+                        return Some(0);
+                    }
+                };
+                if let Some(respuesta) = &respuesta {
+                    let _ = respuesta.send(MensajePago::ACEPTADO { id_pago: id });
+                }
+                Some(Aplicacion::ejecutar(
+                    coordinador,
+                    log,
+                    transaccion,
+                    respuesta.as_ref(),
+                ))
+            }
+            Comando::Pagar(pedido) => Some(Aplicacion::procesar_pago(
+                coordinador,
+                log,
+                pedido,
+                prox,
+                config,
+            )),
+        }
+    }
+
+    /// Arma la transaccion del reintento de un pago fallido. La transaccion
+    /// del reintento registra el id y el offset del proximo pago a leer del
+    /// archivo de entrada, y el archivo y el lote de la bandeja.
     fn procesar_comando(
         id_reintento: usize,
-        parser: &mut ParserFallidos,
         log: &Arc<RwLock<Log>>,
-        prox_pago: usize,
+        (prox_pago, offset_prox, archivo, lote): (usize, Option<u64>, Option<String>, u32),
+        config: &Config,
     ) -> Resultado<Option<Transaccion>> {
         let mut transaccion = log
             .read()
             .expect("Error al tomar lock del log en Aplicacion")
-            .nueva_transaccion(id_reintento, prox_pago); //Le pasamos prox_pago o que se fije en la ultima transaccion
+            .nueva_transaccion(
+                id_reintento,
+                prox_pago, //Le pasamos prox_pago o que se fije en la ultima transaccion
+                Duration::from_millis(config.sla_ms),
+            )?;
+        transaccion.offset_prox = offset_prox;
+        transaccion.archivo = archivo;
+        transaccion.lote = lote;
 
-        match parser.parsear(id_reintento) {
+        let fallido = log
+            .write()
+            .expect("Error al tomar lock del log en Aplicacion")
+            .tomar_fallido(id_reintento);
+        match fallido {
             Ok(Some(pago)) => {
                 println!("[Aplicacion]: Se reintenta el pago de id {}", id_reintento);
                 transaccion.pago = Some(pago)
@@ -230,6 +551,184 @@ impl Aplicacion {
         Ok(Some(transaccion))
     }
 
+    /// Procesa un pago recibido por socket. El id del pago es el número de
+    /// su transacción con `BIT_PAGO_SOCKET`, único entre todos los líderes
+    /// por incluir la época, de forma que no se reasigna aunque el líder
+    /// caiga antes de registrarla.
+    /// Al cliente se le responde el id asignado antes de ejecutar la
+    /// transacción y su resultado al terminarla; si ya no espera la
+    /// respuesta, el pago no se procesa. Como en los reintentos, la
+    /// transacción registra el próximo pago a leer del archivo de entrada.
+    /// Devuelve la cantidad de transacciones que superaron su SLA.
+    fn procesar_pago(
+        coordinador: &mut CoordinadorTransaccion,
+        log: &Arc<RwLock<Log>>,
+        pedido: PedidoPago,
+        (prox_pago, offset_prox, archivo, lote): (usize, Option<u64>, Option<String>, u32),
+        config: &Config,
+    ) -> usize {
+        let transaccion = log
+            .read()
+            .expect("Error al tomar lock del log en Aplicacion")
+            .nueva_transaccion(0, prox_pago, Duration::from_millis(config.sla_ms));
+        let mut transaccion = match transaccion {
+            Ok(transaccion) => transaccion,
+            Err(e) => {
+                println!(
+                    "[Aplicacion]: Se rechaza un pago recibido por socket: {}",
+                    e
+                );
+                let _ = pedido.respuesta.send(MensajePago::RECHAZADO {
+                    motivo: e.to_string(),
+                });
+                return 0;
+            }
+        };
+        let id_pago = BIT_PAGO_SOCKET | transaccion.id.a_numero() as usize;
+        transaccion.id_pago = id_pago;
+        transaccion.offset_prox = offset_prox;
+        transaccion.archivo = archivo;
+        transaccion.lote = lote;
+
+        let pago =
+            Pago::new(id_pago, pedido.monto_aerolinea, pedido.monto_hotel).con_datos(pedido.datos);
+        transaccion.pago = match pago {
+            Ok(pago) => Some(pago),
+            Err(motivo) => {
+                println!(
+                    "[Aplicacion]: Se rechaza un pago recibido por socket: {}",
+                    motivo
+                );
+                let _ = pedido.respuesta.send(MensajePago::RECHAZADO { motivo });
+                return 0;
+            }
+        };
+        if pedido
+            .respuesta
+            .send(MensajePago::ACEPTADO { id_pago })
+            .is_err()
+        {
+            println!(
+                "[Aplicacion]: El cliente del pago de id {} ya no espera la respuesta, no se procesa",
+                id_pago
+            );
+            return 0;
+        }
+        println!(
+            "[Aplicacion]: Se recibe por socket el pago de id {}",
+            id_pago
+        );
+
+        Aplicacion::ejecutar(coordinador, log, transaccion, Some(&pedido.respuesta))
+    }
+
+    /// Procesa la transaccion de un pago y, si se indica un canal de
+    /// respuesta, envia al cliente su resultado.
+    /// Devuelve la cantidad de transacciones que superaron su SLA.
+    fn ejecutar(
+        coordinador: &mut CoordinadorTransaccion,
+        log: &Arc<RwLock<Log>>,
+        transaccion: Transaccion,
+        respuesta: Option<&Sender<MensajePago>>,
+    ) -> usize {
+        let mut transacciones = [transaccion];
+        let (incumplimientos_sla, resultados) =
+            Aplicacion::procesar_transacciones(coordinador, log, &mut transacciones);
+        let respuesta = match respuesta {
+            Some(respuesta) => respuesta,
+            None => return incumplimientos_sla,
+        };
+
+        let id_pago = transacciones[0].id_pago;
+        let resultado = match resultados.into_iter().next() {
+            Some(ResultadoTransaccion::Abort(motivo)) => MensajePago::FALLIDO { id_pago, motivo },
+            Some(ResultadoTransaccion::Pendiente(_)) => MensajePago::PENDIENTE { id_pago },
+            Some(ResultadoTransaccion::Duplicado) => MensajePago::RECHAZADO {
+                motivo: MOTIVO_DUPLICADO.to_string(),
+            },
+            _ => MensajePago::CONFIRMADO { id_pago },
+        };
+        let _ = respuesta.send(resultado);
+
+        incumplimientos_sla
+    }
+
+    /// Devuelve el estado del pago de id dado: confirmado si alguna de sus
+    /// transacciones se commiteo, fallido si esta entre los pagos fallidos,
+    /// pendiente si tiene una transaccion sin finalizar y desconocido si no.
+    fn consultar(log: &Arc<RwLock<Log>>, id_pago: usize) -> MensajePago {
+        let log = log
+            .read()
+            .expect("Error al tomar lock del log en Aplicacion");
+        match log.pago_confirmado(&id_pago) {
+            Ok(true) => return MensajePago::CONFIRMADO { id_pago },
+            Ok(false) => {}
+            Err(e) => {
+                return MensajePago::RECHAZADO {
+                    motivo: e.to_string(),
+                }
+            }
+        }
+        let fallido = match log.listar_fallidos() {
+            Ok(fallidos) => fallidos.into_iter().find(|(p, _)| p.get_id() == id_pago),
+            Err(e) => {
+                return MensajePago::RECHAZADO {
+                    motivo: e.to_string(),
+                }
+            }
+        };
+        if let Some((_, motivo)) = fallido {
+            return MensajePago::FALLIDO {
+                id_pago,
+                motivo: Aplicacion::motivo_conocido(motivo),
+            };
+        }
+        match log.transacciones_pendientes() {
+            Ok(pendientes) if pendientes.iter().any(|t| t.id_pago == id_pago) => {
+                MensajePago::PENDIENTE { id_pago }
+            }
+            Ok(_) => MensajePago::DESCONOCIDO { id_pago },
+            Err(e) => MensajePago::RECHAZADO {
+                motivo: e.to_string(),
+            },
+        }
+    }
+
+    /// Envia al cliente los pagos fallidos, junto al motivo por el cual
+    /// fallaron, y el fin del listado.
+    fn listar_fallidos(log: &Arc<RwLock<Log>>, respuesta: &Sender<MensajePago>) {
+        let fallidos = log
+            .read()
+            .expect("Error al tomar lock del log en Aplicacion")
+            .listar_fallidos();
+        let fallidos = match fallidos {
+            Ok(fallidos) => fallidos,
+            Err(e) => {
+                let _ = respuesta.send(MensajePago::RECHAZADO {
+                    motivo: e.to_string(),
+                });
+                return;
+            }
+        };
+        for (pago, motivo) in fallidos {
+            let _ = respuesta.send(MensajePago::PAGO_FALLIDO {
+                id_pago: pago.get_id(),
+                monto_aerolinea: pago.get_monto_aerolinea(),
+                monto_hotel: pago.get_monto_hotel(),
+                datos: pago.get_datos(),
+                motivo,
+            });
+        }
+        let _ = respuesta.send(MensajePago::FIN);
+    }
+
+    /// Devuelve el motivo de abort dado o, si no se conoce, uno de causa
+    /// desconocida.
+    fn motivo_conocido(motivo: Option<MotivoAborto>) -> MotivoAborto {
+        motivo
+            .unwrap_or_else(|| MotivoAborto::new(CausaAborto::Desconocida, "Transaccion abortada"))
+    }
+
     /// Realiza join sobre hilo
     pub fn join(self) {
         let _ = self.handle.join();
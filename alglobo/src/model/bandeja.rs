@@ -0,0 +1,258 @@
+use super::formato_entrada::TipoFormato;
+use super::parser::Parser;
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::reloj::Reloj;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Nombre del directorio de la bandeja al que se mueven los archivos
+/// procesados.
+const DIRECTORIO_PROCESADOS: &str = "procesados";
+
+/// Extensión que se agrega al nombre de un archivo procesado para guardar su
+/// resumen.
+const EXTENSION_RESUMEN: &str = ".resumen.json";
+
+/// ResumenArchivo contiene el resultado del procesamiento de un archivo de la
+/// bandeja de entrada: la cantidad de pagos válidos del archivo, cuántos de
+/// ellos se confirmaron y cuántos fallaron, la cantidad de líneas rechazadas
+/// y el momento en que se terminó de procesar, en milisegundos desde
+/// UNIX_EPOCH. Si el archivo no se pudo abrir, contiene el error.
+#[derive(Serialize)]
+pub struct ResumenArchivo {
+    pub archivo: String,
+    pub formato: &'static str,
+    pub pagos: usize,
+    pub confirmados: usize,
+    pub fallidos: usize,
+    pub rechazados: usize,
+    pub finalizacion: u64,
+    pub error: Option<String>,
+}
+
+/// Bandeja implementa la lectura de pagos de los archivos de lotes que
+/// llegan a un directorio de entrada. Los archivos se procesan de a uno, en
+/// orden de llegada según su fecha de modificación y luego su nombre, y al
+/// terminar se mueven al directorio `procesados` de la bandeja junto a su
+/// reporte de líneas rechazadas y a un resumen. Los archivos ocultos, cuyo
+/// nombre empieza con `.`, se ignoran, de forma que un lote pueda escribirse
+/// con un nombre oculto y renombrarse al completarse. Cada archivo que se
+/// abre recibe un número de lote nuevo, que separa los ids de sus pagos de
+/// los de los demás archivos aunque en los archivos coincidan.
+pub struct Bandeja {
+    directorio: PathBuf,
+    formato: Option<TipoFormato>,
+    actual: Option<(String, Parser)>,
+    lote: u32,
+}
+
+impl Bandeja {
+    /// Devuelve una instancia de Bandeja, creando el directorio de procesados
+    /// si no existe.
+    /// Recibe el directorio de la bandeja y el formato de sus archivos. Sin
+    /// formato, el de cada archivo se deduce de su extensión.
+    pub fn new(directorio: impl AsRef<Path>, formato: Option<TipoFormato>) -> Resultado<Self> {
+        let directorio = directorio.as_ref().to_path_buf();
+        fs::create_dir_all(directorio.join(DIRECTORIO_PROCESADOS))?;
+        Ok(Bandeja {
+            directorio,
+            formato,
+            actual: None,
+            lote: 0,
+        })
+    }
+
+    /// Devuelve el nombre del archivo que se está procesando, si hay uno.
+    pub fn archivo_actual(&self) -> Option<String> {
+        self.actual.as_ref().map(|(nombre, _)| nombre.clone())
+    }
+
+    /// Devuelve el lote del último archivo abierto, o 0 si no se abrió
+    /// ninguno.
+    pub fn lote(&self) -> u32 {
+        self.lote
+    }
+
+    /// Devuelve el parser del archivo que se está procesando, si hay uno.
+    pub fn parser(&mut self) -> Option<&mut Parser> {
+        self.actual.as_mut().map(|(_, parser)| parser)
+    }
+
+    /// Retoma el archivo registrado en el log, con su lote, desde el pago del
+    /// id y el offset dados, si el archivo sigue en la bandeja. Si ya no está,
+    /// porque se terminó de procesar, no queda ningún archivo en proceso y el
+    /// siguiente archivo recibe el lote posterior.
+    pub fn reanudar(
+        &mut self,
+        archivo: Option<&str>,
+        lote: u32,
+        id: usize,
+        offset: Option<u64>,
+    ) -> Resultado<()> {
+        self.actual = None;
+        self.lote = lote;
+        let archivo = match archivo {
+            Some(archivo) if self.directorio.join(archivo).is_file() => archivo,
+            _ => return Ok(()),
+        };
+        println!("[Bandeja] Se retoma el archivo {} (lote {})", archivo, lote);
+        let mut parser = self.abrir(archivo)?;
+        parser.reanudar(id, offset)?;
+        self.actual = Some((archivo.to_string(), parser));
+        Ok(())
+    }
+
+    /// Abre el archivo pendiente más antiguo de la bandeja, si no hay uno en
+    /// proceso, y devuelve verdadero si queda un archivo en proceso. Los
+    /// archivos que no se pueden abrir, por ejemplo por no tener un
+    /// encabezado válido, se mueven a procesados con el error en su resumen.
+    pub fn siguiente(&mut self) -> Resultado<bool> {
+        while self.actual.is_none() {
+            let archivo = match self.pendientes()?.into_iter().next() {
+                Some(archivo) => archivo,
+                None => return Ok(false),
+            };
+            self.lote += 1;
+            match self.abrir(&archivo) {
+                Ok(parser) => {
+                    println!(
+                        "[Bandeja] Se procesa el archivo {} como lote {}",
+                        archivo, self.lote
+                    );
+                    self.actual = Some((archivo, parser));
+                }
+                Err(e) => {
+                    println!(
+                        "[Bandeja] No se puede procesar el archivo {}: {}",
+                        archivo, e
+                    );
+                    let resumen = ResumenArchivo {
+                        formato: self.tipo(&archivo).codificar(),
+                        pagos: 0,
+                        confirmados: 0,
+                        fallidos: 0,
+                        rechazados: 0,
+                        finalizacion: Reloj::ahora_ms(),
+                        error: Some(e.to_string()),
+                        archivo,
+                    };
+                    self.archivar(&resumen)?;
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Termina el archivo en proceso: lo mueve a procesados junto a su
+    /// reporte de líneas rechazadas y a su resumen, y lo devuelve. Recibe una
+    /// función que indica si el pago de un id se confirmó.
+    /// Devuelve None si no hay un archivo en proceso, y error, manteniendo el
+    /// archivo en proceso, si no se puede saber si un pago se confirmó.
+    pub fn terminar(
+        &mut self,
+        confirmado: impl Fn(usize) -> Resultado<bool>,
+    ) -> Resultado<Option<ResumenArchivo>> {
+        let ids = match &self.actual {
+            Some((_, parser)) => parser.ids(),
+            None => return Ok(None),
+        };
+        let mut confirmados = 0;
+        for id in &ids {
+            if confirmado(*id)? {
+                confirmados += 1;
+            }
+        }
+        let (archivo, parser) = match self.actual.take() {
+            Some(actual) => actual,
+            None => return Ok(None),
+        };
+        let resumen = ResumenArchivo {
+            formato: self.tipo(&archivo).codificar(),
+            pagos: ids.len(),
+            confirmados,
+            fallidos: ids.len() - confirmados,
+            rechazados: parser.rechazados(),
+            finalizacion: Reloj::ahora_ms(),
+            error: None,
+            archivo,
+        };
+        self.archivar(&resumen)?;
+        Ok(Some(resumen))
+    }
+
+    /// Devuelve los nombres de los archivos pendientes de la bandeja en orden
+    /// de llegada.
+    fn pendientes(&self) -> Resultado<Vec<String>> {
+        let mut pendientes = Vec::new();
+        for entrada in fs::read_dir(&self.directorio)? {
+            let entrada = entrada?;
+            let nombre = entrada.file_name().to_string_lossy().to_string();
+            if nombre.starts_with('.')
+                || !entrada.file_type()?.is_file()
+                || Parser::es_asociado(&entrada.path())
+            {
+                continue;
+            }
+            let modificacion = entrada
+                .metadata()?
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            pendientes.push((modificacion, nombre));
+        }
+        pendientes.sort();
+        Ok(pendientes.into_iter().map(|(_, nombre)| nombre).collect())
+    }
+
+    /// Devuelve el formato del archivo de la bandeja dado.
+    fn tipo(&self, archivo: &str) -> TipoFormato {
+        self.formato
+            .unwrap_or_else(|| TipoFormato::desde_ruta(Path::new(archivo)))
+    }
+
+    /// Devuelve un parser del archivo de la bandeja dado, con los ids de
+    /// sus pagos en el lote actual.
+    fn abrir(&self, archivo: &str) -> Resultado<Parser> {
+        Parser::new(self.directorio.join(archivo), self.tipo(archivo))
+            .map(|parser| parser.con_lote(self.lote))
+    }
+
+    /// Mueve el archivo del resumen dado a procesados y guarda el resumen.
+    /// Si ya hay un archivo procesado con el mismo nombre, se agrega al
+    /// nombre el momento de finalización. El resumen se guarda antes de mover
+    /// el archivo, de forma que si el proceso se interrumpe el archivo se
+    /// vuelve a terminar y su resumen se reescribe.
+    fn archivar(&self, resumen: &ResumenArchivo) -> Resultado<()> {
+        let procesados = self.directorio.join(DIRECTORIO_PROCESADOS);
+        let mut destino = resumen.archivo.clone();
+        if procesados.join(&destino).exists() {
+            destino = format!("{}.{}", resumen.finalizacion, destino);
+        }
+        let contenido = serde_json::to_string_pretty(resumen).map_err(|e| {
+            ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Error al generar el resumen de {}: {}",
+                resumen.archivo, e
+            )))
+        })?;
+        fs::write(
+            procesados.join(format!("{}{}", destino, EXTENSION_RESUMEN)),
+            contenido,
+        )?;
+
+        let origen = self.directorio.join(&resumen.archivo);
+        let [indice, rechazados] = Parser::rutas_asociadas(&origen);
+        if rechazados.exists() {
+            let [_, destino_rechazados] = Parser::rutas_asociadas(&procesados.join(&destino));
+            fs::rename(rechazados, destino_rechazados)?;
+        }
+        let _ = fs::remove_file(indice);
+        fs::rename(origen, procesados.join(&destino))?;
+        println!(
+            "[Bandeja] Archivo {} procesado: {} pagos, {} confirmados, {} fallidos, {} lineas rechazadas",
+            resumen.archivo, resumen.pagos, resumen.confirmados, resumen.fallidos, resumen.rechazados
+        );
+        Ok(())
+    }
+}
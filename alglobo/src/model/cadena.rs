@@ -0,0 +1,278 @@
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::id_transaccion::IdTransaccion;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::log::Transaccion;
+
+/// Tamaño en bytes del hash de un registro del log.
+pub const TAM_HASH: usize = 32;
+
+/// Hash SHA-256 de un registro del log.
+pub type Hash = [u8; TAM_HASH];
+
+/// Hash anterior del primer registro de la cadena.
+pub const HASH_INICIAL: Hash = [0; TAM_HASH];
+
+/// Tamaño en bytes de la clave con la que se firman los checkpoints.
+const TAM_CLAVE: usize = 32;
+
+/// Devuelve el hash de un registro del log. Como cada registro incluye el
+/// hash del anterior, el hash del último registro depende de toda la cadena.
+pub fn hash(registro: &[u8]) -> Hash {
+    Sha256::digest(registro).into()
+}
+
+/// Convierte los bytes dados a hexadecimal y los devuelve.
+pub fn a_hexa(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Convierte un texto en hexadecimal a bytes y los devuelve.
+/// Devuelve error si el texto no es hexadecimal.
+pub fn de_hexa(texto: &str) -> Resultado<Vec<u8>> {
+    if !texto.len().is_multiple_of(2) || !texto.is_ascii() {
+        return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+            "Texto hexadecimal invalido: {}",
+            texto
+        ))));
+    }
+    (0..texto.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&texto[i..i + 2], 16)?))
+        .collect()
+}
+
+/// Convierte un texto en hexadecimal a Hash y lo devuelve.
+/// Devuelve error si el texto no es un hash válido.
+pub fn hash_de_hexa(texto: &str) -> Resultado<Hash> {
+    de_hexa(texto)?
+        .try_into()
+        .map_err(|_| ErrorApp::Interno(ErrorInterno::new(&format!("Hash invalido: {}", texto))))
+}
+
+/// Checkpoint representa un punto firmado de la cadena de registros del log.
+/// Contiene el id de la transacción y el hash de su registro, firmados con
+/// la clave local del nodo.
+pub struct Checkpoint {
+    pub id_transaccion: IdTransaccion,
+    pub hash: Hash,
+    pub firma: Vec<u8>,
+}
+
+impl Checkpoint {
+    /// Convierte el Checkpoint a String y lo devuelve.
+    pub fn codificar(&self) -> String {
+        format!(
+            "{},{},{}",
+            self.id_transaccion.a_numero(),
+            a_hexa(&self.hash),
+            a_hexa(&self.firma)
+        )
+    }
+
+    /// Convierte el String a Checkpoint y lo devuelve.
+    /// Devuelve error si el String no es un checkpoint válido.
+    pub fn decodificar(codificado: &str) -> Resultado<Checkpoint> {
+        let campos = codificado.split(',').collect::<Vec<&str>>();
+        if campos.len() != 3 {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Checkpoint invalido: '{}'",
+                codificado
+            ))));
+        }
+        Ok(Checkpoint {
+            id_transaccion: IdTransaccion::desde_numero(campos[0].parse::<u64>()?),
+            hash: hash_de_hexa(campos[1])?,
+            firma: de_hexa(campos[2])?,
+        })
+    }
+
+    /// Devuelve el contenido firmado del checkpoint.
+    fn contenido(id_transaccion: IdTransaccion, hash: &Hash) -> Vec<u8> {
+        let mut contenido = id_transaccion.a_numero().to_le_bytes().to_vec();
+        contenido.extend_from_slice(hash);
+        contenido
+    }
+}
+
+/// Firmante firma y verifica checkpoints con HMAC-SHA256 y una clave local
+/// que no se comparte con los demás nodos.
+pub struct Firmante {
+    clave: Vec<u8>,
+}
+
+impl Firmante {
+    /// Carga la clave del archivo de la ruta dada. Si el archivo no existe,
+    /// genera una clave aleatoria y la guarda.
+    pub fn cargar_o_crear<P: AsRef<Path>>(ruta: P) -> Resultado<Firmante> {
+        match Firmante::cargar(&ruta) {
+            Err(ErrorApp::ErrorIO(e)) if e.kind() == io::ErrorKind::NotFound => {
+                let mut clave = vec![0; TAM_CLAVE];
+                rand::thread_rng().fill_bytes(&mut clave);
+                fs::write(&ruta, a_hexa(&clave))?;
+                println!("[Log]: Se generó una nueva clave para firmar checkpoints");
+                Ok(Firmante { clave })
+            }
+            resultado => resultado,
+        }
+    }
+
+    /// Carga la clave del archivo de la ruta dada.
+    /// Devuelve error si el archivo no existe o no contiene una clave.
+    pub fn cargar<P: AsRef<Path>>(ruta: P) -> Resultado<Firmante> {
+        let clave = de_hexa(fs::read_to_string(ruta)?.trim())?;
+        if clave.is_empty() {
+            return Err(ErrorApp::Interno(ErrorInterno::new(
+                "La clave para firmar checkpoints esta vacia",
+            )));
+        }
+        Ok(Firmante { clave })
+    }
+
+    /// Devuelve un checkpoint firmado del registro de la transacción dada.
+    pub fn firmar(&self, id_transaccion: IdTransaccion, hash: Hash) -> Checkpoint {
+        let mut mac = self.mac();
+        mac.update(&Checkpoint::contenido(id_transaccion, &hash));
+        Checkpoint {
+            id_transaccion,
+            hash,
+            firma: mac.finalize().into_bytes().to_vec(),
+        }
+    }
+
+    /// Devuelve verdadero si la firma del checkpoint es válida.
+    pub fn verificar(&self, checkpoint: &Checkpoint) -> bool {
+        let mut mac = self.mac();
+        mac.update(&Checkpoint::contenido(
+            checkpoint.id_transaccion,
+            &checkpoint.hash,
+        ));
+        mac.verify_slice(&checkpoint.firma).is_ok()
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        // HMAC acepta claves de cualquier largo
+        Hmac::<Sha256>::new_from_slice(&self.clave).expect("Clave de HMAC invalida")
+    }
+}
+
+/// VerificacionCadena contiene el resultado de recorrer la cadena de
+/// registros del log y sus checkpoints.
+pub struct VerificacionCadena {
+    /// Cantidad de registros recorridos
+    pub registros: usize,
+    /// Descripción del primer enlace roto de la cadena, si lo hay
+    pub enlace_roto: Option<String>,
+    /// Cantidad de checkpoints válidos
+    pub checkpoints: usize,
+    /// Descripción del primer checkpoint inválido, si lo hay
+    pub checkpoint_invalido: Option<String>,
+    /// Registros posteriores al último checkpoint válido
+    pub sin_checkpoint: usize,
+}
+
+impl VerificacionCadena {
+    /// Devuelve verdadero si la cadena y todos los checkpoints son válidos.
+    pub fn valida(&self) -> bool {
+        self.enlace_roto.is_none() && self.checkpoint_invalido.is_none()
+    }
+}
+
+impl Display for VerificacionCadena {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        writeln!(f, "Registros:                       {}", self.registros)?;
+        writeln!(f, "Checkpoints validos:             {}", self.checkpoints)?;
+        writeln!(
+            f,
+            "Registros sin checkpoint:        {}",
+            self.sin_checkpoint
+        )?;
+        match &self.enlace_roto {
+            Some(e) => writeln!(f, "Primer enlace roto:              {}", e)?,
+            None => writeln!(f, "Cadena de registros:             integra")?,
+        }
+        match &self.checkpoint_invalido {
+            Some(c) => write!(f, "Primer checkpoint invalido:      {}", c),
+            None => write!(f, "Checkpoints:                     firmas validas"),
+        }
+    }
+}
+
+/// Recorre en orden los registros binarios del log verificando que cada uno
+/// incluya el hash del anterior, y luego verifica la firma de los
+/// checkpoints y que refieran, en orden, a registros de la cadena.
+/// Devuelve error si algún registro no se puede decodificar.
+pub fn verificar(
+    registros: &[Vec<u8>],
+    checkpoints: &[Checkpoint],
+    firmante: &Firmante,
+) -> Resultado<VerificacionCadena> {
+    let mut hashes: Vec<Hash> = Vec::with_capacity(registros.len());
+    let mut ids = Vec::with_capacity(registros.len());
+    let mut enlace_roto = None;
+
+    for (i, registro) in registros.iter().enumerate() {
+        let (transaccion, hash_anterior) = Transaccion::decodificar_encadenado(registro)?;
+        if enlace_roto.is_none() && hash_anterior != *hashes.last().unwrap_or(&HASH_INICIAL) {
+            enlace_roto = Some(format!(
+                "registro {} (transaccion {}, {})",
+                i + 1,
+                transaccion.id,
+                transaccion.estado().codificar()
+            ));
+        }
+        hashes.push(hash(registro));
+        ids.push(transaccion.id);
+    }
+
+    let mut validos = 0;
+    let mut checkpoint_invalido = None;
+    let mut desde = 0;
+    for (i, checkpoint) in checkpoints.iter().enumerate() {
+        let posicion = hashes[desde..]
+            .iter()
+            .position(|h| *h == checkpoint.hash)
+            .map(|p| p + desde);
+        let error = if !firmante.verificar(checkpoint) {
+            Some("firma invalida")
+        } else {
+            match posicion {
+                None => Some("el registro firmado no se encuentra en el log"),
+                Some(p) if ids[p] != checkpoint.id_transaccion => {
+                    Some("el registro firmado corresponde a otra transaccion")
+                }
+                Some(_) => None,
+            }
+        };
+        match (error, posicion) {
+            (None, Some(p)) => {
+                validos += 1;
+                desde = p + 1;
+            }
+            (error, _) => {
+                checkpoint_invalido = Some(format!(
+                    "checkpoint {} (transaccion {}): {}",
+                    i + 1,
+                    checkpoint.id_transaccion,
+                    error.unwrap_or_default()
+                ));
+                break;
+            }
+        }
+    }
+
+    Ok(VerificacionCadena {
+        registros: registros.len(),
+        enlace_roto,
+        checkpoints: validos,
+        checkpoint_invalido,
+        sin_checkpoint: registros.len() - desde,
+    })
+}
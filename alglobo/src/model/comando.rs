@@ -1,15 +1,47 @@
 use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::monto::Monto;
+use common::protocolo_pagos::MensajePago;
+use std::collections::BTreeMap;
+use std::sync::mpsc::Sender;
 
-/// Comando enumera los posibles comandos.
+/// Comando enumera los posibles comandos. Los comandos recibidos por socket
+/// contienen el canal por el que se responde al cliente, mientras que los
+/// leídos de la entrada estándar no lo tienen.
 /// # Variantes
 /// Reintentar: simboliza un intento y contiene el id del pago correspondiente
+/// Listar: simboliza el pedido por socket de listar los pagos fallidos junto
+/// al motivo por el cual fallaron. El pedido de la entrada estándar lo
+/// atiende el proceso principal sin pasar por el líder
+/// Consultar: simboliza la consulta del estado del pago del id que contiene
+/// Pagar: simboliza un pago nuevo recibido por socket, sin id asignado
 /// Finalizar: simboliza la finalización de la ejecución de la aplicación
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Debug)]
 pub enum Comando {
-    Reintentar { id: usize },
+    Reintentar {
+        id: usize,
+        respuesta: Option<Sender<MensajePago>>,
+    },
+    Listar {
+        respuesta: Sender<MensajePago>,
+    },
+    Consultar {
+        id: usize,
+        respuesta: Sender<MensajePago>,
+    },
+    Pagar(PedidoPago),
     Finalizar,
 }
 
+/// PedidoPago contiene un pago nuevo recibido por socket: sus montos y los
+/// datos de la reserva, y el canal por el que se responde al cliente.
+#[derive(Clone, Debug)]
+pub struct PedidoPago {
+    pub monto_aerolinea: Monto,
+    pub monto_hotel: Monto,
+    pub datos: BTreeMap<String, String>,
+    pub respuesta: Sender<MensajePago>,
+}
+
 impl Comando {
     /// Recibe una cadena y devuelve la cadena decodificada.
     /// Devuelve error si la cadena no corresponde a ninguna de las variantes.
@@ -18,14 +50,13 @@ impl Comando {
         match parseado[0] {
             "R" => Ok(Comando::Reintentar {
                 id: parseado[1].parse::<usize>()?,
+                respuesta: None,
             }),
             "F" => Ok(Comando::Finalizar),
-            _ => {
-                return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
-                    "Mensaje erroneo: {}",
-                    parseado[0]
-                ))))
-            }
+            _ => Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Mensaje erroneo: {}",
+                parseado[0]
+            )))),
         }
     }
 }
@@ -1,11 +1,171 @@
-pub struct Config {}
+use super::almacenamiento::TipoAlmacenamiento;
+use super::cotizaciones::MonedasLiquidacion;
+use super::wal::PoliticaDurabilidad;
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, ErrorKind};
+use std::path::Path;
+
+/// Config contiene la configuración del nodo de alglobo. Las rutas de los
+/// archivos son fijas, mientras que los parámetros de procesamiento se leen
+/// de un archivo de configuración.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Config {
+    /// Cantidad máxima de pagos a procesar en una misma ronda de transacción
+    #[serde(default = "Config::tam_lote_default")]
+    pub tam_lote: usize,
+    /// Tiempo máximo en milisegundos a esperar por pagos para completar un lote
+    #[serde(default = "Config::espera_lote_ms_default")]
+    pub espera_lote_ms: u64,
+    /// Tiempo máximo en milisegundos que puede durar una transacción
+    #[serde(default = "Config::sla_ms_default")]
+    pub sla_ms: u64,
+    /// Cuándo se sincronizan a disco los registros del log
+    #[serde(default = "Config::durabilidad_default")]
+    pub durabilidad: PoliticaDurabilidad,
+    /// Tamaño en bytes a partir del cual se sella un segmento del log
+    #[serde(default = "Config::tam_segmento_default")]
+    pub tam_segmento: u64,
+    /// Cantidad de registros del log a partir de la cual se toma un snapshot
+    /// y se archiva el historial. Con 0 no se compacta el log
+    #[serde(default = "Config::registros_por_snapshot_default")]
+    pub registros_por_snapshot: usize,
+    /// Cantidad de registros del log a partir de la cual se escribe un
+    /// checkpoint firmado de la cadena de registros. Con 0 no se escriben
+    #[serde(default = "Config::registros_por_checkpoint_default")]
+    pub registros_por_checkpoint: usize,
+    /// Ruta del archivo con la clave con la que se firman los checkpoints,
+    /// que debe estar fuera del directorio del log para que quien pueda
+    /// reescribir el log no pueda volver a firmarlo. Sin ella no se escriben
+    /// checkpoints
+    #[serde(default)]
+    pub ruta_clave: Option<String>,
+    /// Dónde se persiste el estado de las transacciones y los pagos fallidos
+    #[serde(default = "Config::almacenamiento_default")]
+    pub almacenamiento: TipoAlmacenamiento,
+    /// Moneda en la que liquida cada webservice. Los montos de los pagos se
+    /// convierten a ella con la tabla de cotizaciones
+    #[serde(default)]
+    pub monedas_liquidacion: MonedasLiquidacion,
+}
 
 impl Config {
+    /// Devuelve una Config con valores por default
+    fn new() -> Self {
+        Self {
+            tam_lote: Config::tam_lote_default(),
+            espera_lote_ms: Config::espera_lote_ms_default(),
+            sla_ms: Config::sla_ms_default(),
+            durabilidad: Config::durabilidad_default(),
+            tam_segmento: Config::tam_segmento_default(),
+            registros_por_snapshot: Config::registros_por_snapshot_default(),
+            registros_por_checkpoint: Config::registros_por_checkpoint_default(),
+            ruta_clave: None,
+            almacenamiento: Config::almacenamiento_default(),
+            monedas_liquidacion: MonedasLiquidacion::default(),
+        }
+    }
+
+    /// Lee la configuración de una ruta dada.
+    /// Si el archivo no existe, valores por defecto son asignados.
+    /// Devuelve error si el archivo existe pero no se puede leer o no es una
+    /// configuración válida, incluyendo la que pide el almacenamiento en
+    /// memoria.
+    pub fn get_config<P: AsRef<Path>>(path: P) -> Resultado<Config> {
+        let file = match File::open(&path) {
+            Ok(r) => r,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Config::new()),
+            Err(e) => return Err(ErrorApp::ErrorIO(e)),
+        };
+
+        let reader = BufReader::new(file);
+
+        let config: Config = serde_json::from_reader(reader).map_err(|e| {
+            ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Configuracion invalida en {}: {}",
+                path.as_ref().display(),
+                e
+            )))
+        })?;
+        if config.almacenamiento == TipoAlmacenamiento::Memoria {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Configuracion invalida en {}: el almacenamiento en memoria solo se usa en los tests",
+                path.as_ref().display()
+            ))));
+        }
+        Ok(config)
+    }
+
+    pub fn ruta_config() -> String {
+        "./files/config.json".to_string()
+    }
+
     pub fn ruta_fallidos() -> String {
         "./files/fallidos.csv".to_string()
     }
 
     pub fn ruta_logs() -> String {
+        "./files/estado".to_string()
+    }
+
+    pub fn ruta_log_anterior() -> String {
         "./files/estado.log".to_string()
     }
+
+    pub fn ruta_snapshot() -> String {
+        "./files/estado.snapshot".to_string()
+    }
+
+    pub fn ruta_archivo_logs() -> String {
+        "./files/archivo".to_string()
+    }
+
+    pub fn ruta_checkpoints() -> String {
+        "./files/estado.checkpoints".to_string()
+    }
+
+    pub fn ruta_epoca() -> String {
+        "./files/estado.epoca".to_string()
+    }
+
+    pub fn ruta_sqlite() -> String {
+        "./files/estado.db".to_string()
+    }
+
+    pub fn ruta_cotizaciones() -> String {
+        "./files/cotizaciones.json".to_string()
+    }
+
+    fn tam_lote_default() -> usize {
+        1
+    }
+
+    fn espera_lote_ms_default() -> u64 {
+        100
+    }
+
+    fn sla_ms_default() -> u64 {
+        30000
+    }
+
+    fn durabilidad_default() -> PoliticaDurabilidad {
+        PoliticaDurabilidad::PorRegistro
+    }
+
+    fn tam_segmento_default() -> u64 {
+        1024 * 1024
+    }
+
+    fn registros_por_snapshot_default() -> usize {
+        1000
+    }
+
+    fn registros_por_checkpoint_default() -> usize {
+        100
+    }
+
+    fn almacenamiento_default() -> TipoAlmacenamiento {
+        TipoAlmacenamiento::Archivo
+    }
 }
@@ -1,7 +1,16 @@
-use super::log::{EstadoTransaccion, Log, Transaccion};
+use super::config::Config;
+use super::cotizaciones::TablaCotizaciones;
+use super::estado_transaccion::EstadoTransaccion;
+use super::log::{Log, Transaccion};
+use super::pago::{Pago, DATO_CHECK_IN, DATO_CHECK_OUT, DATO_FECHA_VUELO, DATO_HOTEL, DATO_VUELO};
 use common::dns::DNS;
-use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::error::{ErrorApp, Resultado};
+use common::id_transaccion::IdTransaccion;
+use common::monto::{Moneda, Monto};
+use common::motivo_aborto::{CausaAborto, MotivoAborto};
 use common::protocolo_transaccion::{CodigoTransaccion, MensajeTransaccion, ProtocoloTransaccion};
+use common::reloj::Reloj;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::thread;
@@ -9,38 +18,81 @@ use std::thread::JoinHandle;
 use std::time::Duration;
 
 /// Cantidad de webservices disponibles
-const WEBSERVICES: usize = 3;
+pub const WEBSERVICES: usize = 3;
+/// Id del webservice de la aerolinea
+const AEROLINEA: usize = 0;
+/// Id del webservice del hotel
+const HOTEL: usize = 1;
+/// Id del webservice del banco
+const BANCO: usize = 2;
 /// Tolerancia a recibir las respuestas de todos los webservices
 const TIMEOUT_WEBSERVICES: Duration = Duration::from_secs(4);
 
+/// Respuestas recibidas de los webservices, indexadas por el id del
+/// webservice y el id de la operacion
+type Respuestas = HashMap<(usize, IdTransaccion), MensajeTransaccion>;
+
+/// ResultadoTransaccion representa cómo terminó una transacción de un lote.
+/// # Variantes
+/// Commit: la transacción se commiteó.
+/// Abort: la transacción se abortó, o no llegó a prepararse, por el motivo
+/// que contiene.
+/// Pendiente: no se pudo leer del log el estado de la transacción o registrar
+/// su decisión, por lo que, si llegó a registrarse, queda pendiente en el log
+/// y se retoma al recuperarlo.
+/// Duplicado: el pago ya fue confirmado por otra transacción, por lo que la
+/// transacción se descarta sin ejecutarse y el pago debe rechazarse.
+#[derive(Debug)]
+pub enum ResultadoTransaccion {
+    Commit,
+    Abort(MotivoAborto),
+    Pendiente(ErrorApp),
+    Duplicado,
+}
+
 /// CoordinadorTransaccion implementa el manejo de transacciones a través del
-/// envío y recepción de mensajes con los distintos webservices.
+/// envío y recepción de mensajes con los distintos webservices. Los montos de
+/// cada pago se convierten a la moneda de liquidación de cada webservice con
+/// la tabla de cotizaciones local.
 pub struct CoordinadorTransaccion {
     log: Arc<RwLock<Log>>,
     protocolo: ProtocoloTransaccion,
-    respuestas: Arc<(Mutex<Vec<Option<MensajeTransaccion>>>, Condvar)>,
+    respuestas: Arc<(Mutex<Respuestas>, Condvar)>,
     id: usize,
     destinatarios: Vec<String>,
+    cotizaciones: TablaCotizaciones,
+    monedas: [Moneda; WEBSERVICES],
     continuar: Arc<AtomicBool>,
     respondedor: Option<JoinHandle<()>>,
 }
 
 impl CoordinadorTransaccion {
     /// Devuelve una instancia de CoordinadorTransaccion.
-    /// Recibe el id asociado al nodo de alglobo y un Log.
-    pub fn new(id: usize, log: Arc<RwLock<Log>>) -> Resultado<Self> {
+    /// Recibe el id asociado al nodo de alglobo, un Log y la configuración,
+    /// con las monedas de liquidación. La tabla de cotizaciones se carga al
+    /// crear el coordinador.
+    /// Devuelve error si la tabla de cotizaciones o las monedas de
+    /// liquidación no son válidas.
+    pub fn new(id: usize, log: Arc<RwLock<Log>>, config: &Config) -> Resultado<Self> {
+        let cotizaciones = TablaCotizaciones::cargar(Config::ruta_cotizaciones())?;
+        let monedas = config.monedas_liquidacion.monedas()?;
+        println!(
+            "[Coordinador]: Tabla de cotizaciones vigente: version {}",
+            cotizaciones.version_vigente()
+        );
         let protocolo = ProtocoloTransaccion::new(DNS::direccion_alglobo(&id))?;
-        let respuestas = Arc::new((Mutex::new(vec![None; WEBSERVICES]), Condvar::new()));
+        let respuestas = Arc::new((Mutex::new(Respuestas::new()), Condvar::new()));
         let continuar = Arc::new(AtomicBool::new(true));
         let ret = CoordinadorTransaccion {
             log,
-            protocolo: protocolo.clone()?,
+            protocolo: protocolo.try_clone()?,
             respuestas: respuestas.clone(),
             id,
-            destinatarios: vec![0, 1, 2]
-                .iter()
-                .map(|id| DNS::direccion_webservice(id))
+            destinatarios: (0..WEBSERVICES)
+                .map(|id| DNS::direccion_webservice(&id))
                 .collect(),
+            cotizaciones,
+            monedas,
             continuar: continuar.clone(),
             respondedor: Some(thread::spawn(move || {
                 CoordinadorTransaccion::responder(protocolo, respuestas, continuar)
@@ -58,222 +110,551 @@ impl CoordinadorTransaccion {
         }
     }
 
-    /// Recibe una transaccion y la procesa
-    pub fn submit(&mut self, transaccion: &mut Transaccion) -> Resultado<()> {
-        let trans_en_log = self
-            .log
-            .read()
-            .expect("Error al tomar lock del log en Coordinador")
-            .obtener(&transaccion.id);
-        match trans_en_log {
-            None => self.full_protocol(transaccion),
-            Some(t) => match t.estado {
-                EstadoTransaccion::Prepare => self.full_protocol(transaccion),
-                EstadoTransaccion::Commit => self.commit(transaccion),
-                EstadoTransaccion::Abort => {
-                    let _ = self.abort(transaccion);
-                    Err(ErrorApp::Interno(ErrorInterno::new("Transaccion abortada")))
+    /// Recibe un lote de transacciones y las procesa en una misma ronda de
+    /// mensajes con cada webservice. Devuelve el resultado de cada transaccion
+    /// en el mismo orden en que fueron recibidas. Los errores se manejan por
+    /// transaccion, por lo que el fallo de una no afecta al resto del lote.
+    pub fn submit_lote(&mut self, transacciones: &mut [Transaccion]) -> Vec<ResultadoTransaccion> {
+        let mut resultados = transacciones
+            .iter()
+            .map(|_| ResultadoTransaccion::Commit)
+            .collect::<Vec<ResultadoTransaccion>>();
+        let mut a_preparar = Vec::new();
+        let mut a_commitear = Vec::new();
+        let mut a_abortar = Vec::new();
+
+        for (idx, transaccion) in transacciones.iter_mut().enumerate() {
+            let trans_en_log = self
+                .log
+                .read()
+                .expect("Error al tomar lock del log en Coordinador")
+                .obtener(&transaccion.id);
+            let confirmado = match &trans_en_log {
+                Ok(None) => self.pago_confirmado(transaccion),
+                _ => Ok(false),
+            };
+            match (trans_en_log, confirmado) {
+                (Err(e), _) | (_, Err(e)) => {
+                    println!(
+                        "[Coordinador]: No se pudo leer del log el estado de la transaccion {}: {}",
+                        transaccion.id, e
+                    );
+                    resultados[idx] = ResultadoTransaccion::Pendiente(e);
+                }
+                (Ok(None), Ok(true)) => {
+                    println!(
+                        "[Coordinador]: El pago de id {} ya fue commiteado, se descarta la transaccion {}",
+                        transaccion.id_pago, transaccion.id
+                    );
+                    resultados[idx] = ResultadoTransaccion::Duplicado;
+                }
+                (Ok(None), Ok(false)) => a_preparar.push(idx),
+                (Ok(Some(t)), _) => {
+                    // Los participantes registrados en el log son los que
+                    // votaron en el prepare original
+                    let estado = t.estado();
+                    transaccion.participantes = t.participantes;
+                    match estado {
+                        EstadoTransaccion::Prepare => a_preparar.push(idx),
+                        EstadoTransaccion::Commit => a_commitear.push(idx),
+                        EstadoTransaccion::Abort => {
+                            a_abortar.push(idx);
+                            // El motivo es el registrado en el abort del log
+                            let motivo = t.motivo_aborto.clone().unwrap_or_else(|| {
+                                MotivoAborto::new(CausaAborto::Desconocida, "Transaccion abortada")
+                            });
+                            resultados[idx] = CoordinadorTransaccion::abortada(transaccion, motivo);
+                        }
+                        EstadoTransaccion::Finalize => {}
+                    }
                 }
-                EstadoTransaccion::Finalize => Ok(()),
-            },
+            }
         }
-    }
 
-    /// Ejecuta el protocolo completo para la transaccion
-    fn full_protocol(&mut self, transaccion: &mut Transaccion) -> Resultado<()> {
-        match self.prepare(transaccion) {
-            Ok(_) => self.commit(transaccion),
-            Err(e) => {
-                let _ = self.abort(transaccion);
-                Err(e)
+        if !a_preparar.is_empty() {
+            let votos = self.prepare(transacciones, &a_preparar);
+            for (idx, voto) in a_preparar.into_iter().zip(votos) {
+                match voto {
+                    Ok(None) => a_commitear.push(idx),
+                    Ok(Some(motivo)) => {
+                        a_abortar.push(idx);
+                        resultados[idx] =
+                            CoordinadorTransaccion::abortada(&mut transacciones[idx], motivo);
+                    }
+                    // Sin el prepare en el log no hay una decision que registrar
+                    Err(motivo) => {
+                        resultados[idx] =
+                            CoordinadorTransaccion::abortada(&mut transacciones[idx], motivo)
+                    }
+                }
             }
         }
-    }
-
-    /// Ejecuta el prepare para la transaccion
-    fn prepare(&mut self, transaccion: &mut Transaccion) -> Resultado<()> {
-        self.log
-            .write()
-            .expect("Error al tomar lock del log en Coordinador")
-            .insertar(transaccion.prepare());
-        println!("[Coordinador]: Prepare de transaccion {}", transaccion.id);
-
-        let id_op = transaccion.id;
-        let pago = transaccion
-            .get_pago()
-            .expect("Intento de ejecutar transaccion sin pago");
-        // Preparo los mensajes a enviar
-        let m_hotel = MensajeTransaccion::new(
-            CodigoTransaccion::PREPARE {
-                monto: pago.get_monto_hotel(),
-            },
-            self.id,
-            id_op,
-        );
-        let m_aerolinea = MensajeTransaccion::new(
-            CodigoTransaccion::PREPARE {
-                monto: pago.get_monto_aerolinea(),
-            },
-            self.id,
-            id_op,
-        );
-        let m_banco = MensajeTransaccion::new(
-            CodigoTransaccion::PREPARE {
-                monto: pago.get_monto_hotel() + pago.get_monto_aerolinea(),
-            },
-            self.id,
-            id_op,
-        );
 
-        // Mensaje esperado
-        let esperado = MensajeTransaccion::new(CodigoTransaccion::READY, self.id, id_op);
+        self.decidir(transacciones, &a_commitear, &a_abortar, &mut resultados);
+        resultados
+    }
 
-        self.send_and_wait(vec![m_hotel, m_aerolinea, m_banco], esperado, false)
+    /// Registra el motivo del abort en la transaccion y devuelve el resultado
+    /// correspondiente.
+    fn abortada(transaccion: &mut Transaccion, motivo: MotivoAborto) -> ResultadoTransaccion {
+        transaccion.motivo_aborto = Some(motivo.clone());
+        ResultadoTransaccion::Abort(motivo)
     }
 
-    /// Ejecuta el commit para la transaccion
-    fn commit(&mut self, transaccion: &mut Transaccion) -> Resultado<()> {
+    /// Aplica la transicion dada a la transaccion y la registra en el log.
+    /// La transaccion solo cambia de estado si se pudo registrar, de forma
+    /// que se corresponda con el log.
+    /// Devuelve error si la transicion no es valida o no se pudo registrar.
+    fn registrar(
+        &self,
+        transaccion: &mut Transaccion,
+        transicion: fn(&mut Transaccion) -> Resultado<&Transaccion>,
+    ) -> Resultado<()> {
+        let mut registro = transaccion.clone();
         self.log
             .write()
             .expect("Error al tomar lock del log en Coordinador")
-            .insertar(transaccion.commit());
-        println!("[Coordinador]: Commit de transaccion {}", transaccion.id);
-        let id_op = transaccion.id;
-
-        // Preparo los mensajes a enviar y mensaje esperado
-        let mensaje = MensajeTransaccion::new(CodigoTransaccion::COMMIT, self.id, id_op);
-
-        let res = self.send_and_wait(
-            vec![mensaje.clone(), mensaje.clone(), mensaje.clone()],
-            mensaje,
-            true,
-        );
+            .insertar(transicion(&mut registro)?)?;
+        *transaccion = registro;
+        Ok(())
+    }
 
+    /// Sincroniza a disco los registros escritos en el log. Debe llamarse
+    /// antes de enviar mensajes que dependan de esos registros.
+    fn sincronizar_log(&self) -> Resultado<()> {
         self.log
             .write()
             .expect("Error al tomar lock del log en Coordinador")
-            .insertar(transaccion.finalize());
-        println!("[Coordinador]: Finalize de transaccion {}", transaccion.id);
-        res
+            .sincronizar()
     }
 
-    /// Ejecuta el abort para la transaccion
-    fn abort(&mut self, transaccion: &mut Transaccion) -> Resultado<()> {
+    /// Devuelve verdadero si el pago de la transaccion ya fue commiteado por
+    /// otra transaccion, en cuyo caso no debe volver a ejecutarse.
+    /// Devuelve error si no se pudo leer el log.
+    fn pago_confirmado(&self, transaccion: &Transaccion) -> Resultado<bool> {
         self.log
-            .write()
+            .read()
             .expect("Error al tomar lock del log en Coordinador")
-            .insertar(transaccion.abort());
-        println!("[Coordinador]: Abort de transaccion {}", transaccion.id);
+            .pago_confirmado(&transaccion.id_pago)
+    }
 
-        let id_op = transaccion.id;
+    /// Ejecuta el prepare para las transacciones indicadas por sus indices en
+    /// una misma ronda de mensajes. Los webservices que votan READ_ONLY quedan
+    /// excluidos de los participantes de la transaccion. Cada transacción
+    /// registra en su prepare la versión de la tabla de cotizaciones con la
+    /// que se convierten sus montos, que se mantiene si se retoma. Las
+    /// transacciones cuyos montos no se pueden convertir se abortan sin
+    /// participantes.
+    /// Devuelve, para cada transaccion, None si todos los webservices están
+    /// listos para el commit o el motivo por el cual debe abortarse, y error
+    /// con el motivo si no se pudo registrar su prepare, en cuyo caso no se
+    /// envía a los webservices.
+    fn prepare(
+        &mut self,
+        transacciones: &mut [Transaccion],
+        indices: &[usize],
+    ) -> Vec<Result<Option<MotivoAborto>, MotivoAborto>> {
+        let mut mensajes = Vec::new();
+        let mut sin_conversion = HashMap::new();
+        let mut sin_prepare = HashMap::new();
 
-        // Preparo los mensajes a enviar y mensaje esperado
-        let mensaje = MensajeTransaccion::new(CodigoTransaccion::ABORT, self.id, id_op);
+        for idx in indices {
+            let transaccion = &mut transacciones[*idx];
+            let version = *transaccion
+                .version_cotizaciones
+                .get_or_insert(self.cotizaciones.version_vigente());
+            if let Err(e) = self.registrar(transaccion, Transaccion::prepare) {
+                println!(
+                    "[Coordinador]: No se pudo registrar el prepare de la transaccion {}: {}",
+                    transaccion.id, e
+                );
+                sin_prepare.insert(
+                    *idx,
+                    MotivoAborto::new(CausaAborto::Desconocida, &e.to_string()),
+                );
+                continue;
+            }
+            println!("[Coordinador]: Prepare de transaccion {}", transaccion.id);
 
-        let res = self.send_and_wait(
-            vec![mensaje.clone(), mensaje.clone(), mensaje.clone()],
-            mensaje,
-            true,
-        );
+            let id_op = transaccion.id;
+            let deadline = transaccion.deadline;
+            let pago = transaccion
+                .get_pago()
+                .expect("Intento de ejecutar transaccion sin pago");
+            let montos = match self.montos_liquidacion(&pago, version) {
+                Ok(montos) => montos,
+                Err(e) => {
+                    println!(
+                        "[Coordinador]: No se pueden convertir los montos del pago de id {}: {}",
+                        pago.get_id(),
+                        e
+                    );
+                    sin_conversion.insert(
+                        *idx,
+                        MotivoAborto::new(CausaAborto::MonedaNoSoportada, &e.to_string()),
+                    );
+                    continue;
+                }
+            };
+            // Preparo los mensajes a enviar
+            for (webservice, monto) in montos.iter().enumerate() {
+                mensajes.push((
+                    webservice,
+                    MensajeTransaccion::new(
+                        CodigoTransaccion::PREPARE {
+                            monto: *monto,
+                            clave: pago.get_id(),
+                            deadline,
+                            datos: CoordinadorTransaccion::datos_reserva(&pago, webservice),
+                        },
+                        self.id,
+                        id_op,
+                    ),
+                ));
+            }
+        }
 
-        self.log
-            .write()
-            .expect("Error al tomar lock del log en Coordinador")
-            .insertar(transaccion.finalize());
-        println!("[Coordinador]: Finalize de transaccion {}", transaccion.id);
-        res
+        // Los prepare del lote se sincronizan juntos antes de enviar mensajes
+        let enviados = self
+            .sincronizar_log()
+            .and_then(|_| self.send_and_wait(mensajes, false));
+        let (votos, fallo_envio) = match enviados {
+            Ok(votos) => (votos, None),
+            Err(e) => {
+                println!(
+                    "[Coordinador]: No se pudieron sincronizar o enviar los prepare del lote: {}",
+                    e
+                );
+                let motivo = MotivoAborto::new(CausaAborto::Desconocida, &e.to_string());
+                (Respuestas::new(), Some(motivo))
+            }
+        };
+
+        indices
+            .iter()
+            .map(|idx| {
+                let transaccion = &mut transacciones[*idx];
+                if let Some(motivo) = sin_prepare.remove(idx) {
+                    return Err(motivo);
+                }
+                if let Some(motivo) = sin_conversion.remove(idx) {
+                    transaccion.participantes = Vec::new();
+                    return Ok(Some(motivo));
+                }
+                if let Some(motivo) = &fallo_envio {
+                    return Ok(Some(motivo.clone()));
+                }
+                let id_op = transaccion.id;
+                let voto = |id: usize| votos.get(&(id, id_op));
+
+                transaccion.participantes = (0..WEBSERVICES)
+                    .filter(|id| voto(*id).is_none_or(|v| v.codigo != CodigoTransaccion::READ_ONLY))
+                    .collect();
+
+                if (0..WEBSERVICES).all(|id| {
+                    voto(id).is_some_and(|v| {
+                        v.codigo == CodigoTransaccion::READY
+                            || v.codigo == CodigoTransaccion::READ_ONLY
+                    })
+                }) {
+                    return Ok(None);
+                }
+
+                // Se informa el motivo del primer webservice que votó abort
+                let motivo_voto = (0..WEBSERVICES).find_map(|id| match voto(id) {
+                    Some(MensajeTransaccion {
+                        codigo: CodigoTransaccion::ABORT { motivo: Some(m) },
+                        ..
+                    }) => Some(m.clone()),
+                    _ => None,
+                });
+                Ok(Some(match motivo_voto {
+                    Some(motivo) => motivo,
+                    None if transaccion.vencida() => {
+                        println!(
+                            "[Coordinador]: La transaccion {} superó su deadline durante el prepare",
+                            transaccion.id
+                        );
+                        MotivoAborto::new(CausaAborto::Timeout, "Deadline superado")
+                    }
+                    None => MotivoAborto::new(CausaAborto::Desconocida, "Respuesta no esperada"),
+                }))
+            })
+            .collect()
     }
 
-    /// Envia el mensaje a todos los destinatarios y espera por sus respuestas
-    /// con un timeout definido. En caso de timeout vuelve a enviar.
+    /// Devuelve los montos a pedir a la aerolínea, al hotel y al banco, en
+    /// ese orden, convertidos a la moneda de liquidación de cada uno con la
+    /// versión dada de la tabla de cotizaciones. El monto del banco es la
+    /// suma de los montos de la aerolínea y del hotel convertidos a su
+    /// moneda.
+    /// Devuelve error si algún monto no se puede convertir.
+    fn montos_liquidacion(&self, pago: &Pago, version: u32) -> Resultado<[Monto; WEBSERVICES]> {
+        let convertir = |monto: Monto, webservice: usize| {
+            self.cotizaciones
+                .convertir(monto, self.monedas[webservice], version)
+        };
+        let (aerolinea, hotel) = (pago.get_monto_aerolinea(), pago.get_monto_hotel());
+        let montos = [
+            convertir(aerolinea, AEROLINEA)?,
+            convertir(hotel, HOTEL)?,
+            convertir(aerolinea, BANCO)?.sumar(&convertir(hotel, BANCO)?)?,
+        ];
+        if montos
+            .iter()
+            .any(|m| m.moneda() != aerolinea.moneda() || m.moneda() != hotel.moneda())
+        {
+            println!(
+                "[Coordinador]: Pago de id {} convertido con la version {} de cotizaciones: aerolinea {} -> {}, hotel {} -> {}, banco {}",
+                pago.get_id(),
+                version,
+                aerolinea,
+                montos[AEROLINEA],
+                hotel,
+                montos[HOTEL],
+                montos[BANCO]
+            );
+        }
+        Ok(montos)
+    }
+
+    /// Devuelve los datos de la reserva del pago que corresponden al
+    /// webservice dado: el vuelo solo a la aerolínea y la estadía solo al
+    /// hotel. El cliente y los metadatos corresponden a todos.
+    fn datos_reserva(pago: &Pago, webservice: usize) -> BTreeMap<String, String> {
+        let excluidos: &[&str] = match webservice {
+            AEROLINEA => &[DATO_HOTEL, DATO_CHECK_IN, DATO_CHECK_OUT],
+            HOTEL => &[DATO_VUELO, DATO_FECHA_VUELO],
+            _ => &[
+                DATO_VUELO,
+                DATO_FECHA_VUELO,
+                DATO_HOTEL,
+                DATO_CHECK_IN,
+                DATO_CHECK_OUT,
+            ],
+        };
+        let mut datos = pago.get_datos();
+        datos.retain(|nombre, _| !excluidos.contains(&nombre.as_str()));
+        datos
+    }
+
+    /// Ejecuta el commit y el abort de las transacciones indicadas por sus
+    /// indices en una misma ronda de mensajes con los participantes de cada
+    /// una, y luego las finaliza. Solo se envían las decisiones que se
+    /// pudieron registrar en el log: si no se puede registrar el commit de
+    /// una transaccion se aborta, y si tampoco se puede registrar el abort
+    /// queda pendiente en el log. Actualiza el resultado de cada transaccion
+    /// según la decisión registrada.
+    fn decidir(
+        &mut self,
+        transacciones: &mut [Transaccion],
+        a_commitear: &[usize],
+        a_abortar: &[usize],
+        resultados: &mut [ResultadoTransaccion],
+    ) {
+        let mut mensajes = Vec::new();
+        let mut decididas = Vec::new();
+        let mut a_abortar = a_abortar.to_vec();
+
+        for idx in a_commitear {
+            let transaccion = &mut transacciones[*idx];
+            if let Err(e) = self.registrar(transaccion, Transaccion::commit) {
+                println!(
+                    "[Coordinador]: No se pudo registrar el commit de la transaccion {}, se aborta: {}",
+                    transaccion.id, e
+                );
+                let motivo = MotivoAborto::new(
+                    CausaAborto::Desconocida,
+                    &format!("No se pudo registrar el commit: {}", e),
+                );
+                resultados[*idx] = CoordinadorTransaccion::abortada(transaccion, motivo);
+                a_abortar.push(*idx);
+                continue;
+            }
+            println!("[Coordinador]: Commit de transaccion {}", transaccion.id);
+
+            let mensaje =
+                MensajeTransaccion::new(CodigoTransaccion::COMMIT, self.id, transaccion.id);
+            mensajes.extend(
+                transaccion
+                    .participantes
+                    .iter()
+                    .map(|id| (*id, mensaje.clone())),
+            );
+            decididas.push(*idx);
+        }
+
+        for idx in a_abortar {
+            let transaccion = &mut transacciones[idx];
+            if let Err(e) = self.registrar(transaccion, Transaccion::abort) {
+                println!(
+                    "[Coordinador]: No se pudo registrar el abort de la transaccion {}, queda pendiente: {}",
+                    transaccion.id, e
+                );
+                resultados[idx] = ResultadoTransaccion::Pendiente(e);
+                continue;
+            }
+            println!("[Coordinador]: Abort de transaccion {}", transaccion.id);
+
+            let mensaje = MensajeTransaccion::new(
+                CodigoTransaccion::ABORT { motivo: None },
+                self.id,
+                transaccion.id,
+            );
+            mensajes.extend(
+                transaccion
+                    .participantes
+                    .iter()
+                    .map(|id| (*id, mensaje.clone())),
+            );
+            decididas.push(idx);
+        }
+
+        let enviados = self
+            .sincronizar_log()
+            .and_then(|_| self.send_and_wait(mensajes, true));
+        if let Err(e) = enviados {
+            // Las decisiones ya registradas se vuelven a enviar al retomar
+            // las transacciones pendientes
+            println!(
+                "[Coordinador]: No se pudieron sincronizar o enviar las decisiones del lote, quedan sin finalizar: {}",
+                e
+            );
+            return;
+        }
+
+        for idx in decididas {
+            let transaccion = &mut transacciones[idx];
+            match self.registrar(transaccion, Transaccion::finalize) {
+                Ok(()) => println!("[Coordinador]: Finalize de transaccion {}", transaccion.id),
+                Err(e) => println!(
+                    "[Coordinador]: No se pudo registrar el finalize de la transaccion {}: {}",
+                    transaccion.id, e
+                ),
+            }
+        }
+    }
+
+    /// Envia a cada destinatario sus mensajes en un lote, dividido en varios
+    /// datagramas si no entra en uno solo, y espera por las respuestas de
+    /// todos ellos con un timeout definido. En caso de timeout vuelve a
+    /// enviar, salvo los prepare cuyo deadline ya pasó. Si el mensaje es
+    /// critico, vuelve a enviar hasta que todas las respuestas tengan el
+    /// mismo codigo que el mensaje enviado. Devuelve las respuestas
+    /// recibidas.
     fn send_and_wait(
         &mut self,
-        mensajes: Vec<MensajeTransaccion>,
-        esperado: MensajeTransaccion,
+        mut mensajes: Vec<(usize, MensajeTransaccion)>,
         mensaje_critico: bool,
-    ) -> Resultado<()> {
+    ) -> Resultado<Respuestas> {
         loop {
-            let respuestas;
-            *self
-                .respuestas
+            mensajes.retain(|(_, mensaje)| match mensaje.codigo {
+                CodigoTransaccion::PREPARE { deadline, .. } => Reloj::ahora_ms() <= deadline,
+                _ => true,
+            });
+            if mensajes.is_empty() {
+                return Ok(Respuestas::new());
+            }
+
+            self.respuestas
                 .0
                 .lock()
-                .expect("Error al tomar lock de respuestas en Coordinador") =
-                vec![None; WEBSERVICES];
+                .expect("Error al tomar lock de respuestas en Coordinador")
+                .clear();
 
-            for (idx, mensaje) in mensajes.iter().enumerate() {
-                self.protocolo
-                    .enviar(mensaje, self.destinatarios[idx].clone())?;
+            for (id, direccion) in self.destinatarios.iter().enumerate() {
+                let lote = mensajes
+                    .iter()
+                    .filter(|(destinatario, _)| *destinatario == id)
+                    .map(|(_, mensaje)| mensaje.clone())
+                    .collect::<Vec<MensajeTransaccion>>();
+                match lote.as_slice() {
+                    [] => {}
+                    [mensaje] => self.protocolo.enviar(mensaje, direccion.clone())?,
+                    _ => self.protocolo.enviar_lote(&lote, direccion.clone())?,
+                }
             }
-            respuestas = self.respuestas.1.wait_timeout_while(
+            let respuestas = self.respuestas.1.wait_timeout_while(
                 self.respuestas
                     .0
                     .lock()
                     .expect("Error al tomar lock de respuestas en Coordinador"),
                 TIMEOUT_WEBSERVICES,
-                |respuestas| respuestas.iter().any(Option::is_none),
+                |respuestas| {
+                    mensajes
+                        .iter()
+                        .any(|(id, mensaje)| !respuestas.contains_key(&(*id, mensaje.id_op)))
+                },
             );
 
-            let mensajes_esperados = match &respuestas {
-                Ok(val) if !val.1.timed_out() => respuestas
-                    .expect("Error al tomar lock de respuestas en Coordinador")
-                    .0
-                    .iter()
-                    .all(|opt| opt.as_ref().map_or(false, |r| r == &esperado)),
+            let respuestas = match respuestas {
+                Ok((val, timeout)) if !timeout.timed_out() => val.clone(),
                 _ => {
                     println!(
                         "[Coordinador] Timeout de recepcion a webservices, reintentando id {}",
-                        esperado.id_op
+                        mensajes[0].1.id_op
                     );
                     continue;
                 }
             };
 
+            let mensajes_esperados = !mensaje_critico
+                || mensajes.iter().all(|(id, mensaje)| {
+                    respuestas
+                        .get(&(*id, mensaje.id_op))
+                        .is_some_and(|r| r.mismo_codigo(&mensaje.codigo))
+                });
+
             if mensajes_esperados {
-                break;
-            } else if mensaje_critico {
-                continue;
-            } else {
-                return Err(ErrorApp::Interno(ErrorInterno::new(
-                    "Respuesta no esperada",
-                )));
+                return Ok(respuestas);
             }
         }
-
-        Ok(())
     }
 
     /// Recibe mensajes de los webservices y guarda el resultado.
     fn responder(
         mut protocolo: ProtocoloTransaccion,
-        respuestas: Arc<(Mutex<Vec<Option<MensajeTransaccion>>>, Condvar)>,
+        respuestas: Arc<(Mutex<Respuestas>, Condvar)>,
         continuar: Arc<AtomicBool>,
     ) {
         while continuar.load(Ordering::Relaxed) {
-            let mensaje = match protocolo.recibir(Some(TIMEOUT_WEBSERVICES)) {
+            let mensajes = match protocolo.recibir_lote(Some(TIMEOUT_WEBSERVICES)) {
                 Ok(m) => m,
                 Err(_) => continue,
             };
-            let id_emisor = mensaje.id_emisor;
-            match mensaje.codigo {
-                CodigoTransaccion::READY | CodigoTransaccion::COMMIT | CodigoTransaccion::ABORT => {
-                    println!(
-                        "[Coordinador] Recibí {:?} de {} para la transaccion {}",
-                        mensaje.codigo, id_emisor, mensaje.id_op
-                    );
-                    respuestas
-                        .0
-                        .lock()
-                        .expect("Error al tomar lock de respuestas en Coordinador")[id_emisor] =
-                        Some(mensaje);
-                    respuestas.1.notify_all();
-                }
-                _ => {
-                    println!(
-                        "[Coordinador]: Recibí algo que no puedo interpretar de {}",
-                        id_emisor
-                    );
-                }
+            for mensaje in mensajes {
+                CoordinadorTransaccion::guardar_respuesta(mensaje, &respuestas);
+            }
+        }
+    }
+
+    /// Guarda la respuesta de un webservice y notifica su llegada.
+    fn guardar_respuesta(
+        mensaje: MensajeTransaccion,
+        respuestas: &Arc<(Mutex<Respuestas>, Condvar)>,
+    ) {
+        let id_emisor = mensaje.id_emisor;
+        match mensaje.codigo {
+            CodigoTransaccion::READY
+            | CodigoTransaccion::COMMIT
+            | CodigoTransaccion::ABORT { .. }
+            | CodigoTransaccion::READ_ONLY => {
+                println!(
+                    "[Coordinador] Recibí {:?} de {} para la transaccion {}",
+                    mensaje.codigo, id_emisor, mensaje.id_op
+                );
+                respuestas
+                    .0
+                    .lock()
+                    .expect("Error al tomar lock de respuestas en Coordinador")
+                    .insert((id_emisor, mensaje.id_op), mensaje);
+                respuestas.1.notify_all();
+            }
+            _ => {
+                println!(
+                    "[Coordinador]: Recibí algo que no puedo interpretar de {}",
+                    id_emisor
+                );
             }
         }
     }
@@ -0,0 +1,187 @@
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::monto::{Moneda, Monto};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Cantidad de decimales de las tasas de cambio.
+const DECIMALES_TASA: u32 = 6;
+
+/// MonedasLiquidacion indica la moneda en la que liquida cada webservice,
+/// por su código ISO 4217.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MonedasLiquidacion {
+    #[serde(default = "MonedasLiquidacion::moneda_default")]
+    pub aerolinea: String,
+    #[serde(default = "MonedasLiquidacion::moneda_default")]
+    pub hotel: String,
+    #[serde(default = "MonedasLiquidacion::moneda_default")]
+    pub banco: String,
+}
+
+impl MonedasLiquidacion {
+    /// Devuelve las monedas de la aerolínea, del hotel y del banco, en ese
+    /// orden.
+    /// Devuelve error si alguna no es un código de moneda válido.
+    pub fn monedas(&self) -> Resultado<[Moneda; 3]> {
+        Ok([
+            Moneda::decodificar(&self.aerolinea)?,
+            Moneda::decodificar(&self.hotel)?,
+            Moneda::decodificar(&self.banco)?,
+        ])
+    }
+
+    fn moneda_default() -> String {
+        Moneda::POR_DEFECTO.codigo().to_string()
+    }
+}
+
+impl Default for MonedasLiquidacion {
+    fn default() -> Self {
+        MonedasLiquidacion {
+            aerolinea: MonedasLiquidacion::moneda_default(),
+            hotel: MonedasLiquidacion::moneda_default(),
+            banco: MonedasLiquidacion::moneda_default(),
+        }
+    }
+}
+
+/// Formato del archivo de cotizaciones: la moneda base y las versiones de la
+/// tabla, cada una con el valor en la moneda base de una unidad de cada
+/// moneda.
+#[derive(Deserialize)]
+struct ArchivoCotizaciones {
+    base: String,
+    versiones: Vec<VersionArchivo>,
+}
+
+#[derive(Deserialize)]
+struct VersionArchivo {
+    version: u32,
+    tasas: HashMap<String, String>,
+}
+
+/// TablaCotizaciones contiene las versiones de la tabla de tasas de cambio
+/// configurada localmente. Se conservan todas las versiones de forma que una
+/// transacción retomada convierta sus montos con la misma versión con la que
+/// se preparó. La versión vigente es la mayor; la versión 0 corresponde a no
+/// tener tabla, por lo que solo admite montos en la moneda de destino.
+pub struct TablaCotizaciones {
+    base: Moneda,
+    versiones: BTreeMap<u32, HashMap<Moneda, u64>>,
+}
+
+impl TablaCotizaciones {
+    /// Carga la tabla de cotizaciones del archivo de la ruta dada. Si el
+    /// archivo no existe, devuelve una tabla sin versiones.
+    /// Devuelve error si el archivo no es una tabla válida.
+    pub fn cargar(ruta: impl AsRef<Path>) -> Resultado<Self> {
+        let contenido = match fs::read_to_string(ruta) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(TablaCotizaciones {
+                    base: Moneda::POR_DEFECTO,
+                    versiones: BTreeMap::new(),
+                })
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let archivo: ArchivoCotizaciones = serde_json::from_str(&contenido).map_err(|e| {
+            ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Tabla de cotizaciones invalida: {}",
+                e
+            )))
+        })?;
+
+        let mut versiones = BTreeMap::new();
+        for version in archivo.versiones {
+            if version.version == 0 || versiones.contains_key(&version.version) {
+                return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                    "Tabla de cotizaciones invalida: version {} invalida o repetida",
+                    version.version
+                ))));
+            }
+            let tasas = version
+                .tasas
+                .iter()
+                .map(|(moneda, tasa)| Ok((Moneda::decodificar(moneda)?, parsear_tasa(tasa)?)))
+                .collect::<Resultado<HashMap<Moneda, u64>>>()?;
+            versiones.insert(version.version, tasas);
+        }
+
+        Ok(TablaCotizaciones {
+            base: Moneda::decodificar(&archivo.base)?,
+            versiones,
+        })
+    }
+
+    /// Devuelve la versión vigente de la tabla, o 0 si no tiene versiones.
+    pub fn version_vigente(&self) -> u32 {
+        self.versiones.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// Convierte el monto dado a la moneda dada con la versión dada de la
+    /// tabla y lo devuelve, redondeado al centavo más cercano. La conversión
+    /// se hace en un único paso a través de la moneda base, de forma que se
+    /// redondea una sola vez.
+    /// Devuelve error si la versión no existe o no tiene la cotización de
+    /// alguna de las monedas.
+    pub fn convertir(&self, monto: Monto, destino: Moneda, version: u32) -> Resultado<Monto> {
+        if monto.moneda() == destino {
+            return Ok(monto);
+        }
+        let tasas = self.versiones.get(&version).ok_or_else(|| {
+            ErrorApp::Interno(ErrorInterno::new(&format!(
+                "No existe la version {} de la tabla de cotizaciones",
+                version
+            )))
+        })?;
+        let tasa = |moneda: Moneda| -> Resultado<u128> {
+            if moneda == self.base {
+                return Ok(10u128.pow(DECIMALES_TASA));
+            }
+            tasas.get(&moneda).map(|t| *t as u128).ok_or_else(|| {
+                ErrorApp::Interno(ErrorInterno::new(&format!(
+                    "No hay cotizacion de {} en la version {} de la tabla de cotizaciones",
+                    moneda, version
+                )))
+            })
+        };
+        let (origen, destino_tasa) = (tasa(monto.moneda())?, tasa(destino)?);
+        let centavos = (monto.centavos() as u128 * origen + destino_tasa / 2) / destino_tasa;
+        let centavos = u64::try_from(centavos)
+            .map_err(|_| ErrorApp::Interno(ErrorInterno::new("Monto fuera de rango")))?;
+        Ok(Monto::new(centavos, destino))
+    }
+}
+
+/// Convierte una tasa de cambio, un número decimal positivo con a lo sumo
+/// `DECIMALES_TASA` decimales, a un entero en millonésimas y lo devuelve.
+/// Devuelve error si no es una tasa válida.
+fn parsear_tasa(tasa: &str) -> Resultado<u64> {
+    let error = || {
+        ErrorApp::Interno(ErrorInterno::new(&format!(
+            "Tabla de cotizaciones invalida: tasa erronea '{}'",
+            tasa
+        )))
+    };
+    let (entero, fraccion) = tasa.split_once('.').unwrap_or((tasa, "0"));
+    let digitos = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !digitos(entero) || !digitos(fraccion) || fraccion.len() > DECIMALES_TASA as usize {
+        return Err(error());
+    }
+    let fraccion = format!("{:0<ancho$}", fraccion, ancho = DECIMALES_TASA as usize);
+    let valor = entero
+        .parse::<u64>()
+        .ok()
+        .and_then(|e| e.checked_mul(10u64.pow(DECIMALES_TASA)))
+        .and_then(|e| e.checked_add(fraccion.parse::<u64>().ok()?))
+        .ok_or_else(error)?;
+    if valor == 0 {
+        return Err(error());
+    }
+    Ok(valor)
+}
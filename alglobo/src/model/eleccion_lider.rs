@@ -1,4 +1,4 @@
-use common::dns::DNS;
+use common::dns::{DNS, NODOS_ALGLOBO};
 use common::error::Resultado;
 use common::protocolo_lider::{CodigoLider, MensajeLider, ProtocoloLider};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -7,7 +7,7 @@ use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
 /// Cantidad maxima de procesos en el sistema
-const TEAM_MEMBERS: usize = 7;
+pub const TEAM_MEMBERS: usize = NODOS_ALGLOBO;
 /// Tiempo de espera para proclamarse lider
 const TIMEOUT_LIDER: Duration = Duration::from_secs(6);
 /// Tolerancia a recibir un mensaje
@@ -29,6 +29,31 @@ pub struct EleccionLider {
     respondedor: Option<JoinHandle<()>>,
 }
 
+/// VistaLider permite consultar desde otros hilos el lider conocido por una
+/// EleccionLider, sin participar de la elección.
+#[derive(Clone)]
+pub struct VistaLider {
+    id: usize,
+    id_lider: Arc<(Mutex<Option<usize>>, Condvar)>,
+}
+
+impl VistaLider {
+    /// Devuelve el id del proceso lider, o None si hay una elección en curso.
+    /// No es bloqueante
+    pub fn get_id_lider(&self) -> Option<usize> {
+        *self
+            .id_lider
+            .0
+            .lock()
+            .expect("Error al tomar lock del id_lider en VistaLider")
+    }
+
+    /// Devuelve true si el proceso es lider. No es bloqueante
+    pub fn soy_lider(&self) -> bool {
+        self.get_id_lider() == Some(self.id)
+    }
+}
+
 impl EleccionLider {
     /// Devuelve una instancia de EleccionLider.
     /// Recibe el id asociado al nodo de alglobo.
@@ -92,6 +117,14 @@ impl EleccionLider {
             .expect("Se obtuvo un id None")
     }
 
+    /// Devuelve una VistaLider del lider conocido por el proceso
+    pub fn vista(&self) -> VistaLider {
+        VistaLider {
+            id: self.id,
+            id_lider: self.id_lider.clone(),
+        }
+    }
+
     /// Comienza la busqueda de un nuevo lider
     pub fn buscar_nuevo_lider(&mut self) {
         if self.stop.load(Ordering::Relaxed) {
@@ -322,7 +355,7 @@ impl EleccionLider {
     fn clone(&self) -> EleccionLider {
         EleccionLider {
             id: self.id,
-            protocolo: self.protocolo.clone(),
+            protocolo: self.protocolo.try_clone(),
             id_lider: self.id_lider.clone(),
             obtuve_ok: self.obtuve_ok.clone(),
             stop: self.stop.clone(),
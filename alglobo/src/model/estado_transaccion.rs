@@ -0,0 +1,130 @@
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::reloj::Reloj;
+
+/// EstadoTransaccion representa el estado de la transaccion.
+/// # Variantes
+/// Prepare: simboliza el estado de prepare completo. En este estado se puede
+/// realizar commit.
+/// Commit: simboliza el estado commit completo. Es decir, la transacción fue
+/// correctamente completada.
+/// Abort: simboliza el estado abort completo. Es decir, la transacción fue
+/// correctamente abortada.
+/// Finalize: simboliza que los participantes confirmaron el commit o el
+/// abort, por lo que la transacción no requiere más acciones.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EstadoTransaccion {
+    Prepare,
+    Commit,
+    Abort,
+    Finalize,
+}
+
+impl EstadoTransaccion {
+    /// Convierte el EstadoTransaccion a su código y lo devuelve.
+    pub fn codificar(&self) -> &'static str {
+        match self {
+            EstadoTransaccion::Prepare => "PREPARE",
+            EstadoTransaccion::Commit => "COMMIT",
+            EstadoTransaccion::Abort => "ABORT",
+            EstadoTransaccion::Finalize => "FINALIZE",
+        }
+    }
+
+    /// Convierte el código a EstadoTransaccion y lo devuelve.
+    /// Devuelve error si el código no matchea con ningún estado.
+    pub fn decodificar(codigo: &str) -> Resultado<EstadoTransaccion> {
+        match codigo {
+            "PREPARE" => Ok(EstadoTransaccion::Prepare),
+            "COMMIT" => Ok(EstadoTransaccion::Commit),
+            "ABORT" => Ok(EstadoTransaccion::Abort),
+            "FINALIZE" => Ok(EstadoTransaccion::Finalize),
+            _ => Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Estado de transaccion erroneo: {}",
+                codigo
+            )))),
+        }
+    }
+
+    /// Devuelve verdadero si una transacción puede pasar del estado `desde` al
+    /// estado `hacia`. Un estado `desde` vacío indica el primer registro de la
+    /// transacción. Registrar dos veces el mismo estado es válido, ya que los
+    /// mensajes se reintentan.
+    pub fn transicion_valida(desde: Option<EstadoTransaccion>, hacia: EstadoTransaccion) -> bool {
+        matches!(
+            (desde, hacia),
+            (None, EstadoTransaccion::Prepare)
+                | (Some(EstadoTransaccion::Prepare), EstadoTransaccion::Commit)
+                | (Some(EstadoTransaccion::Prepare), EstadoTransaccion::Abort)
+                | (Some(EstadoTransaccion::Commit), EstadoTransaccion::Finalize)
+                | (Some(EstadoTransaccion::Abort), EstadoTransaccion::Finalize)
+        ) || desde == Some(hacia)
+    }
+}
+
+/// Transicion registra un cambio de estado de una transacción y el momento
+/// en que ocurrió, en milisegundos desde UNIX_EPOCH. Una transición sin
+/// estado `desde` indica el primer estado conocido de la transacción.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Transicion {
+    pub desde: Option<EstadoTransaccion>,
+    pub hacia: EstadoTransaccion,
+    pub momento: u64,
+}
+
+/// MaquinaEstados mantiene el estado de una transacción y solo permite
+/// cambiarlo siguiendo las transiciones válidas de EstadoTransaccion,
+/// registrando el historial de transiciones realizadas.
+#[derive(Clone, Debug)]
+pub struct MaquinaEstados {
+    actual: EstadoTransaccion,
+    historial: Vec<Transicion>,
+}
+
+impl MaquinaEstados {
+    /// Devuelve una instancia de MaquinaEstados en el estado inicial dado.
+    /// Para las transacciones leídas del log el estado inicial es el de su
+    /// registro, que puede no ser Prepare.
+    pub fn new(inicial: EstadoTransaccion) -> Self {
+        MaquinaEstados {
+            actual: inicial,
+            historial: vec![Transicion {
+                desde: None,
+                hacia: inicial,
+                momento: Reloj::ahora_ms(),
+            }],
+        }
+    }
+
+    /// Devuelve el estado actual.
+    pub fn actual(&self) -> EstadoTransaccion {
+        self.actual
+    }
+
+    /// Devuelve las transiciones realizadas, comenzando por el estado
+    /// inicial.
+    pub fn historial(&self) -> &[Transicion] {
+        &self.historial
+    }
+
+    /// Cambia el estado actual al estado dado y registra la transición. Pasar
+    /// al estado actual no modifica el historial.
+    /// Devuelve error si la transición no es válida.
+    pub fn transicionar(&mut self, hacia: EstadoTransaccion) -> Resultado<()> {
+        if !EstadoTransaccion::transicion_valida(Some(self.actual), hacia) {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Transicion invalida de {} a {}",
+                self.actual.codificar(),
+                hacia.codificar()
+            ))));
+        }
+        if hacia != self.actual {
+            self.historial.push(Transicion {
+                desde: Some(self.actual),
+                hacia,
+                momento: Reloj::ahora_ms(),
+            });
+            self.actual = hacia;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use super::formato_entrada::{
+    validar_id, validar_metadatos, validar_monto, FormatoEntrada, CAMPO_METADATOS,
+};
+use super::pago::{Pago, DATOS_RESERVA};
+use std::collections::BTreeMap;
+
+/// Nombres de las columnas requeridas en el encabezado.
+const COLUMNAS: [&str; 3] = ["id", "monto_aerolinea", "monto_hotel"];
+
+/// FormatoCsvEncabezado interpreta archivos csv cuya primera línea nombra las
+/// columnas. Las columnas requeridas pueden estar en cualquier orden, las de
+/// los datos de la reserva son opcionales y las columnas adicionales se
+/// ignoran.
+#[derive(Debug)]
+pub struct FormatoCsvEncabezado {
+    posiciones: [usize; 3],
+    reserva: Vec<(&'static str, usize)>,
+    metadatos: Option<usize>,
+    columnas: usize,
+}
+
+impl FormatoCsvEncabezado {
+    /// Devuelve una instancia de FormatoCsvEncabezado, que debe leer el
+    /// encabezado antes de parsear pagos.
+    pub fn new() -> Self {
+        FormatoCsvEncabezado {
+            posiciones: [0, 1, 2],
+            reserva: Vec::new(),
+            metadatos: None,
+            columnas: COLUMNAS.len(),
+        }
+    }
+}
+
+impl Default for FormatoCsvEncabezado {
+    fn default() -> Self {
+        FormatoCsvEncabezado::new()
+    }
+}
+
+impl FormatoEntrada for FormatoCsvEncabezado {
+    fn tiene_encabezado(&self) -> bool {
+        true
+    }
+
+    /// Los nombres de las columnas no distinguen mayúsculas.
+    fn leer_encabezado(&mut self, encabezado: &str) -> Result<(), String> {
+        let nombres = encabezado
+            .split(',')
+            .map(|n| n.trim().to_lowercase())
+            .collect::<Vec<String>>();
+        for (posicion, columna) in self.posiciones.iter_mut().zip(COLUMNAS) {
+            *posicion = nombres
+                .iter()
+                .position(|n| n == columna)
+                .ok_or_else(|| format!("falta la columna {}", columna))?;
+        }
+        let posicion = |columna: &str| nombres.iter().position(|n| n == columna);
+        self.reserva = DATOS_RESERVA
+            .iter()
+            .filter_map(|columna| Some((*columna, posicion(columna)?)))
+            .collect();
+        self.metadatos = posicion(CAMPO_METADATOS);
+        self.columnas = nombres.len();
+        Ok(())
+    }
+
+    fn parsear(&self, contenido: &str) -> Result<Pago, String> {
+        let campos = contenido.split(',').collect::<Vec<&str>>();
+        if campos.len() != self.columnas {
+            return Err(format!(
+                "se esperaban {} campos y hay {}",
+                self.columnas,
+                campos.len()
+            ));
+        }
+        let [id, monto_aerolinea, monto_hotel] = self.posiciones;
+        let mut datos = self
+            .reserva
+            .iter()
+            .map(|(nombre, posicion)| (nombre.to_string(), campos[*posicion].to_string()))
+            .collect::<BTreeMap<String, String>>();
+        if let Some(posicion) = self.metadatos {
+            datos.append(&mut validar_metadatos(campos[posicion])?);
+        }
+        Pago::new(
+            validar_id(campos[id])?,
+            validar_monto(campos[monto_aerolinea], "aerolinea")?,
+            validar_monto(campos[monto_hotel], "hotel")?,
+        )
+        .con_datos(datos)
+    }
+}
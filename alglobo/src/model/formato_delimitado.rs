@@ -0,0 +1,55 @@
+use super::formato_entrada::{validar_id, validar_metadatos, validar_monto, FormatoEntrada};
+use super::pago::{Pago, DATOS_RESERVA};
+use std::collections::BTreeMap;
+
+/// Cantidad de campos obligatorios de cada línea.
+const CAMPOS_OBLIGATORIOS: usize = 3;
+/// Cantidad máxima de campos de cada línea: los obligatorios, los datos de la
+/// reserva y los metadatos.
+const CAMPOS_MAXIMOS: usize = CAMPOS_OBLIGATORIOS + DATOS_RESERVA.len() + 1;
+
+/// FormatoDelimitado interpreta líneas sin encabezado con los campos
+/// `id`, `monto_aerolinea` y `monto_hotel`, seguidos opcionalmente por los
+/// datos de la reserva, en ese orden, separados por un caracter dado. Los
+/// datos de la reserva finales pueden omitirse. Se usa para los formatos csv
+/// y tsv.
+#[derive(Debug)]
+pub struct FormatoDelimitado {
+    separador: char,
+}
+
+impl FormatoDelimitado {
+    /// Devuelve una instancia de FormatoDelimitado.
+    /// Recibe el caracter que separa los campos.
+    pub fn new(separador: char) -> Self {
+        FormatoDelimitado { separador }
+    }
+}
+
+impl FormatoEntrada for FormatoDelimitado {
+    fn parsear(&self, contenido: &str) -> Result<Pago, String> {
+        let campos = contenido.split(self.separador).collect::<Vec<&str>>();
+        if campos.len() < CAMPOS_OBLIGATORIOS || campos.len() > CAMPOS_MAXIMOS {
+            return Err(format!(
+                "se esperaban entre {} y {} campos y hay {}",
+                CAMPOS_OBLIGATORIOS,
+                CAMPOS_MAXIMOS,
+                campos.len()
+            ));
+        }
+        let mut datos = DATOS_RESERVA
+            .iter()
+            .zip(&campos[CAMPOS_OBLIGATORIOS..])
+            .map(|(nombre, valor)| (nombre.to_string(), valor.to_string()))
+            .collect::<BTreeMap<String, String>>();
+        if let Some(metadatos) = campos.get(CAMPOS_MAXIMOS - 1) {
+            datos.append(&mut validar_metadatos(metadatos)?);
+        }
+        Pago::new(
+            validar_id(campos[0])?,
+            validar_monto(campos[1], "aerolinea")?,
+            validar_monto(campos[2], "hotel")?,
+        )
+        .con_datos(datos)
+    }
+}
@@ -0,0 +1,176 @@
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::monto::{Moneda, Monto};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::path::Path;
+
+use super::formato_csv_encabezado::FormatoCsvEncabezado;
+use super::formato_delimitado::FormatoDelimitado;
+use super::formato_jsonl::FormatoJsonl;
+use super::pago::{Pago, PREFIJO_METADATO};
+
+/// Nombre del campo con los metadatos del pago.
+pub const CAMPO_METADATOS: &str = "metadatos";
+
+/// TipoFormato representa los formatos de archivo de entrada de pagos
+/// disponibles.
+/// # Variantes
+/// Csv: una línea `id,monto_aerolinea,monto_hotel` por pago, sin encabezado.
+/// En todos los formatos cada monto puede indicar su moneda, y cada pago
+/// puede tener además los datos de la reserva `cliente`, `vuelo`,
+/// `fecha_vuelo`, `hotel`, `check_in`, `check_out` y `metadatos`, que en Csv
+/// y Tsv van en ese orden a continuación de los montos.
+/// CsvEncabezado: csv con una primera línea que nombra las columnas `id`,
+/// `monto_aerolinea` y `monto_hotel`, en cualquier orden, y opcionalmente las
+/// de los datos de la reserva.
+/// Jsonl: un objeto JSON por línea con los campos `id`, `monto_aerolinea` y
+/// `monto_hotel`, y opcionalmente los de los datos de la reserva, con los
+/// metadatos como un objeto.
+/// Tsv: como Csv, con los campos separados por tabulaciones.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TipoFormato {
+    Csv,
+    CsvEncabezado,
+    Jsonl,
+    Tsv,
+}
+
+impl TipoFormato {
+    /// Convierte el TipoFormato a su código y lo devuelve.
+    pub fn codificar(&self) -> &'static str {
+        match self {
+            TipoFormato::Csv => "csv",
+            TipoFormato::CsvEncabezado => "csv-encabezado",
+            TipoFormato::Jsonl => "jsonl",
+            TipoFormato::Tsv => "tsv",
+        }
+    }
+
+    /// Convierte el código a TipoFormato y lo devuelve.
+    /// Devuelve error si el código no matchea con ningún formato.
+    pub fn decodificar(codigo: &str) -> Resultado<TipoFormato> {
+        match codigo {
+            "csv" => Ok(TipoFormato::Csv),
+            "csv-encabezado" => Ok(TipoFormato::CsvEncabezado),
+            "jsonl" => Ok(TipoFormato::Jsonl),
+            "tsv" => Ok(TipoFormato::Tsv),
+            _ => Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Formato de entrada erroneo: {}",
+                codigo
+            )))),
+        }
+    }
+
+    /// Devuelve el formato que corresponde a la extensión del archivo de la
+    /// ruta dada: `.tsv` para Tsv, `.jsonl` y `.ndjson` para Jsonl y Csv para
+    /// cualquier otra.
+    pub fn desde_ruta(ruta: &Path) -> TipoFormato {
+        match ruta.extension().and_then(|e| e.to_str()) {
+            Some("tsv") => TipoFormato::Tsv,
+            Some("jsonl") | Some("ndjson") => TipoFormato::Jsonl,
+            _ => TipoFormato::Csv,
+        }
+    }
+}
+
+/// FormatoEntrada abstrae la interpretación de las líneas de un archivo de
+/// entrada de pagos.
+pub trait FormatoEntrada: Send + Debug {
+    /// Devuelve verdadero si la primera línea no vacía del archivo es un
+    /// encabezado en lugar de un pago.
+    fn tiene_encabezado(&self) -> bool {
+        false
+    }
+
+    /// Interpreta el encabezado del archivo.
+    /// Devuelve el motivo por el cual no es válido si no lo es.
+    fn leer_encabezado(&mut self, _encabezado: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Convierte el contenido de una línea a Pago y lo devuelve.
+    /// Devuelve el motivo por el cual no es un pago válido si no lo es.
+    fn parsear(&self, contenido: &str) -> Result<Pago, String>;
+}
+
+/// Devuelve el FormatoEntrada del tipo dado.
+pub fn crear(tipo: TipoFormato) -> Box<dyn FormatoEntrada> {
+    match tipo {
+        TipoFormato::Csv => Box::new(FormatoDelimitado::new(',')),
+        TipoFormato::CsvEncabezado => Box::new(FormatoCsvEncabezado::new()),
+        TipoFormato::Jsonl => Box::new(FormatoJsonl {}),
+        TipoFormato::Tsv => Box::new(FormatoDelimitado::new('\t')),
+    }
+}
+
+/// Límite, exclusivo, de los ids de pago de los archivos de entrada. Los ids
+/// de los pagos de un archivo de la bandeja se desplazan en múltiplos del
+/// límite según el lote del archivo, y los de los pagos recibidos por socket
+/// tienen el bit más alto en 1.
+pub const LIMITE_ID_PAGO: usize = 1 << 32;
+
+/// Convierte el campo de id de un pago a número y lo devuelve.
+/// Devuelve el motivo por el cual no es válido si no lo es, incluyendo los
+/// ids desde `LIMITE_ID_PAGO`.
+pub fn validar_id(campo: &str) -> Result<usize, String> {
+    if campo.is_empty() || !campo.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("id de pago invalido '{}'", campo));
+    }
+    campo
+        .parse::<usize>()
+        .ok()
+        .filter(|id| *id < LIMITE_ID_PAGO)
+        .ok_or_else(|| format!("id de pago fuera de rango '{}'", campo))
+}
+
+/// Convierte el campo de un monto, que debe ser un número no negativo con dos
+/// decimales seguido opcionalmente por un espacio y el código de su moneda,
+/// como `12.50 USD`, a Monto y lo devuelve. Sin código, el monto está en la
+/// moneda por defecto. Recibe además el nombre del monto para informar el
+/// motivo por el cual no es válido si no lo es.
+pub fn validar_monto(campo: &str, nombre: &str) -> Result<Monto, String> {
+    let (decimal, moneda) = match campo.split_once(' ') {
+        Some((decimal, codigo)) => (
+            decimal,
+            Moneda::decodificar(codigo)
+                .map_err(|_| format!("moneda de {} invalida '{}'", nombre, codigo))?,
+        ),
+        None => (campo, Moneda::POR_DEFECTO),
+    };
+    let valido = match decimal.split_once('.') {
+        Some((entero, decimales)) => {
+            !entero.is_empty()
+                && entero.bytes().all(|b| b.is_ascii_digit())
+                && decimales.len() == 2
+                && decimales.bytes().all(|b| b.is_ascii_digit())
+        }
+        None => false,
+    };
+    if !valido {
+        return Err(format!(
+            "monto de {} invalido '{}': se espera un numero con dos decimales",
+            nombre, campo
+        ));
+    }
+    Monto::decodificar_decimal(decimal, moneda)
+        .map_err(|_| format!("monto de {} fuera de rango '{}'", nombre, campo))
+}
+
+/// Convierte el campo de metadatos de un pago, con el formato
+/// `clave=valor;clave=valor`, a datos de la reserva y los devuelve.
+/// Devuelve el motivo por el cual no es válido si no lo es.
+pub fn validar_metadatos(campo: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut metadatos = BTreeMap::new();
+    for metadato in campo.split(';').filter(|m| !m.is_empty()) {
+        let (nombre, valor) = metadato
+            .split_once('=')
+            .ok_or_else(|| format!("metadato invalido '{}': se espera clave=valor", metadato))?;
+        if metadatos
+            .insert(format!("{}{}", PREFIJO_METADATO, nombre), valor.to_string())
+            .is_some()
+        {
+            return Err(format!("metadato repetido '{}'", nombre));
+        }
+    }
+    Ok(metadatos)
+}
@@ -0,0 +1,79 @@
+use super::formato_entrada::{validar_id, validar_monto, FormatoEntrada, CAMPO_METADATOS};
+use super::pago::{Pago, DATOS_RESERVA, PREFIJO_METADATO};
+use common::monto::{Moneda, Monto};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// FormatoJsonl interpreta archivos JSON Lines, con un objeto por línea con
+/// los campos `id`, `monto_aerolinea` y `monto_hotel`. Los montos pueden ser
+/// números o strings con dos decimales, seguidos opcionalmente por la moneda.
+/// Los datos de la reserva son opcionales, con los metadatos como un objeto
+/// de strings o números, y los campos adicionales se ignoran.
+#[derive(Debug)]
+pub struct FormatoJsonl {}
+
+impl FormatoJsonl {
+    /// Devuelve el campo del objeto dado como texto, para validarlo como los
+    /// campos de los formatos delimitados.
+    fn campo(objeto: &Value, nombre: &str) -> Result<String, String> {
+        match objeto.get(nombre) {
+            Some(Value::String(texto)) => Ok(texto.clone()),
+            Some(Value::Number(numero)) => Ok(numero.to_string()),
+            Some(otro) => Err(format!("campo {} invalido '{}'", nombre, otro)),
+            None => Err(format!("falta el campo {}", nombre)),
+        }
+    }
+
+    /// Convierte el campo de un monto a Monto y lo devuelve. Los montos
+    /// numéricos pueden tener menos de dos decimales y se convierten desde su
+    /// representación en el JSON, sin pasar por punto flotante.
+    fn monto(objeto: &Value, nombre: &str, descripcion: &str) -> Result<Monto, String> {
+        let campo = FormatoJsonl::campo(objeto, nombre)?;
+        if !objeto[nombre].is_number() {
+            return validar_monto(&campo, descripcion);
+        }
+        Monto::decodificar_decimal(&campo, Moneda::POR_DEFECTO).map_err(|_| {
+            format!(
+                "monto de {} invalido '{}': se espera un numero no negativo con a lo sumo dos decimales",
+                descripcion, campo
+            )
+        })
+    }
+
+    /// Devuelve los datos de la reserva presentes en el objeto dado.
+    fn datos(objeto: &Value) -> Result<BTreeMap<String, String>, String> {
+        let mut datos = BTreeMap::new();
+        for nombre in DATOS_RESERVA.iter().filter(|n| objeto.get(n).is_some()) {
+            datos.insert(nombre.to_string(), FormatoJsonl::campo(objeto, nombre)?);
+        }
+        match objeto.get(CAMPO_METADATOS) {
+            Some(Value::Object(metadatos)) => {
+                for nombre in metadatos.keys() {
+                    datos.insert(
+                        format!("{}{}", PREFIJO_METADATO, nombre),
+                        FormatoJsonl::campo(&objeto[CAMPO_METADATOS], nombre)?,
+                    );
+                }
+            }
+            Some(otro) => return Err(format!("campo {} invalido '{}'", CAMPO_METADATOS, otro)),
+            None => {}
+        }
+        Ok(datos)
+    }
+}
+
+impl FormatoEntrada for FormatoJsonl {
+    fn parsear(&self, contenido: &str) -> Result<Pago, String> {
+        let objeto = serde_json::from_str::<Value>(contenido)
+            .map_err(|e| format!("JSON invalido: {}", e))?;
+        if !objeto.is_object() {
+            return Err("se esperaba un objeto JSON".to_string());
+        }
+        Pago::new(
+            validar_id(&FormatoJsonl::campo(&objeto, "id")?)?,
+            FormatoJsonl::monto(&objeto, "monto_aerolinea", "aerolinea")?,
+            FormatoJsonl::monto(&objeto, "monto_hotel", "hotel")?,
+        )
+        .con_datos(FormatoJsonl::datos(&objeto)?)
+    }
+}
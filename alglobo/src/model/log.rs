@@ -1,197 +1,640 @@
-use common::error::Resultado;
-use regex::Regex;
-use std::collections::HashMap;
-use std::fs;
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::{BufReader, Write};
+use common::campos;
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::id_transaccion::IdTransaccion;
+use common::monto::{Moneda, Monto};
+use common::motivo_aborto::{CausaAborto, MotivoAborto};
+use common::reloj::Reloj;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 
+use super::almacenamiento::{self, Almacenamiento, TipoAlmacenamiento};
+use super::cadena::{Hash, TAM_HASH};
 use super::config::Config;
+use super::coordinador_transaccion::WEBSERVICES;
+use super::eleccion_lider::TEAM_MEMBERS;
+use super::estado_transaccion::{EstadoTransaccion, MaquinaEstados, Transicion};
 use super::pago::Pago;
 
-/// EstadoTransaccion representa el estado de la transaccion.
-/// # Variantes
-/// Prepare: simboliza el estado de prepare completo. En este estado se puede
-/// realizar commit.
-/// Commit: simboliza el estado commit completo. Es decir, la transacción fue
-/// correctamente completada.
-/// Abort: simboliza el estado abort completo. Es decir, la transacción fue
-/// correctamente abortada.
-#[derive(Clone, PartialEq)]
-pub enum EstadoTransaccion {
-    Prepare,
-    Commit,
-    Abort,
-    Finalize,
+/// Sello identifica quién y cuándo escribió un registro del log: el momento
+/// de la escritura en milisegundos desde UNIX_EPOCH, los milisegundos
+/// transcurridos desde el prepare de la transacción según un reloj
+/// monotónico, y el id del nodo y la época de liderazgo en la que escribió.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sello {
+    pub escritura: u64,
+    pub desde_prepare: u64,
+    pub nodo: usize,
+    pub epoca: u32,
 }
 
-/// Representa una transaccion. Contiene información sobre el pago actual y
-/// sobre el pago siguiente.
+/// Representa una transaccion, identificada por un id único entre todos los
+/// líderes. Contiene el id del pago actual y, si se conoce, el pago con sus
+/// montos y sus datos de la reserva; el id del pago siguiente y, si se
+/// conocen, su offset en el archivo de entrada y el archivo de la bandeja al
+/// que corresponde; el lote del último archivo abierto de la bandeja, que
+/// determina el de los archivos siguientes; los webservices que participan
+/// del commit o abort; su creación y su deadline, en milisegundos desde
+/// UNIX_EPOCH, y la versión de la tabla de cotizaciones con la que se
+/// convirtieron los montos. Si fue abortada contiene el motivo, y si se leyó
+/// del log, el sello de su escritura. El estado solo cambia siguiendo las
+/// transiciones válidas de su MaquinaEstados.
 #[derive(Clone)]
 pub struct Transaccion {
-    pub id: usize,
+    pub id: IdTransaccion,
     pub id_pago: usize,
     pub id_pago_prox: usize,
-    pub estado: EstadoTransaccion,
+    maquina: MaquinaEstados,
     pub pago: Option<Pago>,
+    pub participantes: Vec<usize>,
+    pub creacion: u64,
+    pub deadline: u64,
+    pub motivo_aborto: Option<MotivoAborto>,
+    pub sello: Option<Sello>,
+    pub offset_prox: Option<u64>,
+    pub version_cotizaciones: Option<u32>,
+    pub archivo: Option<String>,
+    pub lote: u32,
+    inicio: Instant,
 }
 
 impl Transaccion {
-    /// Devuelve una instancia de Transaccion.
+    /// Devuelve una instancia de Transaccion creada en el momento actual y
+    /// sin deadline.
     /// Recibe el id de la transaccion, el id del pago actual, el id del pago
     /// proximo y el estado de la transaccion.
-    pub fn new(id: usize, id_pago: usize, id_pago_prox: usize, estado: EstadoTransaccion) -> Self {
+    pub fn new(
+        id: IdTransaccion,
+        id_pago: usize,
+        id_pago_prox: usize,
+        estado: EstadoTransaccion,
+    ) -> Self {
         Self {
             id,
             id_pago,
             id_pago_prox,
-            estado,
+            maquina: MaquinaEstados::new(estado),
             pago: None,
+            participantes: (0..WEBSERVICES).collect(),
+            creacion: Reloj::ahora_ms(),
+            deadline: u64::MAX,
+            motivo_aborto: None,
+            sello: None,
+            offset_prox: None,
+            version_cotizaciones: None,
+            archivo: None,
+            lote: 0,
+            inicio: Instant::now(),
         }
     }
 
+    /// Establece la creación de una transacción leída del log. Como el reloj
+    /// monotónico no se comparte entre procesos, el tiempo transcurrido
+    /// hasta el momento se estima con la hora del sistema.
+    pub fn restaurar_creacion(&mut self, creacion: u64) {
+        let transcurrido = Duration::from_millis(Reloj::ahora_ms().saturating_sub(creacion));
+        self.creacion = creacion;
+        self.inicio = Instant::now()
+            .checked_sub(transcurrido)
+            .unwrap_or_else(Instant::now);
+    }
+
+    /// Sella la transacción con el momento actual, el id del nodo y la época
+    /// de liderazgo dados, antes de escribirla en el log.
+    pub fn sellar(&mut self, nodo: usize, epoca: u32) {
+        self.sello = Some(Sello {
+            escritura: Reloj::ahora_ms(),
+            desde_prepare: self.inicio.elapsed().as_millis() as u64,
+            nodo,
+            epoca,
+        });
+    }
+
+    /// Establece el deadline de la transacción a partir de su creación.
+    pub fn con_plazo(mut self, plazo: Duration) -> Self {
+        self.deadline = self.creacion.saturating_add(plazo.as_millis() as u64);
+        self
+    }
+
+    /// Devuelve verdadero si el deadline de la transacción ya pasó.
+    pub fn vencida(&self) -> bool {
+        Reloj::ahora_ms() > self.deadline
+    }
+
     /// Devuelve el pago
     pub fn get_pago(&self) -> Option<Pago> {
         self.pago.as_ref().cloned()
     }
 
+    /// Devuelve el estado actual de la transacción.
+    pub fn estado(&self) -> EstadoTransaccion {
+        self.maquina.actual()
+    }
+
+    /// Devuelve las transiciones de estado de la transacción, comenzando por
+    /// su estado inicial.
+    pub fn transiciones(&self) -> &[Transicion] {
+        self.maquina.historial()
+    }
+
+    /// Cambia el estado de la transacción al estado dado.
+    /// Devuelve error si la transición no es válida.
+    fn transicionar(&mut self, estado: EstadoTransaccion) -> Resultado<&Self> {
+        self.maquina.transicionar(estado).map_err(|e| {
+            ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Transaccion {}: {}",
+                self.id, e
+            )))
+        })?;
+        Ok(self)
+    }
+
     /// Cambiar el estado de la transacción a Prepare.
-    pub fn prepare(&mut self) -> &Self {
-        self.estado = EstadoTransaccion::Prepare;
-        self
+    /// Devuelve error si la transición no es válida.
+    pub fn prepare(&mut self) -> Resultado<&Self> {
+        self.transicionar(EstadoTransaccion::Prepare)
     }
 
     /// Cambiar el estado de la transacción a Commit.
-    pub fn commit(&mut self) -> &Self {
-        self.estado = EstadoTransaccion::Commit;
-        self
+    /// Devuelve error si la transición no es válida.
+    pub fn commit(&mut self) -> Resultado<&Self> {
+        self.transicionar(EstadoTransaccion::Commit)
     }
 
     /// Cambiar el estado de la transacción a Abort.
-    pub fn abort(&mut self) -> &Self {
-        self.estado = EstadoTransaccion::Abort;
-        self
+    /// Devuelve error si la transición no es válida.
+    pub fn abort(&mut self) -> Resultado<&Self> {
+        self.transicionar(EstadoTransaccion::Abort)
     }
 
     /// Cambiar el estado de la transacción a Finalize.
-    pub fn finalize(&mut self) -> &Self {
-        self.estado = EstadoTransaccion::Finalize;
-        self
+    /// Devuelve error si la transición no es válida.
+    pub fn finalize(&mut self) -> Resultado<&Self> {
+        self.transicionar(EstadoTransaccion::Finalize)
+    }
+
+    /// Continúa el historial de estados de un registro anterior de la misma
+    /// transacción con el estado de esta, de forma que el estado de una
+    /// transacción leída del log siga las mismas transiciones que al
+    /// escribirse.
+    /// Devuelve error si la transición desde el estado anterior no es válida.
+    pub fn continuar(&mut self, anterior: &Transaccion) -> Resultado<()> {
+        let estado = self.estado();
+        self.maquina = anterior.maquina.clone();
+        self.transicionar(estado).map(|_| ())
+    }
+
+    /// Convierte la transacción al formato binario del log y la devuelve.
+    /// Si tiene el pago, incluye sus montos, en centavos y con su moneda, y
+    /// sus datos de la reserva, de forma que la transacción se pueda retomar
+    /// sin volver a leer el archivo de entrada. Luego se incluyen, si los
+    /// tiene, el sello de la escritura, el offset del pago siguiente en el
+    /// archivo de entrada, la versión de la tabla de cotizaciones y el
+    /// archivo de la bandeja de entrada; siempre el lote de la bandeja, y el
+    /// motivo del abort, si lo tiene. El registro termina con el hash del
+    /// registro anterior del log, que lo encadena al mismo.
+    pub fn codificar(&self, hash_anterior: &Hash) -> Vec<u8> {
+        let mut bytes = vec![VERSION_REGISTRO];
+        bytes.extend_from_slice(&self.id.a_numero().to_le_bytes());
+        bytes.extend_from_slice(&(self.id_pago as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.id_pago_prox as u64).to_le_bytes());
+        bytes.push(match self.estado() {
+            EstadoTransaccion::Prepare => 0,
+            EstadoTransaccion::Commit => 1,
+            EstadoTransaccion::Abort => 2,
+            EstadoTransaccion::Finalize => 3,
+        });
+        bytes.extend_from_slice(&self.creacion.to_le_bytes());
+        bytes.extend_from_slice(&self.deadline.to_le_bytes());
+        bytes.push(self.participantes.len() as u8);
+        bytes.extend(self.participantes.iter().map(|p| *p as u8));
+        match self.pago.as_ref() {
+            Some(pago) => {
+                bytes.push(1);
+                for monto in [pago.get_monto_aerolinea(), pago.get_monto_hotel()] {
+                    bytes.extend_from_slice(&monto.centavos().to_le_bytes());
+                    bytes.extend_from_slice(monto.moneda().codigo().as_bytes());
+                }
+                let datos = campos::codificar(&pago.get_datos());
+                bytes.extend_from_slice(&(datos.len() as u64).to_le_bytes());
+                bytes.extend_from_slice(datos.as_bytes());
+            }
+            _ => bytes.push(0),
+        }
+        match self.sello {
+            Some(sello) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&sello.escritura.to_le_bytes());
+                bytes.extend_from_slice(&sello.desde_prepare.to_le_bytes());
+                bytes.extend_from_slice(&(sello.nodo as u64).to_le_bytes());
+                bytes.extend_from_slice(&(sello.epoca as u64).to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+        match self.offset_prox {
+            Some(offset) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&offset.to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+        match self.version_cotizaciones {
+            Some(version) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&(version as u64).to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+        match &self.archivo {
+            Some(archivo) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&(archivo.len() as u64).to_le_bytes());
+                bytes.extend_from_slice(archivo.as_bytes());
+            }
+            None => bytes.push(0),
+        }
+        bytes.extend_from_slice(&(self.lote as u64).to_le_bytes());
+        match &self.motivo_aborto {
+            Some(motivo) => {
+                bytes.push(1);
+                for texto in [motivo.causa.codificar(), motivo.descripcion.as_str()] {
+                    bytes.extend_from_slice(&(texto.len() as u64).to_le_bytes());
+                    bytes.extend_from_slice(texto.as_bytes());
+                }
+            }
+            None => bytes.push(0),
+        }
+        bytes.extend_from_slice(hash_anterior);
+        bytes
+    }
+
+    /// Convierte un registro en el formato binario del log a Transaccion y
+    /// la devuelve. Devuelve error si el registro es invalido.
+    pub fn decodificar(bytes: &[u8]) -> Resultado<Transaccion> {
+        Ok(Transaccion::decodificar_encadenado(bytes)?.0)
+    }
+
+    /// Convierte un registro en el formato binario del log a Transaccion y
+    /// la devuelve junto al hash del registro anterior.
+    /// Devuelve error si el registro es invalido.
+    pub fn decodificar_encadenado(bytes: &[u8]) -> Resultado<(Transaccion, Hash)> {
+        let mut lector = LectorRegistro::new(bytes);
+        let version = lector.u8()?;
+        if version != VERSION_REGISTRO {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Log corrupto: version de registro desconocida {}",
+                version
+            ))));
+        }
+        let id = IdTransaccion::desde_numero(lector.u64()?);
+        let id_pago = lector.u64()? as usize;
+        let id_pago_prox = lector.u64()? as usize;
+        let estado = match lector.u8()? {
+            0 => EstadoTransaccion::Prepare,
+            1 => EstadoTransaccion::Commit,
+            2 => EstadoTransaccion::Abort,
+            3 => EstadoTransaccion::Finalize,
+            e => {
+                return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                    "Log corrupto: estado de transaccion desconocido {}",
+                    e
+                ))))
+            }
+        };
+
+        let mut transaccion = Transaccion::new(id, id_pago, id_pago_prox, estado);
+        transaccion.restaurar_creacion(lector.u64()?);
+        transaccion.deadline = lector.u64()?;
+        let cantidad = lector.u8()?;
+        transaccion.participantes = (0..cantidad)
+            .map(|_| lector.u8().map(|p| p as usize))
+            .collect::<Resultado<Vec<usize>>>()?;
+        if lector.u8()? == 1 {
+            transaccion.pago = Some(
+                Pago::new(id_pago, lector.monto()?, lector.monto()?)
+                    .con_datos(lector.datos()?)
+                    .map_err(|e| {
+                        ErrorApp::Interno(ErrorInterno::new(&format!(
+                            "Log corrupto: datos de reserva invalidos: {}",
+                            e
+                        )))
+                    })?,
+            );
+        }
+        if lector.u8()? == 1 {
+            transaccion.sello = Some(Sello {
+                escritura: lector.u64()?,
+                desde_prepare: lector.u64()?,
+                nodo: lector.u64()? as usize,
+                epoca: lector.u64()? as u32,
+            });
+        }
+        if lector.u8()? == 1 {
+            transaccion.offset_prox = Some(lector.u64()?);
+        }
+        if lector.u8()? == 1 {
+            transaccion.version_cotizaciones = Some(lector.u64()? as u32);
+        }
+        if lector.u8()? == 1 {
+            transaccion.archivo = Some(lector.texto()?);
+        }
+        transaccion.lote = lector.u64()? as u32;
+        if lector.u8()? == 1 {
+            let causa = CausaAborto::decodificar(&lector.texto()?).map_err(|e| {
+                ErrorApp::Interno(ErrorInterno::new(&format!("Log corrupto: {}", e)))
+            })?;
+            transaccion.motivo_aborto = Some(MotivoAborto::new(causa, &lector.texto()?));
+        }
+        let mut hash_anterior = [0; TAM_HASH];
+        hash_anterior.copy_from_slice(lector.leer(TAM_HASH)?);
+
+        Ok((transaccion, hash_anterior))
     }
 }
 
-/// Representa un log system.
+/// Versión del formato binario de los registros del log.
+const VERSION_REGISTRO: u8 = 1;
+
+/// Lee en orden los campos de un registro binario del log.
+pub(crate) struct LectorRegistro<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> LectorRegistro<'a> {
+    /// Devuelve un lector posicionado al comienzo del registro dado.
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        LectorRegistro { bytes, pos: 0 }
+    }
+
+    /// Devuelve los próximos `cantidad` bytes del registro.
+    pub(crate) fn leer(&mut self, cantidad: usize) -> Resultado<&[u8]> {
+        if self.bytes.len() - self.pos < cantidad {
+            return Err(ErrorApp::Interno(ErrorInterno::new(
+                "Log corrupto: registro incompleto",
+            )));
+        }
+        self.pos += cantidad;
+        Ok(&self.bytes[self.pos - cantidad..self.pos])
+    }
+
+    pub(crate) fn u8(&mut self) -> Resultado<u8> {
+        Ok(self.leer(1)?[0])
+    }
+
+    pub(crate) fn u64(&mut self) -> Resultado<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.leer(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn monto(&mut self) -> Resultado<Monto> {
+        let centavos = self.u64()?;
+        let moneda = String::from_utf8(self.leer(3)?.to_vec())?;
+        Ok(Monto::new(centavos, Moneda::decodificar(&moneda)?))
+    }
+
+    fn texto(&mut self) -> Resultado<String> {
+        let largo = self.u64()? as usize;
+        Ok(String::from_utf8(self.leer(largo)?.to_vec())?)
+    }
+
+    fn datos(&mut self) -> Resultado<BTreeMap<String, String>> {
+        campos::decodificar(&self.texto()?)
+    }
+}
+
+/// Representa un log system. Delega la persistencia de las transacciones y
+/// de los pagos fallidos en el Almacenamiento indicado por la configuración.
+/// Cada registro se sella con el id del nodo y la época de liderazgo en la
+/// que se abrió el log, que también compone los ids de las transacciones
+/// nuevas.
 pub struct Log {
-    archivo: File,
-    log: HashMap<usize, Transaccion>,
-    ultima_trans: Option<Transaccion>,
+    almacenamiento: Box<dyn Almacenamiento>,
+    nodo: usize,
+    epoca: u32,
 }
 
 impl Log {
-    /// Genera una instancia de la clase.
-    /// Recibe un path donde dicho archivo debe ser construido.
-    pub fn new() -> Resultado<Self> {
-        let archivo = fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .append(true)
-            .create(true)
-            .open(Config::ruta_logs())?;
-
-        let mut log = Log {
-            archivo,
-            log: HashMap::new(),
-            ultima_trans: None,
-        };
+    /// Genera una instancia de la clase con el almacenamiento de la
+    /// configuración para el nodo de id dado, recuperando el estado
+    /// persistido en el mismo, y comienza una nueva época de liderazgo.
+    /// Devuelve error si el estado persistido está corrupto.
+    pub fn new(config: &Config, nodo: usize) -> Resultado<Self> {
+        let mut log = Log::reparar(config)?;
+        log.nodo = nodo;
+        log.epoca = Log::epoca_siguiente(log.almacenamiento.epoca_maxima()?, nodo);
+        log.almacenamiento.registrar_epoca(log.epoca)?;
+        println!("[Log]: Nodo {} inicia la epoca {}", nodo, log.epoca);
+        Ok(log)
+    }
 
-        log.leer_archivo();
+    /// Devuelve la menor época mayor a la dada que pertenece al nodo de id
+    /// dado. Las épocas de cada nodo son las congruentes con su id módulo la
+    /// cantidad de nodos, de forma que dos nodos nunca comiencen la misma
+    /// época aunque lean un estado desactualizado.
+    fn epoca_siguiente(epoca_maxima: u32, nodo: usize) -> u32 {
+        let nodos = TEAM_MEMBERS as u32;
+        let minima = epoca_maxima + 1;
+        minima + (nodo as u32 % nodos + nodos - minima % nodos) % nodos
+    }
 
-        Ok(log)
+    /// Genera una instancia de la clase con el almacenamiento de la
+    /// configuración, recuperando el estado persistido en el mismo y
+    /// reparando lo que haya quedado incompleto, sin iniciar una época de
+    /// liderazgo. Devuelve error si el estado persistido está corrupto o si
+    /// la configuración pide auditar un almacenamiento que no encadena los
+    /// registros.
+    pub fn reparar(config: &Config) -> Resultado<Self> {
+        Log::validar_auditoria(config)?;
+        let mut almacenamiento = almacenamiento::crear(config)?;
+        almacenamiento.recuperar()?;
+        Ok(Log {
+            almacenamiento,
+            nodo: 0,
+            epoca: 0,
+        })
     }
 
-    /// Crea una nueva transaccion inicializada y la devuelve
-    pub fn nueva_transaccion(&self, id_pago: usize, id_prox_pago: usize) -> Transaccion {
-        //La idea es que devuelva una transaccion semi inicializada, con el id seteado.
-        //Luego habra que cargarle los demas campos
-        let id = self.ultima_trans.as_ref().map(|t| t.id).unwrap_or(0);
-        Transaccion::new(id + 1, id_pago, id_prox_pago, EstadoTransaccion::Prepare)
+    /// Genera una instancia de la clase con el almacenamiento de la
+    /// configuración abierto en modo de lectura y sin recuperar su estado,
+    /// para inspeccionar el historial de transacciones sin modificarlo.
+    pub fn abrir_lectura(config: &Config) -> Resultado<Self> {
+        Ok(Log {
+            almacenamiento: almacenamiento::abrir_lectura(config)?,
+            nodo: 0,
+            epoca: 0,
+        })
     }
 
-    /// Recibe un id y devuelve una transacción si lo contiene o None si no.
-    pub fn obtener(&self, id: &usize) -> Option<Transaccion> {
-        self.log.get(id).cloned()
+    /// Verifica que el estado persistido en el almacenamiento de la
+    /// configuración se pueda recuperar, sin modificarlo. Devuelve error si
+    /// está corrupto o si la configuración pide auditar un almacenamiento
+    /// que no encadena los registros.
+    pub fn verificar(config: &Config) -> Resultado<()> {
+        Log::validar_auditoria(config)?;
+        almacenamiento::crear(config)?.verificar()
     }
 
-    /// Inserta una transacción en el log de transacciones.
-    pub fn insertar(&mut self, transaccion: &Transaccion) {
-        if let Some(t) = self.obtener(&transaccion.id) {
-            if t.estado == transaccion.estado {
-                return;
-            }
+    /// Devuelve error si la configuración indica una clave para auditar el
+    /// log con checkpoints firmados pero su almacenamiento no encadena los
+    /// registros, ya que solo lo hace el almacenamiento en archivo.
+    fn validar_auditoria(config: &Config) -> Resultado<()> {
+        if config.ruta_clave.is_some() && config.almacenamiento != TipoAlmacenamiento::Archivo {
+            return Err(ErrorApp::Interno(ErrorInterno::new(
+                "La auditoria del log con una clave requiere el almacenamiento en archivo",
+            )));
         }
-        let salida = self.formatear_transaccion(transaccion);
-        self.log.insert(transaccion.id, transaccion.clone());
-        writeln!(self.archivo, "{}", salida).expect("Error al escribir en el archivo de log");
-        self.ultima_trans = Some(transaccion.clone());
+        Ok(())
     }
 
-    /// Recibe una transaccion y devuelve un String formateado
-    fn formatear_transaccion(&self, t: &Transaccion) -> String {
-        let estado = match &t.estado {
-            EstadoTransaccion::Commit => "COMMIT",
-            EstadoTransaccion::Abort => "ABORT",
-            EstadoTransaccion::Prepare => "PREPARE",
-            EstadoTransaccion::Finalize => "FINALIZE",
-        };
+    /// Devuelve el id siguiente al mayor id de transacción del log dentro de
+    /// la época del log, o el primero de la época si no tiene transacciones.
+    /// Devuelve error si no se pudo leer el almacenamiento.
+    fn id_siguiente(&self) -> Resultado<IdTransaccion> {
+        let maximo = self.almacenamiento.id_maximo()?;
+        if maximo.epoca == self.epoca {
+            Ok(maximo.siguiente())
+        } else {
+            Ok(IdTransaccion::new(self.epoca, 1))
+        }
+    }
 
-        format!("{},{},{},{}", t.id, t.id_pago, t.id_pago_prox, estado)
+    /// Crea una nueva transaccion inicializada con el plazo indicado y la
+    /// devuelve.
+    /// Devuelve error si no se pudo leer el almacenamiento.
+    pub fn nueva_transaccion(
+        &self,
+        id_pago: usize,
+        id_prox_pago: usize,
+        plazo: Duration,
+    ) -> Resultado<Transaccion> {
+        //La idea es que devuelva una transaccion semi inicializada, con el id seteado.
+        //Luego habra que cargarle los demas campos
+        Ok(Transaccion::new(
+            self.id_siguiente()?,
+            id_pago,
+            id_prox_pago,
+            EstadoTransaccion::Prepare,
+        )
+        .con_plazo(plazo))
     }
 
-    /// Procesa completamente el archivo de logs, inicializando las variables internas
-    fn leer_archivo(&mut self) {
-        let matcher = Regex::new(r"^(\d+),(\d+),(\d+),(COMMIT|ABORT|PREPARE|FINALIZE)$")
-            .expect("Error al crear la regex, posiblemente es invalida");
-        let reader = BufReader::new(&self.archivo);
+    /// Crea una transaccion inicializada con el plazo indicado por cada pago y
+    /// las devuelve. Los ids de las transacciones son consecutivos dentro de
+    /// la época del log.
+    /// Devuelve error si no se pudo leer el almacenamiento.
+    pub fn nuevas_transacciones(
+        &self,
+        ids_pago: &[usize],
+        id_prox_pago: usize,
+        plazo: Duration,
+    ) -> Resultado<Vec<Transaccion>> {
+        let id_siguiente = self.id_siguiente()?;
+        Ok(ids_pago
+            .iter()
+            .enumerate()
+            .map(|(i, id_pago)| {
+                Transaccion::new(
+                    IdTransaccion::new(id_siguiente.epoca, id_siguiente.secuencia + i as u32),
+                    *id_pago,
+                    id_prox_pago,
+                    EstadoTransaccion::Prepare,
+                )
+                .con_plazo(plazo)
+            })
+            .collect())
+    }
 
-        let mut ultimo_id = 0;
+    /// Devuelve las transacciones que no fueron finalizadas, ordenadas por id.
+    /// Devuelve error si no se pudo leer el almacenamiento.
+    pub fn transacciones_pendientes(&self) -> Resultado<Vec<Transaccion>> {
+        self.almacenamiento.transacciones_pendientes()
+    }
+
+    /// Recibe un id y devuelve una transacción si lo contiene o None si no.
+    /// Devuelve error si no se pudo leer el almacenamiento.
+    pub fn obtener(&self, id: &IdTransaccion) -> Resultado<Option<Transaccion>> {
+        self.almacenamiento.obtener(*id)
+    }
+
+    /// Devuelve verdadero si alguna transacción del pago de id pasado por
+    /// parámetro llegó a commitearse.
+    /// Devuelve error si no se pudo leer el almacenamiento.
+    pub fn pago_confirmado(&self, id_pago: &usize) -> Resultado<bool> {
+        self.almacenamiento.pago_confirmado(*id_pago)
+    }
 
-        for linea in reader.lines() {
-            let linea = &linea.expect("Error al leer del archivo de log");
-            let cap = match matcher.captures(linea) {
-                None => continue,
-                Some(value) => value,
-            };
-            //No deberia fallar si ya paso la regex
-            let transaccion = self
-                .parsear_transaccion(cap)
-                .expect("Error al parsear transaccion");
-            ultimo_id = transaccion.id;
-            self.log.insert(transaccion.id, transaccion);
+    /// Inserta una transacción en el log de transacciones, sellada con el
+    /// nodo y la época del log. Si el log ya tiene la transacción en el
+    /// mismo estado no se vuelve a insertar.
+    /// Devuelve error si el log tiene la transacción en un estado desde el
+    /// cual no se puede pasar al de la transacción dada, o si no se pudo
+    /// leer o escribir el almacenamiento.
+    pub fn insertar(&mut self, transaccion: &Transaccion) -> Resultado<()> {
+        let mut registro = transaccion.clone();
+        // Solo el registro del prepare incluye el pago, los siguientes lo
+        // conservan del anterior
+        if registro.estado() != EstadoTransaccion::Prepare {
+            registro.pago = None;
         }
+        // Solo el registro del abort incluye el motivo, de forma que un
+        // abort retomado por otro líder informe la misma causa
+        if registro.estado() != EstadoTransaccion::Abort {
+            registro.motivo_aborto = None;
+        }
+        match self.obtener(&transaccion.id)? {
+            Some(t) if t.estado() == transaccion.estado() => return Ok(()),
+            Some(t) => registro.continuar(&t)?,
+            None if !EstadoTransaccion::transicion_valida(None, transaccion.estado()) => {
+                return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                    "Transaccion {}: transicion invalida de INICIO a {}",
+                    transaccion.id,
+                    transaccion.estado().codificar()
+                ))))
+            }
+            None => {}
+        }
+        registro.sellar(self.nodo, self.epoca);
+        self.almacenamiento.insertar(&registro)
+    }
+
+    /// Sincroniza a disco los registros escritos desde la última
+    /// sincronización, según la política de durabilidad del log.
+    /// Devuelve error si no se pudo sincronizar el almacenamiento.
+    pub fn sincronizar(&mut self) -> Resultado<()> {
+        self.almacenamiento.sincronizar()
+    }
 
-        self.ultima_trans = self.log.get(&ultimo_id).cloned();
+    /// Devuelve la última transacción.
+    /// Devuelve error si no se pudo leer el almacenamiento.
+    pub fn ultima_transaccion(&self) -> Resultado<Option<Transaccion>> {
+        self.almacenamiento.ultima_transaccion()
     }
 
-    /// Recibe argumentos para crear una transaccion y la devuelve
-    fn parsear_transaccion(&self, argumentos: regex::Captures) -> Resultado<Transaccion> {
-        let trans_id = argumentos[1].parse::<usize>()?;
-        let pago_id = argumentos[2].parse::<usize>()?;
-        let prox_pago_id = argumentos[3].parse::<usize>()?;
-        let operacion = &argumentos[4];
+    /// Persiste un pago fallido junto al motivo del abort.
+    pub fn escribir_fallido(&mut self, pago: &Pago, motivo: Option<&MotivoAborto>) {
+        if let Err(e) = self.almacenamiento.escribir_fallido(pago, motivo) {
+            println!(
+                "[Log]: No se pudo guardar el pago fallido de id {}: {}",
+                pago.get_id(),
+                e
+            );
+        }
+    }
 
-        let estado = match operacion {
-            "COMMIT" => EstadoTransaccion::Commit,
-            "ABORT" => EstadoTransaccion::Abort,
-            "PREPARE" => EstadoTransaccion::Prepare,
-            "FINALIZE" => EstadoTransaccion::Finalize,
-            _ => panic!("Estado erroneo"),
-        };
+    /// Devuelve los registros de todas las transacciones del log, incluyendo
+    /// los archivados, en el orden en que se insertaron.
+    pub fn historial(&self) -> Resultado<Vec<Transaccion>> {
+        self.almacenamiento.historial()
+    }
 
-        Ok(Transaccion::new(trans_id, pago_id, prox_pago_id, estado))
+    /// Devuelve los pagos fallidos junto al motivo por el cual fallaron.
+    pub fn listar_fallidos(&self) -> Resultado<Vec<(Pago, Option<MotivoAborto>)>> {
+        self.almacenamiento.listar_fallidos()
     }
 
-    /// Devuelve la última transacción.
-    pub fn ultima_transaccion(&self) -> Option<Transaccion> {
-        self.ultima_trans.clone()
+    /// Quita el pago fallido de id dado y lo devuelve, si existe.
+    pub fn tomar_fallido(&mut self, id_pago: usize) -> Resultado<Option<Pago>> {
+        self.almacenamiento.tomar_fallido(id_pago)
     }
 }
@@ -0,0 +1,317 @@
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::id_transaccion::IdTransaccion;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::wal::{PoliticaDurabilidad, RegistroWal, Wal};
+
+/// Extensión de los archivos de segmento.
+const EXTENSION_SEGMENTO: &str = "seg";
+/// Cantidad de registros consecutivos que abarca cada entrada del índice.
+const REGISTROS_POR_BLOQUE: usize = 32;
+/// Tamaño del id con el que se prefija cada registro.
+const TAM_ID: usize = 8;
+
+/// Entrada del índice disperso de un segmento. Abarca un bloque de registros
+/// consecutivos e indica el offset del primero y el rango de ids de
+/// transacción que contiene.
+struct EntradaIndice {
+    offset: u64,
+    id_min: IdTransaccion,
+    id_max: IdTransaccion,
+    registros: usize,
+}
+
+/// Segmento del log junto a su índice disperso.
+struct Segmento {
+    numero: u64,
+    indice: Vec<EntradaIndice>,
+}
+
+impl Segmento {
+    /// Agrega al índice el registro de la transaccion de id dado que
+    /// comienza en el offset indicado.
+    fn indexar(&mut self, id: IdTransaccion, offset: u64) {
+        match self.indice.last_mut() {
+            Some(entrada) if entrada.registros < REGISTROS_POR_BLOQUE => {
+                entrada.id_min = entrada.id_min.min(id);
+                entrada.id_max = entrada.id_max.max(id);
+                entrada.registros += 1;
+            }
+            _ => self.indice.push(EntradaIndice {
+                offset,
+                id_min: id,
+                id_max: id,
+                registros: 1,
+            }),
+        }
+    }
+
+    /// Devuelve verdadero si el segmento no tiene registros.
+    fn vacio(&self) -> bool {
+        self.indice.is_empty()
+    }
+}
+
+/// LogSegmentado implementa un log de solo agregado dividido en archivos de
+/// segmento de tamaño fijo. Cada registro se asocia al id de su transacción
+/// y se mantiene en memoria solo un índice disperso por segmento, por lo que
+/// las búsquedas por id leen del disco únicamente los bloques que pueden
+/// contenerlo. Los segmentos completos se sellan y pueden archivarse.
+pub struct LogSegmentado {
+    directorio: PathBuf,
+    directorio_archivo: PathBuf,
+    politica: PoliticaDurabilidad,
+    tam_segmento: u64,
+    sellados: Vec<Segmento>,
+    activo: Segmento,
+    wal: Wal,
+}
+
+impl LogSegmentado {
+    /// Abre el log segmentado del directorio dado, creándolo si no existe, y
+    /// devuelve los registros de los segmentos posteriores al número
+    /// indicado, en orden. Los segmentos anteriores o iguales a dicho número
+    /// ya fueron cubiertos por un snapshot y se mueven al directorio de
+    /// archivo.
+    /// Devuelve error si algún segmento está corrupto.
+    pub fn abrir(
+        directorio: &Path,
+        directorio_archivo: &Path,
+        politica: PoliticaDurabilidad,
+        tam_segmento: u64,
+        desde: u64,
+    ) -> Resultado<(Self, Vec<Vec<u8>>)> {
+        fs::create_dir_all(directorio)?;
+        let mut numeros = LogSegmentado::numeros_segmentos(directorio)?;
+
+        let cubiertos = numeros.iter().filter(|n| **n <= desde).count();
+        for numero in numeros.drain(..cubiertos) {
+            LogSegmentado::archivar_segmento(directorio, directorio_archivo, numero)?;
+        }
+
+        let numero_activo = numeros.pop().unwrap_or(desde + 1);
+        let mut sellados = Vec::new();
+        let mut registros = Vec::new();
+
+        for numero in numeros {
+            let leidos = Wal::leer_completo(&LogSegmentado::ruta_segmento(directorio, numero))?;
+            sellados.push(LogSegmentado::indexar_segmento(
+                numero,
+                &leidos,
+                &mut registros,
+            )?);
+        }
+
+        let (wal, leidos) = Wal::abrir(
+            &LogSegmentado::ruta_segmento(directorio, numero_activo),
+            politica,
+        )?;
+        let activo = LogSegmentado::indexar_segmento(numero_activo, &leidos, &mut registros)?;
+
+        Ok((
+            LogSegmentado {
+                directorio: directorio.to_path_buf(),
+                directorio_archivo: directorio_archivo.to_path_buf(),
+                politica,
+                tam_segmento,
+                sellados,
+                activo,
+                wal,
+            },
+            registros,
+        ))
+    }
+
+    /// Lee los registros de todos los segmentos del directorio dado sin
+    /// modificarlos. Devuelve error si algún segmento está corrupto.
+    pub fn leer(directorio: &Path) -> Resultado<Vec<Vec<u8>>> {
+        let numeros = LogSegmentado::numeros_segmentos(directorio)?;
+        let mut registros = Vec::new();
+
+        for (i, numero) in numeros.iter().enumerate() {
+            let ruta = LogSegmentado::ruta_segmento(directorio, *numero);
+            // El último segmento puede estar siendo escrito por el líder
+            let leidos = if i + 1 == numeros.len() {
+                Wal::leer(&ruta)?
+            } else {
+                Wal::leer_completo(&ruta)?
+                    .into_iter()
+                    .map(|(_, r)| r)
+                    .collect()
+            };
+            for registro in leidos {
+                registros.push(LogSegmentado::separar_id(&registro)?.1.to_vec());
+            }
+        }
+
+        Ok(registros)
+    }
+
+    /// Agrega el registro de la transaccion de id dado al segmento activo,
+    /// sellándolo y comenzando uno nuevo si alcanzó el tamaño de segmento.
+    pub fn agregar(&mut self, id: IdTransaccion, registro: &[u8]) -> Resultado<()> {
+        let mut datos = Vec::with_capacity(TAM_ID + registro.len());
+        datos.extend_from_slice(&id.a_numero().to_le_bytes());
+        datos.extend_from_slice(registro);
+
+        let offset = self.wal.escribir(&datos)?;
+        self.activo.indexar(id, offset);
+
+        if self.wal.largo() >= self.tam_segmento {
+            self.rotar()?;
+        }
+        Ok(())
+    }
+
+    /// Devuelve el último registro de la transaccion de id dado, buscando
+    /// desde el segmento más reciente hacia el más antiguo, o None si no se
+    /// encuentra en los segmentos no archivados.
+    pub fn buscar(&self, id: IdTransaccion) -> Resultado<Option<Vec<u8>>> {
+        for segmento in std::iter::once(&self.activo).chain(self.sellados.iter().rev()) {
+            let ruta = LogSegmentado::ruta_segmento(&self.directorio, segmento.numero);
+            for entrada in segmento.indice.iter().rev() {
+                if id < entrada.id_min || id > entrada.id_max {
+                    continue;
+                }
+                let bloque = Wal::leer_desde(&ruta, entrada.offset, entrada.registros)?;
+                for registro in bloque.iter().rev() {
+                    let (id_registro, datos) = LogSegmentado::separar_id(registro)?;
+                    if id_registro == id {
+                        return Ok(Some(datos.to_vec()));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Sincroniza a disco los registros escritos desde la última
+    /// sincronización.
+    pub fn sincronizar(&mut self) -> Resultado<()> {
+        self.wal.sincronizar()
+    }
+
+    /// Sella el segmento activo si tiene registros y devuelve el número del
+    /// último segmento sellado. Todos los registros escritos hasta el momento
+    /// quedan en segmentos con número menor o igual al devuelto.
+    pub fn sellar(&mut self) -> Resultado<u64> {
+        if !self.activo.vacio() {
+            self.rotar()?;
+        }
+        Ok(self.activo.numero - 1)
+    }
+
+    /// Mueve al directorio de archivo los segmentos sellados con número menor
+    /// o igual al indicado.
+    pub fn archivar(&mut self, hasta: u64) -> Resultado<()> {
+        while self.sellados.first().is_some_and(|s| s.numero <= hasta) {
+            let segmento = self.sellados.remove(0);
+            LogSegmentado::archivar_segmento(
+                &self.directorio,
+                &self.directorio_archivo,
+                segmento.numero,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Sella el segmento activo y comienza uno nuevo.
+    fn rotar(&mut self) -> Resultado<()> {
+        self.wal.sincronizar()?;
+        let numero = self.activo.numero + 1;
+        let (wal, _) = Wal::abrir(
+            &LogSegmentado::ruta_segmento(&self.directorio, numero),
+            self.politica,
+        )?;
+        self.wal = wal;
+        let sellado = std::mem::replace(
+            &mut self.activo,
+            Segmento {
+                numero,
+                indice: Vec::new(),
+            },
+        );
+        self.sellados.push(sellado);
+        Ok(())
+    }
+
+    /// Construye el índice de un segmento a partir de sus registros y agrega
+    /// el contenido de los mismos a los registros dados.
+    fn indexar_segmento(
+        numero: u64,
+        leidos: &[RegistroWal],
+        registros: &mut Vec<Vec<u8>>,
+    ) -> Resultado<Segmento> {
+        let mut segmento = Segmento {
+            numero,
+            indice: Vec::new(),
+        };
+        for (offset, registro) in leidos {
+            let (id, datos) = LogSegmentado::separar_id(registro)?;
+            segmento.indexar(id, *offset);
+            registros.push(datos.to_vec());
+        }
+        Ok(segmento)
+    }
+
+    /// Separa un registro de segmento en el id de su transacción y su
+    /// contenido.
+    fn separar_id(registro: &[u8]) -> Resultado<(IdTransaccion, &[u8])> {
+        if registro.len() < TAM_ID {
+            return Err(ErrorApp::Interno(ErrorInterno::new(
+                "Log corrupto: registro de segmento sin id",
+            )));
+        }
+        let mut id = [0; TAM_ID];
+        id.copy_from_slice(&registro[..TAM_ID]);
+        Ok((
+            IdTransaccion::desde_numero(u64::from_le_bytes(id)),
+            &registro[TAM_ID..],
+        ))
+    }
+
+    /// Devuelve los números de los segmentos del directorio dado, ordenados.
+    fn numeros_segmentos(directorio: &Path) -> Resultado<Vec<u64>> {
+        let entradas = match fs::read_dir(directorio) {
+            Ok(e) => e,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(ErrorApp::ErrorIO(e)),
+        };
+        let mut numeros = Vec::new();
+        for entrada in entradas {
+            let ruta = entrada?.path();
+            if ruta.extension().and_then(|e| e.to_str()) != Some(EXTENSION_SEGMENTO) {
+                continue;
+            }
+            if let Some(numero) = ruta
+                .file_stem()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.parse::<u64>().ok())
+            {
+                numeros.push(numero);
+            }
+        }
+        numeros.sort_unstable();
+        Ok(numeros)
+    }
+
+    /// Mueve el segmento de número dado al directorio de archivo.
+    fn archivar_segmento(
+        directorio: &Path,
+        directorio_archivo: &Path,
+        numero: u64,
+    ) -> Resultado<()> {
+        fs::create_dir_all(directorio_archivo)?;
+        fs::rename(
+            LogSegmentado::ruta_segmento(directorio, numero),
+            LogSegmentado::ruta_segmento(directorio_archivo, numero),
+        )?;
+        Ok(())
+    }
+
+    /// Devuelve la ruta del segmento de número dado en el directorio dado.
+    fn ruta_segmento(directorio: &Path, numero: u64) -> PathBuf {
+        directorio.join(format!("{:08}.{}", numero, EXTENSION_SEGMENTO))
+    }
+}
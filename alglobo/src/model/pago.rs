@@ -1,21 +1,158 @@
+use common::monto::Monto;
+use std::collections::BTreeMap;
+
+/// Nombre del dato con el id del cliente.
+pub const DATO_CLIENTE: &str = "cliente";
+/// Nombre del dato con el número de vuelo.
+pub const DATO_VUELO: &str = "vuelo";
+/// Nombre del dato con la fecha del vuelo.
+pub const DATO_FECHA_VUELO: &str = "fecha_vuelo";
+/// Nombre del dato con el id del hotel.
+pub const DATO_HOTEL: &str = "hotel";
+/// Nombre del dato con la fecha de check-in.
+pub const DATO_CHECK_IN: &str = "check_in";
+/// Nombre del dato con la fecha de check-out.
+pub const DATO_CHECK_OUT: &str = "check_out";
+/// Nombres de los datos de la reserva, sin contar los metadatos.
+pub const DATOS_RESERVA: [&str; 6] = [
+    DATO_CLIENTE,
+    DATO_VUELO,
+    DATO_FECHA_VUELO,
+    DATO_HOTEL,
+    DATO_CHECK_IN,
+    DATO_CHECK_OUT,
+];
+/// Prefijo del nombre de los datos que son metadatos.
+pub const PREFIJO_METADATO: &str = "meta.";
+
+/// Vuelo identifica el vuelo reservado con un pago. La fecha tiene el formato
+/// `AAAA-MM-DD`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Vuelo {
+    pub numero: String,
+    pub fecha: String,
+}
+
+/// Estadia identifica la reserva de hotel de un pago. Las fechas tienen el
+/// formato `AAAA-MM-DD`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Estadia {
+    pub hotel: String,
+    pub check_in: String,
+    pub check_out: String,
+}
+
 /// Pago representa un pago a realizar. Contiene el monto a pagar de la
-/// aerolínea y del hotel.
+/// aerolínea y del hotel, y opcionalmente los datos de la reserva: el id del
+/// cliente, el vuelo, la estadía en el hotel y metadatos libres.
 #[derive(Clone)]
 pub struct Pago {
     id: usize,
-    monto_aerolinea: f64,
-    monto_hotel: f64,
+    monto_aerolinea: Monto,
+    monto_hotel: Monto,
+    cliente: Option<String>,
+    vuelo: Option<Vuelo>,
+    estadia: Option<Estadia>,
+    metadatos: BTreeMap<String, String>,
 }
 
 impl Pago {
-    /// Devuelve una instancia de Pago.
+    /// Devuelve una instancia de Pago, sin datos de la reserva.
     /// Recibe el id del pago, el monto de la aerolinea y el monto del hotel.
-    pub fn new(id: usize, monto_aerolinea: f64, monto_hotel: f64) -> Pago {
+    pub fn new(id: usize, monto_aerolinea: Monto, monto_hotel: Monto) -> Pago {
         Pago {
             id,
             monto_aerolinea,
             monto_hotel,
+            cliente: None,
+            vuelo: None,
+            estadia: None,
+            metadatos: BTreeMap::new(),
+        }
+    }
+
+    /// Devuelve el pago con los datos de la reserva dados, por nombre. Los
+    /// datos vacíos se ignoran. El vuelo requiere número y fecha, y la
+    /// estadía requiere hotel, check-in y check-out, con el check-out
+    /// posterior al check-in. Los metadatos se nombran con `PREFIJO_METADATO`.
+    /// Devuelve el motivo por el cual los datos no son válidos si no lo son.
+    pub fn con_datos(mut self, datos: BTreeMap<String, String>) -> Result<Pago, String> {
+        let mut reserva = BTreeMap::new();
+        for (nombre, valor) in datos.into_iter().filter(|(_, v)| !v.is_empty()) {
+            match nombre.strip_prefix(PREFIJO_METADATO) {
+                Some("") => return Err("metadato sin nombre".to_string()),
+                Some(metadato) => {
+                    self.metadatos.insert(metadato.to_string(), valor);
+                }
+                None if DATOS_RESERVA.contains(&nombre.as_str()) => {
+                    reserva.insert(nombre, valor);
+                }
+                None => return Err(format!("dato de reserva desconocido '{}'", nombre)),
+            }
+        }
+
+        self.cliente = reserva.remove(DATO_CLIENTE);
+        self.vuelo = match (reserva.remove(DATO_VUELO), reserva.remove(DATO_FECHA_VUELO)) {
+            (Some(numero), Some(fecha)) => Some(Vuelo {
+                numero,
+                fecha: validar_fecha(fecha, DATO_FECHA_VUELO)?,
+            }),
+            (None, None) => None,
+            _ => return Err("el vuelo requiere numero y fecha".to_string()),
+        };
+        self.estadia = match (
+            reserva.remove(DATO_HOTEL),
+            reserva.remove(DATO_CHECK_IN),
+            reserva.remove(DATO_CHECK_OUT),
+        ) {
+            (Some(hotel), Some(check_in), Some(check_out)) => {
+                let check_in = validar_fecha(check_in, DATO_CHECK_IN)?;
+                let check_out = validar_fecha(check_out, DATO_CHECK_OUT)?;
+                if check_out <= check_in {
+                    return Err(format!(
+                        "el check_out '{}' no es posterior al check_in '{}'",
+                        check_out, check_in
+                    ));
+                }
+                Some(Estadia {
+                    hotel,
+                    check_in,
+                    check_out,
+                })
+            }
+            (None, None, None) => None,
+            _ => return Err("la estadia requiere hotel, check_in y check_out".to_string()),
+        };
+        Ok(self)
+    }
+
+    /// Devuelve los datos de la reserva por nombre, con el formato que recibe
+    /// `con_datos`.
+    pub fn get_datos(&self) -> BTreeMap<String, String> {
+        let mut datos = self
+            .metadatos
+            .iter()
+            .map(|(nombre, valor)| (format!("{}{}", PREFIJO_METADATO, nombre), valor.clone()))
+            .collect::<BTreeMap<String, String>>();
+        if let Some(cliente) = &self.cliente {
+            datos.insert(DATO_CLIENTE.to_string(), cliente.clone());
+        }
+        if let Some(vuelo) = &self.vuelo {
+            datos.insert(DATO_VUELO.to_string(), vuelo.numero.clone());
+            datos.insert(DATO_FECHA_VUELO.to_string(), vuelo.fecha.clone());
+        }
+        if let Some(estadia) = &self.estadia {
+            datos.insert(DATO_HOTEL.to_string(), estadia.hotel.clone());
+            datos.insert(DATO_CHECK_IN.to_string(), estadia.check_in.clone());
+            datos.insert(DATO_CHECK_OUT.to_string(), estadia.check_out.clone());
         }
+        datos
+    }
+
+    /// Devuelve el pago con el id dado en lugar del suyo.
+    pub fn con_id(mut self, id: usize) -> Pago {
+        self.id = id;
+        self
     }
 
     /// Devuelve el id del pago.
@@ -24,12 +161,70 @@ impl Pago {
     }
 
     /// Devuelve el monto de la aerolínea.
-    pub fn get_monto_aerolinea(&self) -> f64 {
+    pub fn get_monto_aerolinea(&self) -> Monto {
         self.monto_aerolinea
     }
 
     /// Devuelve el monto del hotel.
-    pub fn get_monto_hotel(&self) -> f64 {
+    pub fn get_monto_hotel(&self) -> Monto {
         self.monto_hotel
     }
+
+    /// Devuelve el id del cliente, si se conoce.
+    pub fn get_cliente(&self) -> Option<&String> {
+        self.cliente.as_ref()
+    }
+
+    /// Devuelve el vuelo reservado, si se conoce.
+    pub fn get_vuelo(&self) -> Option<&Vuelo> {
+        self.vuelo.as_ref()
+    }
+
+    /// Devuelve la estadía en el hotel, si se conoce.
+    pub fn get_estadia(&self) -> Option<&Estadia> {
+        self.estadia.as_ref()
+    }
+
+    /// Devuelve los metadatos del pago, por nombre y sin prefijo.
+    pub fn get_metadatos(&self) -> &BTreeMap<String, String> {
+        &self.metadatos
+    }
+}
+
+/// Devuelve la fecha dada si tiene el formato `AAAA-MM-DD` y es una fecha
+/// válida. Recibe además el nombre del dato para informar el motivo por el
+/// cual no es válida si no lo es.
+fn validar_fecha(fecha: String, nombre: &str) -> Result<String, String> {
+    let partes = fecha.split('-').collect::<Vec<&str>>();
+    let numero = |parte: &str, digitos: usize| {
+        if parte.len() == digitos && parte.bytes().all(|b| b.is_ascii_digit()) {
+            parte.parse::<u32>().ok()
+        } else {
+            None
+        }
+    };
+    let valida = match partes.as_slice() {
+        [anio, mes, dia] => match (numero(anio, 4), numero(mes, 2), numero(dia, 2)) {
+            (Some(anio), Some(mes), Some(dia)) => {
+                let bisiesto = (anio % 4 == 0 && anio % 100 != 0) || anio % 400 == 0;
+                let dias = match mes {
+                    1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+                    4 | 6 | 9 | 11 => 30,
+                    2 if bisiesto => 29,
+                    2 => 28,
+                    _ => 0,
+                };
+                dia >= 1 && dia <= dias
+            }
+            _ => false,
+        },
+        _ => false,
+    };
+    if !valida {
+        return Err(format!(
+            "{} invalida '{}': se espera una fecha AAAA-MM-DD",
+            nombre, fecha
+        ));
+    }
+    Ok(fecha)
 }
@@ -1,81 +1,592 @@
+use super::formato_entrada::{self, FormatoEntrada, TipoFormato, LIMITE_ID_PAGO};
 use super::pago::Pago;
-use common::error::Resultado;
-use regex::Regex;
+use super::wal::Wal;
+use common::error::{ErrorApp, ErrorInterno, Resultado};
 
 /// Parser implementa el parseo de los request que se encuentran en un archivo
-/// dado.
+/// dado, interpretando sus líneas según su formato, en el orden del archivo y
+/// sin importar sus ids. Mantiene un índice con el id, el offset y el número
+/// de línea de cada pago en el archivo, que se guarda junto al mismo, de
+/// forma de posicionarse en cualquier pago sin recorrer el archivo. Las
+/// líneas que no son un pago válido y los pagos con un id ya aparecido en el
+/// archivo se informan en un reporte de rechazados, también junto al archivo.
+/// Los pagos de un archivo de la bandeja de entrada se identifican por su id
+/// en el archivo desplazado según el lote del archivo, de forma que los ids
+/// de distintos archivos no coincidan.
 #[derive(Debug)]
 pub struct Parser {
     lector: io::BufReader<File>,
-    matcher: Regex,
-    posicion: usize,
+    ruta: PathBuf,
+    tipo: TipoFormato,
+    formato: Box<dyn FormatoEntrada>,
+    base: usize,
+    offset: u64,
+    linea: u64,
+    inicio: (u64, u64),
+    entradas: Vec<EntradaIndice>,
+    por_id: HashMap<usize, usize>,
+    fin_indice: u64,
+    lineas_indice: u64,
+    leido_hasta: u64,
+    ruta_rechazados: PathBuf,
+    rechazados: usize,
+}
+
+/// EntradaIndice indica el offset y el número de línea de un pago en el
+/// archivo de entrada.
+#[derive(Debug, Clone, Copy)]
+struct EntradaIndice {
+    id: usize,
+    offset: u64,
+    linea: u64,
 }
 
 use std::{
-    fs::File,
-    io::{self, prelude::*},
+    collections::HashMap,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, prelude::*, SeekFrom},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
 };
 
+/// Extensión que se agrega a la ruta del archivo de entrada para guardar su
+/// índice.
+const EXTENSION_INDICE: &str = ".indice";
+
+/// Extensión que se agrega a la ruta del archivo de entrada para guardar el
+/// reporte de sus líneas rechazadas.
+const EXTENSION_RECHAZADOS: &str = ".rechazados";
+
+/// Versión del formato del índice guardado.
+const VERSION_INDICE: u8 = 4;
+
+/// Tamaño en bytes de una entrada del índice guardado: el id del pago, su
+/// offset y su número de línea.
+const TAM_ENTRADA: usize = 24;
+
 impl Parser {
     /// Devuelve una instancia de Parser.
-    /// Recibe la ruta del archivo a ser procesado.
-    pub fn new(path: impl AsRef<std::path::Path>) -> Resultado<Parser> {
-        let file = File::open(path)?;
-        let parser = Parser {
+    /// Recibe la ruta del archivo a ser procesado y su formato. Si el índice
+    /// guardado no corresponde al archivo, porque no existe, el archivo se
+    /// modificó o se indexó con otro formato, se reconstruye y se guarda junto
+    /// al reporte de líneas rechazadas.
+    /// Devuelve error si el formato requiere un encabezado y el archivo no lo
+    /// tiene o no es válido.
+    pub fn new(path: impl AsRef<std::path::Path>, tipo: TipoFormato) -> Resultado<Parser> {
+        let file = File::open(&path)?;
+        let mut parser = Parser {
             lector: io::BufReader::new(file),
-            matcher: Regex::new(r"^(\d+),(\d+\.\d{2}),(\d+\.\d{2})$")?,
-            posicion: 0,
+            ruta: path.as_ref().to_path_buf(),
+            tipo,
+            formato: formato_entrada::crear(tipo),
+            base: 0,
+            offset: 0,
+            linea: 1,
+            inicio: (0, 1),
+            entradas: Vec::new(),
+            por_id: HashMap::new(),
+            fin_indice: 0,
+            lineas_indice: 0,
+            leido_hasta: 0,
+            ruta_rechazados: Parser::ruta_con_extension(path.as_ref(), EXTENSION_RECHAZADOS),
+            rechazados: 0,
         };
+        parser.leer_encabezado()?;
+        parser.inicio = (parser.offset, parser.linea);
+        parser.cargar_indice()?;
 
         Ok(parser)
     }
 
-    /// Parsea un pago correspondiente al id pasado por parámetro.
+    /// Devuelve el parser con los ids de sus pagos en el lote dado: el id de
+    /// cada pago es su id en el archivo más el lote por `LIMITE_ID_PAGO`. Sin
+    /// lote, los ids son los del archivo.
+    pub fn con_lote(mut self, lote: u32) -> Parser {
+        self.base = lote as usize * LIMITE_ID_PAGO;
+        self
+    }
+
+    /// Devuelve el offset en el archivo de la próxima línea a leer.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Devuelve la cantidad de líneas rechazadas del archivo.
+    pub fn rechazados(&self) -> usize {
+        self.rechazados
+    }
+
+    /// Devuelve la ruta del reporte de líneas rechazadas del archivo.
+    pub fn ruta_rechazados(&self) -> &Path {
+        &self.ruta_rechazados
+    }
+
+    /// Devuelve los ids de los pagos indexados del archivo, en el orden del
+    /// archivo.
+    pub fn ids(&self) -> Vec<usize> {
+        self.entradas.iter().map(|e| self.base + e.id).collect()
+    }
+
+    /// Devuelve las rutas de los archivos que se guardan junto al archivo de
+    /// entrada de la ruta dada: su índice y su reporte de líneas rechazadas.
+    pub fn rutas_asociadas(ruta: &Path) -> [PathBuf; 2] {
+        [
+            Parser::ruta_con_extension(ruta, EXTENSION_INDICE),
+            Parser::ruta_con_extension(ruta, EXTENSION_RECHAZADOS),
+        ]
+    }
+
+    /// Devuelve verdadero si la ruta dada corresponde a un archivo que se
+    /// guarda junto a un archivo de entrada, en lugar de a un archivo de
+    /// entrada.
+    pub fn es_asociado(ruta: &Path) -> bool {
+        let nombre = ruta.to_string_lossy();
+        nombre.ends_with(EXTENSION_INDICE) || nombre.ends_with(EXTENSION_RECHAZADOS)
+    }
+
+    /// Devuelve el id del próximo pago a leer según el índice, o None si no
+    /// quedan pagos indexados.
+    pub fn proximo_id(&self) -> Option<usize> {
+        let posicion = self.entradas.partition_point(|e| e.offset < self.offset);
+        self.entradas.get(posicion).map(|e| self.base + e.id)
+    }
+
+    /// Parsea el próximo pago del archivo, en el orden en que aparecen. Si la
+    /// línea de un pago indexado no corresponde al índice, porque el archivo
+    /// cambió sin cambiar su tamaño ni su fecha de modificación, el índice se
+    /// reconstruye.
     /// Devuelve el pago parseado si lo encuentra o None si llega al final
     /// del archivo.
-    pub fn parsear(&mut self, id: Option<usize>) -> Resultado<Option<Pago>> {
+    pub fn parsear(&mut self) -> Resultado<Option<Pago>> {
         loop {
+            let (linea, offset) = (self.linea, self.offset);
             let mut buffer = String::new();
             let bytes = self.lector.read_line(&mut buffer)?;
+            self.offset += bytes as u64;
+            self.linea += 1;
 
             if bytes == 0 {
                 // Llegue al final del archivo
                 return Ok(None);
             }
 
-            buffer = buffer.replace("\n", "");
-
-            let cap = match self.matcher.captures(&buffer) {
+            let contenido = buffer.trim_end_matches(['\n', '\r']);
+            // Las líneas indexadas se validaron al construir el índice, por lo
+            // que solo se validan las agregadas luego
+            if offset >= self.fin_indice.max(self.leido_hasta) {
+                self.leido_hasta = self.offset;
+                if let Err(error) = self.indexar(contenido, offset, linea) {
+                    self.rechazar(linea, contenido, &error);
+                }
+            }
+            let entrada = match self.entrada(offset) {
+                Some(entrada) => entrada,
                 None => continue,
-                Some(value) => value,
             };
+            let pago = match self.formato.parsear(contenido) {
+                Ok(pago) if pago.get_id() == entrada.id => pago,
+                _ => match self.reindexar(offset, contenido)? {
+                    Some(pago) => pago,
+                    None => continue,
+                },
+            };
+            let id = self.base + pago.get_id();
+            let pago = pago.con_id(id);
 
-            //Los parse no pueden fallar porque pasaron la regex
+            println!("[Parser] Nuevo pago de id '{}' con un monto de aerolinea de '{}' y monto de hotel de '{}'",
+                    pago.get_id(), pago.get_monto_aerolinea(), pago.get_monto_hotel());
 
-            self.posicion = cap[1]
-                .parse::<usize>()
-                .expect("Error al parsear id de pago");
-            if let Some(id_buscado) = id {
-                if id_buscado > self.posicion {
-                    continue;
-                }
-            }
+            return Ok(Some(pago));
+        }
+    }
+
+    /// Devuelve el pago del id pasado por parámetro, si existe, sin modificar
+    /// la posición de lectura en el archivo.
+    pub fn buscar(&mut self, id: usize) -> Resultado<Option<Pago>> {
+        let entrada = match self.local(id).and_then(|local| self.por_id.get(&local)) {
+            Some(posicion) => self.entradas[*posicion],
+            None => return Ok(None),
+        };
+        let (offset, linea) = (self.offset, self.linea);
+        self.mover(entrada.offset, entrada.linea)?;
+        // Si el índice se reconstruye el pago puede ya no estar en esa línea
+        let pago = self.parsear().map(|p| p.filter(|p| p.get_id() == id));
+        self.mover(offset, linea)?;
+        pago
+    }
+
+    /// Reconstruye el índice recorriendo el archivo, sin usar el guardado,
+    /// porque la línea del offset dado, de contenido dado, no corresponde al
+    /// pago indexado en el mismo. Deja la lectura a continuación de la línea.
+    /// Devuelve el pago de la línea si está en el nuevo índice, o None si fue
+    /// rechazada.
+    /// Devuelve error si la línea tampoco corresponde al nuevo índice, porque
+    /// el archivo se está modificando.
+    fn reindexar(&mut self, offset: u64, contenido: &str) -> Resultado<Option<Pago>> {
+        println!(
+            "[Parser] El indice no corresponde al archivo en el offset {}, se reconstruye",
+            offset
+        );
+        let (siguiente, linea) = (self.offset, self.linea);
+        self.entradas.clear();
+        self.por_id.clear();
+        self.rechazados = 0;
+        self.leido_hasta = 0;
+        self.mover(self.inicio.0, self.inicio.1)?;
+        self.construir_indice()?;
+        self.mover(siguiente, linea)?;
+
+        let entrada = match self.entrada(offset) {
+            Some(entrada) => entrada,
+            None => return Ok(None),
+        };
+        match self.formato.parsear(contenido) {
+            Ok(pago) if pago.get_id() == entrada.id => Ok(Some(pago)),
+            _ => Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "El archivo de entrada {} se modifico durante la lectura",
+                self.ruta.display()
+            )))),
+        }
+    }
 
-            println!("[Parser] Nuevo pago de id '{}' con un monto de aerolinea '{}' y monto de hotel de '{}'",
-                    &cap[1], &cap[2], &cap[3]);
-
-            //Si pasa la regex sabemos que el casteo no fallara.
-            let pago = Pago::new(
-                self.posicion,
-                cap[2]
-                    .parse::<f64>()
-                    .expect("Error al parsear monto de aerolinea"),
-                cap[3]
-                    .parse::<f64>()
-                    .expect("Error al parsear monto de hotel"),
+    /// Retoma la lectura en el offset guardado en el log, que corresponde al
+    /// pago del id pasado por parámetro o al final del archivo. Si el offset
+    /// no corresponde al pago, se retoma en el pago del id dado si existe,
+    /// porque el archivo cambió antes del mismo, o si no en el pago que
+    /// comienza en el offset, ya que si la lectura había llegado al final del
+    /// archivo el id registrado es una estimación y el pago se agregó luego.
+    /// Sin offset, como en los registros migrados del log de texto anterior,
+    /// que registran el id siguiente al del último pago, se retoma en el pago
+    /// del id dado o a continuación del pago de id anterior.
+    /// Devuelve error si no se encuentra dónde retomar la lectura.
+    pub fn reanudar(&mut self, id: usize, offset: Option<u64>) -> Resultado<()> {
+        // El id estimado al final del archivo puede exceder el lote
+        let local = id.checked_sub(self.base);
+        let en_offset = offset.and_then(|o| self.entrada(o));
+        if let Some(entrada) = en_offset.filter(|e| Some(e.id) == local) {
+            println!(
+                "[Parser] Se retoma la lectura en el pago de id {} (offset {})",
+                id, entrada.offset
             );
+            return self.mover(entrada.offset, entrada.linea);
+        }
+        if offset == Some(self.fin_indice) {
+            println!(
+                "[Parser] Se retoma la lectura al final del archivo (offset {})",
+                self.fin_indice
+            );
+            return self.mover(self.fin_indice, self.lineas_indice + 1);
+        }
+        if let Some(posicion) = local.and_then(|l| self.por_id.get(&l)) {
+            let entrada = self.entradas[*posicion];
+            println!(
+                "[Parser] Se retoma la lectura en el pago de id {} (linea {})",
+                id, entrada.linea
+            );
+            return self.mover(entrada.offset, entrada.linea);
+        }
+        if let Some(entrada) = en_offset {
+            println!(
+                "[Parser] Se retoma la lectura en el pago de id {} (offset {}), agregado luego del ultimo pago leido",
+                self.base + entrada.id,
+                entrada.offset
+            );
+            return self.mover(entrada.offset, entrada.linea);
+        }
+        if offset.is_none() {
+            let anterior = local
+                .and_then(|l| l.checked_sub(1))
+                .and_then(|a| self.por_id.get(&a));
+            if let Some(anterior) = anterior {
+                return match self.entradas.get(anterior + 1).copied() {
+                    Some(entrada) => self.mover(entrada.offset, entrada.linea),
+                    None => self.mover(self.fin_indice, self.lineas_indice + 1),
+                };
+            }
+        }
+        Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+            "No se encuentra el pago de id {} para retomar la lectura del archivo {}",
+            id,
+            self.ruta.display()
+        ))))
+    }
 
-            return Ok(Some(pago));
+    /// Agrega el pago del id dado, si está indexado, al reporte de
+    /// rechazados por el motivo dado, sin modificar la posición de lectura
+    /// en el archivo. Se usa para los pagos que se rechazan luego de
+    /// leerlos, como los que ya fueron confirmados.
+    pub fn rechazar_pago(&mut self, id: usize, motivo: &str) -> Resultado<()> {
+        let entrada = match self.local(id).and_then(|local| self.por_id.get(&local)) {
+            Some(posicion) => self.entradas[*posicion],
+            None => return Ok(()),
+        };
+        let (offset, linea) = (self.offset, self.linea);
+        self.mover(entrada.offset, entrada.linea)?;
+        let mut buffer = String::new();
+        let leido = self
+            .lector
+            .read_line(&mut buffer)
+            .map(|bytes| self.offset += bytes as u64);
+        self.mover(offset, linea)?;
+        leido?;
+        self.rechazar(entrada.linea, buffer.trim_end_matches(['\n', '\r']), motivo);
+        Ok(())
+    }
+
+    /// Devuelve el id en el archivo del pago del id dado, o None si el id no
+    /// corresponde al lote del archivo.
+    fn local(&self, id: usize) -> Option<usize> {
+        id.checked_sub(self.base)
+            .filter(|local| *local < LIMITE_ID_PAGO)
+    }
+
+    /// Devuelve la entrada del índice del pago en el offset dado, si existe.
+    fn entrada(&self, offset: u64) -> Option<EntradaIndice> {
+        self.entradas
+            .binary_search_by_key(&offset, |e| e.offset)
+            .ok()
+            .map(|posicion| self.entradas[posicion])
+    }
+
+    /// Mueve la lectura al offset dado, que corresponde al número de línea
+    /// dado, si no es el actual.
+    fn mover(&mut self, offset: u64, linea: u64) -> Resultado<()> {
+        if offset != self.offset {
+            self.lector.seek(SeekFrom::Start(offset))?;
+            self.offset = offset;
+        }
+        self.linea = linea;
+        Ok(())
+    }
+
+    /// Agrega al índice el pago de una línea del archivo, que debe ser
+    /// posterior a las ya indexadas. Las líneas vacías se ignoran.
+    /// Devuelve el motivo por el cual se rechaza la línea si no es un pago
+    /// válido o su id ya aparece en el índice.
+    fn indexar(&mut self, contenido: &str, offset: u64, linea: u64) -> Result<(), String> {
+        if contenido.trim().is_empty() {
+            return Ok(());
+        }
+        let id = self.formato.parsear(contenido)?.get_id();
+        if let Some(posicion) = self.por_id.get(&id) {
+            return Err(format!(
+                "id de pago {} duplicado: aparece en la linea {}",
+                id, self.entradas[*posicion].linea
+            ));
+        }
+        self.por_id.insert(id, self.entradas.len());
+        self.entradas.push(EntradaIndice { id, offset, linea });
+        Ok(())
+    }
+
+    /// Lee el encabezado del archivo, que es su primera línea no vacía, si el
+    /// formato lo requiere, y deja la lectura en la línea siguiente.
+    /// Devuelve error si el archivo no tiene encabezado o no es válido.
+    fn leer_encabezado(&mut self) -> Resultado<()> {
+        if !self.formato.tiene_encabezado() {
+            return Ok(());
+        }
+        let mut buffer = String::new();
+        loop {
+            buffer.clear();
+            let bytes = self.lector.read_line(&mut buffer)?;
+            if bytes == 0 {
+                return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                    "El archivo de entrada {} no tiene encabezado",
+                    self.ruta.display()
+                ))));
+            }
+            self.offset += bytes as u64;
+            self.linea += 1;
+            let contenido = buffer
+                .trim_start_matches('\u{feff}')
+                .trim_end_matches(['\n', '\r']);
+            if contenido.trim().is_empty() {
+                continue;
+            }
+            return self.formato.leer_encabezado(contenido).map_err(|e| {
+                ErrorApp::Interno(ErrorInterno::new(&format!(
+                    "Encabezado invalido en el archivo de entrada {}: {}",
+                    self.ruta.display(),
+                    e
+                )))
+            });
+        }
+    }
+
+    /// Agrega una línea al reporte de rechazados. Un error al escribir el
+    /// reporte no impide procesar el archivo.
+    fn rechazar(&mut self, linea: u64, contenido: &str, error: &str) {
+        println!("[Parser] Se rechaza la linea {}: {}", linea, error);
+        self.rechazados += 1;
+        let resultado = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.ruta_rechazados)
+            .and_then(|mut archivo| {
+                archivo.write_all(Parser::formatear_rechazo(linea, contenido, error).as_bytes())
+            });
+        if let Err(e) = resultado {
+            println!("[Parser] No se pudo guardar la linea rechazada: {}", e);
+        }
+    }
+
+    /// Devuelve el registro del reporte de rechazados para una línea, con su
+    /// número, el motivo del rechazo y su contenido original.
+    fn formatear_rechazo(linea: u64, contenido: &str, error: &str) -> String {
+        format!("{},{},{}\n", linea, error, contenido)
+    }
+
+    /// Carga el índice guardado del archivo si corresponde a su tamaño, su
+    /// fecha de modificación actuales y al formato. Si no, lo construye.
+    fn cargar_indice(&mut self) -> Resultado<()> {
+        let firma = Parser::firma(&self.ruta)?;
+        let ruta_indice = Parser::ruta_con_extension(&self.ruta, EXTENSION_INDICE);
+        if let Some((lineas, rechazados, entradas)) =
+            Parser::leer_indice(&ruta_indice, &firma, self.tipo)
+        {
+            self.por_id = entradas
+                .iter()
+                .enumerate()
+                .map(|(posicion, e)| (e.id, posicion))
+                .collect();
+            self.entradas = entradas;
+            self.fin_indice = firma.0;
+            self.lineas_indice = lineas;
+            self.rechazados = rechazados;
+            return Ok(());
+        }
+        self.construir_indice()
+    }
+
+    /// Construye el índice del archivo recorriendo y validando el archivo
+    /// desde la posición de lectura, posterior al encabezado si lo hay, y lo
+    /// guarda junto al reporte de las líneas rechazadas. Un error al
+    /// guardarlos no impide procesar el archivo.
+    fn construir_indice(&mut self) -> Resultado<()> {
+        let firma = Parser::firma(&self.ruta)?;
+        let ruta_indice = Parser::ruta_con_extension(&self.ruta, EXTENSION_INDICE);
+        self.fin_indice = self.offset;
+        self.lineas_indice = self.linea - 1;
+        let mut rechazos = String::new();
+        let mut buffer = String::new();
+        loop {
+            buffer.clear();
+            let bytes = self.lector.read_line(&mut buffer)?;
+            if bytes == 0 {
+                break;
+            }
+            self.lineas_indice += 1;
+            let contenido = buffer.trim_end_matches(['\n', '\r']);
+            if let Err(error) = self.indexar(contenido, self.fin_indice, self.lineas_indice) {
+                println!(
+                    "[Parser] Se rechaza la linea {}: {}",
+                    self.lineas_indice, error
+                );
+                self.rechazados += 1;
+                rechazos.push_str(&Parser::formatear_rechazo(
+                    self.lineas_indice,
+                    contenido,
+                    &error,
+                ));
+            }
+            self.fin_indice += bytes as u64;
+        }
+        self.lector.seek(SeekFrom::Start(self.offset))?;
+        println!(
+            "[Parser] Se indexaron {} pagos del archivo de entrada y se rechazaron {} lineas",
+            self.entradas.len(),
+            self.rechazados
+        );
+
+        if let Err(e) = fs::write(&self.ruta_rechazados, rechazos) {
+            println!("[Parser] No se pudo guardar el reporte de rechazados: {}", e);
+        }
+        let mut entradas = Vec::with_capacity(self.entradas.len() * TAM_ENTRADA);
+        for entrada in &self.entradas {
+            entradas.extend_from_slice(&(entrada.id as u64).to_le_bytes());
+            entradas.extend_from_slice(&entrada.offset.to_le_bytes());
+            entradas.extend_from_slice(&entrada.linea.to_le_bytes());
         }
+        let encabezado = format!(
+            "{},{},{}",
+            Parser::formatear_firma(&firma, self.tipo),
+            self.lineas_indice,
+            self.rechazados
+        )
+        .into_bytes();
+        if let Err(e) = Wal::reescribir(&ruta_indice, &[encabezado, entradas]) {
+            println!("[Parser] No se pudo guardar el indice: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Lee el índice guardado en la ruta dada y devuelve sus entradas, en el
+    /// orden del archivo, junto a la cantidad de líneas indexadas y de líneas
+    /// rechazadas, si corresponde a la firma y al formato dados, o None si no.
+    fn leer_indice(
+        ruta: &Path,
+        firma: &(u64, u64),
+        tipo: TipoFormato,
+    ) -> Option<(u64, usize, Vec<EntradaIndice>)> {
+        let registros = Wal::leer(ruta).ok()?;
+        let (encabezado, entradas) = match registros.as_slice() {
+            [encabezado, entradas] => (String::from_utf8(encabezado.clone()).ok()?, entradas),
+            _ => return None,
+        };
+        let contadores = encabezado.strip_prefix(&format!("{},", Parser::formatear_firma(firma, tipo)))?;
+        let (lineas, rechazados) = contadores.split_once(',')?;
+        if !entradas.len().is_multiple_of(TAM_ENTRADA) {
+            return None;
+        }
+        let numero = |bytes: &[u8]| {
+            let mut numero = [0; 8];
+            numero.copy_from_slice(bytes);
+            u64::from_le_bytes(numero)
+        };
+        Some((
+            lineas.parse::<u64>().ok()?,
+            rechazados.parse::<usize>().ok()?,
+            entradas
+                .chunks(TAM_ENTRADA)
+                .map(|e| EntradaIndice {
+                    id: numero(&e[..8]) as usize,
+                    offset: numero(&e[8..16]),
+                    linea: numero(&e[16..]),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Devuelve el tamaño del archivo de la ruta dada y su fecha de
+    /// modificación en milisegundos desde UNIX_EPOCH.
+    fn firma(ruta: &Path) -> Resultado<(u64, u64)> {
+        let metadata = fs::metadata(ruta)?;
+        let modificacion = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| ErrorApp::Interno(ErrorInterno::new(&e.to_string())))?;
+        Ok((metadata.len(), modificacion.as_millis() as u64))
+    }
+
+    /// Devuelve el comienzo del encabezado del índice guardado, con la
+    /// versión del formato del índice, el formato del archivo y su firma.
+    fn formatear_firma(firma: &(u64, u64), tipo: TipoFormato) -> String {
+        format!(
+            "INDICE,{},{},{},{}",
+            VERSION_INDICE,
+            tipo.codificar(),
+            firma.0,
+            firma.1
+        )
+    }
+
+    /// Devuelve la ruta del archivo de entrada de la ruta dada con la
+    /// extensión dada agregada.
+    fn ruta_con_extension(ruta: &Path, extension: &str) -> PathBuf {
+        let mut ruta_extendida = OsString::from(ruta.as_os_str());
+        ruta_extendida.push(extension);
+        PathBuf::from(ruta_extendida)
     }
 }
@@ -1,13 +1,20 @@
 use super::config::Config;
 use super::pago::Pago;
-use common::error::Resultado;
+use common::campos;
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::monto::Monto;
+use common::motivo_aborto::MotivoAborto;
 use regex::Regex;
 use std::fs;
 use std::fs::File;
 use std::io::{self, prelude::*};
 
 /// ParserFallidos implementa el parseo de los request fallidos que se
-/// encuentran en un archivo dado.
+/// encuentran en un archivo dado. Cada pago fallido se guarda con sus montos
+/// y su moneda, sus datos de la reserva codificados y precedidos por `+`, si
+/// los tiene, y el motivo por el cual se abortó su transacción, si se
+/// conoce. Los montos sin moneda, escritos por versiones anteriores,
+/// corresponden a la moneda por defecto.
 pub struct ParserFallidos {
     archivo: File,
     matcher: Regex,
@@ -18,12 +25,11 @@ impl ParserFallidos {
     pub fn new() -> Resultado<Self> {
         Ok(ParserFallidos {
             archivo: fs::OpenOptions::new()
-                .write(true)
                 .read(true)
                 .append(true)
                 .create(true)
                 .open(Config::ruta_fallidos())?,
-            matcher: Regex::new(r"^(\d+),(\d+\.\d{2}),(\d+\.\d{2})$")?,
+            matcher: ParserFallidos::matcher()?,
         })
     }
 
@@ -45,11 +51,9 @@ impl ParserFallidos {
                 if cap[1].parse::<usize>().expect("Error al parsear id de pago") == id {
                     println!("[ParserFallidos] Reintento de pago de id '{}' con un monto de aerolinea '{}' y monto de hotel de '{}'",
                         &cap[1], &cap[2], &cap[3]);
-                    pago = Some(Pago::new(
-                        cap[1].parse::<usize>().expect("Error al parsear id de pago"),
-                        cap[2].parse::<f64>().expect("Error al parsear monto de aerolinea"),
-                        cap[3].parse::<f64>().expect("Error al parsear monto de hotel"),
-                    ));
+                    pago = Some(
+                        ParserFallidos::parsear_pago(&cap).expect("Error al parsear pago fallido"),
+                    );
 
                     "".to_string()
                 } else {
@@ -67,10 +71,69 @@ impl ParserFallidos {
         Ok(pago)
     }
 
-    /// Escribe un pago fallido en el archivo de fallidos.
-    pub fn escribir_fallido(&mut self, pago: Pago) {
+    /// Devuelve la regex que matchea un pago fallido
+    fn matcher() -> Resultado<Regex> {
+        Ok(Regex::new(
+            r"^(\d+),(\d+\.\d{2}(?: [A-Z]{3})?),(\d+\.\d{2}(?: [A-Z]{3})?)(?:,\+([^ ,]*))?(?:,([A-Z_]+ .*))?$",
+        )?)
+    }
+
+    /// Convierte los campos de un pago fallido matcheados por la regex a Pago
+    /// y lo devuelve.
+    /// Devuelve error si los datos de la reserva no son válidos.
+    fn parsear_pago(cap: &regex::Captures) -> Resultado<Pago> {
+        let pago = Pago::new(
+            cap[1].parse::<usize>()?,
+            Monto::decodificar(&cap[2])?,
+            Monto::decodificar(&cap[3])?,
+        );
+        match cap.get(4) {
+            Some(datos) => pago
+                .con_datos(campos::decodificar(datos.as_str())?)
+                .map_err(|e| {
+                    ErrorApp::Interno(ErrorInterno::new(&format!(
+                        "Pago fallido con datos de reserva invalidos: {}",
+                        e
+                    )))
+                }),
+            None => Ok(pago),
+        }
+    }
+
+    /// Devuelve los pagos del archivo de fallidos junto al motivo por el
+    /// cual fallaron, si se conoce. No requiere abrir el archivo para
+    /// escritura.
+    pub fn listar() -> Resultado<Vec<(Pago, Option<MotivoAborto>)>> {
+        let contenido = match fs::read_to_string(Config::ruta_fallidos()) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let matcher = ParserFallidos::matcher()?;
+        let mut fallidos = Vec::new();
+
+        for linea in contenido.lines() {
+            if let Some(cap) = matcher.captures(linea) {
+                let pago = ParserFallidos::parsear_pago(&cap)?;
+                let motivo = match cap.get(5) {
+                    Some(m) => Some(MotivoAborto::decodificar(m.as_str())?),
+                    None => None,
+                };
+                fallidos.push((pago, motivo));
+            }
+        }
+
+        Ok(fallidos)
+    }
+
+    /// Escribe un pago fallido en el archivo de fallidos junto al motivo
+    /// del abort.
+    pub fn escribir_fallido(&mut self, pago: Pago, motivo: Option<&MotivoAborto>) {
         let _ = self.archivo.seek(io::SeekFrom::End(0));
-        let salida = self.formatear_pago(pago);
+        let mut salida = self.formatear_pago(pago);
+        if let Some(motivo) = motivo {
+            salida = format!("{},{}", salida, motivo.codificar('\n'));
+        }
 
         match writeln!(self.archivo, "{}", salida) {
             Ok(v) => v,
@@ -80,11 +143,16 @@ impl ParserFallidos {
 
     /// Recibe un Pago y devuelve un String formateado
     fn formatear_pago(&self, pago: Pago) -> String {
-        format!(
-            "{},{:.2},{:.2}",
+        let mut salida = format!(
+            "{},{},{}",
             pago.get_id(),
             pago.get_monto_aerolinea(),
             pago.get_monto_hotel()
-        )
+        );
+        let datos = pago.get_datos();
+        if !datos.is_empty() {
+            salida = format!("{},+{}", salida, campos::codificar(&datos));
+        }
+        salida
     }
 }
@@ -0,0 +1,125 @@
+use super::comando::{Comando, PedidoPago};
+use super::eleccion_lider::VistaLider;
+use common::dns::DNS;
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::protocolo_pagos::{MensajePago, ProtocoloPagos};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Tiempo de espera entre revisiones del liderazgo mientras se espera el
+/// resultado de un pago
+const ESPERA_RESULTADO: Duration = Duration::from_millis(500);
+
+/// ServidorPagos implementa la recepción de pedidos de pagos por socket TCP.
+/// Todos los nodos aceptan conexiones, pero solo el líder atiende los
+/// pedidos: el resto redirige al cliente al líder. Los pedidos se envían al
+/// flujo principal de la aplicación como comandos, que responde al cliente
+/// por el canal del comando.
+pub struct ServidorPagos {}
+
+impl ServidorPagos {
+    /// Comienza a aceptar conexiones en la dirección de pagos del nodo de id
+    /// dado, en un hilo que se mantiene hasta que finaliza el proceso. Cada
+    /// conexión se atiende en un hilo propio.
+    /// Devuelve error si no se puede escuchar en la dirección.
+    pub fn iniciar(id: usize, lider: VistaLider, enviador: Sender<Comando>) -> Resultado<()> {
+        let listener = TcpListener::bind(DNS::direccion_pagos(&id))?;
+        thread::spawn(move || {
+            for conexion in listener.incoming().flatten() {
+                let lider = lider.clone();
+                let enviador = enviador.clone();
+                thread::spawn(move || {
+                    if let Err(e) = ServidorPagos::atender(conexion, &lider, &enviador) {
+                        println!("[ServidorPagos]: Error al atender un cliente: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    /// Atiende los pedidos de un cliente hasta que cierra la conexión: pagos
+    /// nuevos, reintentos de pagos fallidos, consultas del estado de un pago
+    /// y el listado de los pagos fallidos. Un mensaje que no es un pedido
+    /// válido se rechaza y cierra la conexión.
+    fn atender(
+        conexion: TcpStream,
+        lider: &VistaLider,
+        enviador: &Sender<Comando>,
+    ) -> Resultado<()> {
+        let mut protocolo = ProtocoloPagos::new(conexion)?;
+        loop {
+            let mensaje = match protocolo.recibir(None) {
+                Ok(Some(mensaje)) => mensaje,
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    return protocolo.enviar(&MensajePago::RECHAZADO {
+                        motivo: e.to_string(),
+                    })
+                }
+            };
+            let (respuesta, receptor) = channel();
+            let comando = match mensaje {
+                MensajePago::PAGAR {
+                    monto_aerolinea,
+                    monto_hotel,
+                    datos,
+                } => Comando::Pagar(PedidoPago {
+                    monto_aerolinea,
+                    monto_hotel,
+                    datos,
+                    respuesta,
+                }),
+                MensajePago::REINTENTAR { id_pago } => Comando::Reintentar {
+                    id: id_pago,
+                    respuesta: Some(respuesta),
+                },
+                MensajePago::ESTADO { id_pago } => Comando::Consultar {
+                    id: id_pago,
+                    respuesta,
+                },
+                MensajePago::LISTAR => Comando::Listar { respuesta },
+                otro => {
+                    return protocolo.enviar(&MensajePago::RECHAZADO {
+                        motivo: format!("Se esperaba un pedido: {}", otro.codificar()),
+                    })
+                }
+            };
+            if !lider.soy_lider() {
+                protocolo.enviar(&ServidorPagos::redireccion(lider))?;
+                continue;
+            }
+
+            enviador
+                .send(comando)
+                .map_err(|_| ErrorApp::Interno(ErrorInterno::new("La aplicacion finalizo")))?;
+            // Si el nodo deja de ser líder antes de terminar la respuesta, se
+            // redirige al cliente al nuevo líder, que termina la transacción
+            loop {
+                match receptor.recv_timeout(ESPERA_RESULTADO) {
+                    Ok(mensaje) => {
+                        protocolo.enviar(&mensaje)?;
+                        if mensaje.es_final() {
+                            break;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) if lider.soy_lider() => {}
+                    Err(_) => {
+                        protocolo.enviar(&ServidorPagos::redireccion(lider))?;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Devuelve el mensaje que redirige al cliente al líder conocido.
+    fn redireccion(lider: &VistaLider) -> MensajePago {
+        match lider.get_id_lider() {
+            Some(id_lider) => MensajePago::LIDER { id_lider },
+            None => MensajePago::SIN_LIDER,
+        }
+    }
+}
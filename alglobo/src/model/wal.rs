@@ -0,0 +1,318 @@
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::Path;
+
+/// Encabezado con el que comienza todo archivo en formato WAL. Los archivos
+/// que no lo tienen se consideran en el formato de texto anterior.
+const MAGIA: &[u8] = b"ALGWAL01";
+/// Tamaño del encabezado de cada registro: largo, CRC del largo y CRC del
+/// contenido.
+const TAM_ENCABEZADO: usize = 12;
+
+/// PoliticaDurabilidad representa cuándo se sincronizan a disco los
+/// registros escritos en el log.
+/// # Variantes
+/// PorRegistro: se hace fsync luego de escribir cada registro.
+/// Grupal: se hace fsync una vez por grupo de registros, al sincronizar
+/// explícitamente el log (por ejemplo, antes de enviar los mensajes de una
+/// ronda de transacción).
+/// Ninguna: no se hace fsync, la sincronización queda a cargo del sistema
+/// operativo.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PoliticaDurabilidad {
+    PorRegistro,
+    Grupal,
+    Ninguna,
+}
+
+/// Registro leído de un archivo WAL junto al offset en el que comienza.
+pub type RegistroWal = (u64, Vec<u8>);
+
+/// Wal implementa un archivo de solo agregado en el que cada registro se
+/// escribe precedido por su largo y su CRC, de forma de detectar escrituras
+/// incompletas y corrupción al recuperarlo. El largo tiene su propio CRC,
+/// para no confundir un largo corrupto con un registro incompleto.
+pub struct Wal {
+    archivo: File,
+    politica: PoliticaDurabilidad,
+    pendientes: usize,
+    largo: u64,
+}
+
+impl Wal {
+    /// Abre el archivo WAL de la ruta dada, creándolo si no existe, y
+    /// devuelve el Wal junto con los registros recuperados.
+    /// Si el último registro quedó incompleto se trunca el archivo antes del
+    /// mismo. Devuelve error si hay corrupción en medio del archivo.
+    pub fn abrir(ruta: &Path, politica: PoliticaDurabilidad) -> Resultado<(Wal, Vec<RegistroWal>)> {
+        let contenido = Wal::leer_contenido(ruta)?;
+
+        let (registros, largo) = if contenido.is_empty() {
+            Wal::reescribir(ruta, &[])?;
+            (Vec::new(), MAGIA.len())
+        } else if !contenido.starts_with(MAGIA) {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "{} no es un archivo WAL",
+                ruta.display()
+            ))));
+        } else {
+            let (registros, largo_valido) = Wal::recuperar(&contenido)?;
+            if largo_valido < contenido.len() {
+                println!(
+                    "[Log]: Se descartan {} bytes de un registro incompleto al final de {}",
+                    contenido.len() - largo_valido,
+                    ruta.display()
+                );
+                let archivo = fs::OpenOptions::new().write(true).open(ruta)?;
+                archivo.set_len(largo_valido as u64)?;
+                archivo.sync_all()?;
+            }
+            (registros, largo_valido)
+        };
+
+        let archivo = fs::OpenOptions::new().read(true).append(true).open(ruta)?;
+
+        Ok((
+            Wal {
+                archivo,
+                politica,
+                pendientes: 0,
+                largo: largo as u64,
+            },
+            registros,
+        ))
+    }
+
+    /// Lee los registros del archivo de la ruta dada sin modificarlo.
+    /// Un registro incompleto al final del archivo se ignora, ya que puede
+    /// estar siendo escrito por el líder. Los archivos en el formato de texto
+    /// anterior se leen como un registro por línea. Devuelve error si hay
+    /// corrupción en medio del archivo.
+    pub fn leer<P: AsRef<Path>>(ruta: P) -> Resultado<Vec<Vec<u8>>> {
+        let contenido = Wal::leer_contenido(ruta.as_ref())?;
+        if contenido.starts_with(MAGIA) {
+            Ok(Wal::recuperar(&contenido)?
+                .0
+                .into_iter()
+                .map(|(_, registro)| registro)
+                .collect())
+        } else {
+            Ok(contenido
+                .split(|b| *b == b'\n')
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_vec())
+                .collect())
+        }
+    }
+
+    /// Lee los registros del archivo WAL de la ruta dada junto a sus offsets,
+    /// sin modificarlo. A diferencia de `leer`, un registro incompleto al
+    /// final se considera corrupción, por lo que debe usarse para archivos
+    /// que ya no reciben escrituras.
+    pub fn leer_completo(ruta: &Path) -> Resultado<Vec<RegistroWal>> {
+        let contenido = Wal::leer_contenido(ruta)?;
+        if !contenido.starts_with(MAGIA) {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "{} no es un archivo WAL",
+                ruta.display()
+            ))));
+        }
+        let (registros, largo_valido) = Wal::recuperar(&contenido)?;
+        if largo_valido < contenido.len() {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Log corrupto: registro incompleto en el offset {} de {}",
+                largo_valido,
+                ruta.display()
+            ))));
+        }
+        Ok(registros)
+    }
+
+    /// Lee a lo sumo `cantidad` registros del archivo de la ruta dada a
+    /// partir del offset indicado, que debe ser el comienzo de un registro.
+    pub fn leer_desde(ruta: &Path, offset: u64, cantidad: usize) -> Resultado<Vec<Vec<u8>>> {
+        let mut archivo = File::open(ruta)?;
+        archivo.seek(SeekFrom::Start(offset))?;
+        let mut registros = Vec::with_capacity(cantidad);
+        let mut encabezado = [0; TAM_ENCABEZADO];
+
+        while registros.len() < cantidad {
+            if archivo.read_exact(&mut encabezado).is_err() {
+                break;
+            }
+            let largo = match Wal::leer_largo(&encabezado, 0) {
+                Some(largo) => largo,
+                None => break,
+            };
+            let crc = Wal::leer_u32(&encabezado, 8);
+            let mut datos = vec![0; largo];
+            if archivo.read_exact(&mut datos).is_err() || crc32fast::hash(&datos) != crc {
+                break;
+            }
+            registros.push(datos);
+        }
+
+        Ok(registros)
+    }
+
+    /// Agrega un registro al final del archivo, sincronizándolo a disco según
+    /// la política de durabilidad. Devuelve el offset en el que comienza.
+    /// Si la escritura falla se trunca el archivo en ese offset, de forma que
+    /// los registros siguientes no queden detrás de uno incompleto.
+    pub fn escribir(&mut self, registro: &[u8]) -> Resultado<u64> {
+        let offset = self.largo;
+        let marco = Wal::enmarcar(registro);
+        if let Err(e) = self.archivo.write_all(&marco) {
+            if let Err(e) = self.archivo.set_len(offset) {
+                println!(
+                    "[Log]: No se pudo descartar el registro incompleto en el offset {}: {}",
+                    offset, e
+                );
+            }
+            return Err(e.into());
+        }
+        self.largo += marco.len() as u64;
+        self.pendientes += 1;
+        if self.politica == PoliticaDurabilidad::PorRegistro {
+            self.sincronizar()?;
+        }
+        Ok(offset)
+    }
+
+    /// Sincroniza a disco los registros escritos desde la última
+    /// sincronización. No hace nada si la política es Ninguna.
+    pub fn sincronizar(&mut self) -> Resultado<()> {
+        if self.pendientes > 0 && self.politica != PoliticaDurabilidad::Ninguna {
+            self.archivo.sync_data()?;
+        }
+        self.pendientes = 0;
+        Ok(())
+    }
+
+    /// Devuelve el tamaño del archivo en bytes.
+    pub fn largo(&self) -> u64 {
+        self.largo
+    }
+
+    /// Reescribe el archivo de la ruta dada en formato WAL con los registros
+    /// dados. El archivo nuevo se escribe aparte y luego reemplaza al
+    /// anterior, de forma que una caída durante la escritura no pierda
+    /// registros.
+    pub fn reescribir(ruta: &Path, registros: &[Vec<u8>]) -> Resultado<()> {
+        let ruta_temporal = ruta.with_extension("tmp");
+        let mut archivo = File::create(&ruta_temporal)?;
+        archivo.write_all(MAGIA)?;
+        for registro in registros {
+            archivo.write_all(&Wal::enmarcar(registro))?;
+        }
+        archivo.sync_all()?;
+        fs::rename(&ruta_temporal, ruta)?;
+        Ok(())
+    }
+
+    /// Devuelve el contenido del archivo de la ruta dada, o un contenido
+    /// vacío si el archivo no existe.
+    fn leer_contenido(ruta: &Path) -> Resultado<Vec<u8>> {
+        match fs::read(ruta) {
+            Ok(c) => Ok(c),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(ErrorApp::ErrorIO(e)),
+        }
+    }
+
+    /// Devuelve el registro precedido por su largo, el CRC del largo y el CRC
+    /// del registro.
+    fn enmarcar(registro: &[u8]) -> Vec<u8> {
+        let largo = (registro.len() as u32).to_le_bytes();
+        let mut marco = Vec::with_capacity(TAM_ENCABEZADO + registro.len());
+        marco.extend_from_slice(&largo);
+        marco.extend_from_slice(&crc32fast::hash(&largo).to_le_bytes());
+        marco.extend_from_slice(&crc32fast::hash(registro).to_le_bytes());
+        marco.extend_from_slice(registro);
+        marco
+    }
+
+    /// Recorre los registros de un archivo en formato WAL y los devuelve
+    /// junto con el largo de la parte válida del archivo. Un registro
+    /// incompleto o con CRC inválido solo se considera una escritura
+    /// interrumpida si es el último del archivo: con el largo válido, si
+    /// termina al final del archivo o más allá, y con el largo corrupto, si
+    /// no le sigue ningún registro válido. En cualquier otro caso se
+    /// considera corrupción.
+    fn recuperar(contenido: &[u8]) -> Resultado<(Vec<RegistroWal>, usize)> {
+        let mut registros = Vec::new();
+        let mut offset = MAGIA.len();
+
+        while offset < contenido.len() {
+            if contenido.len() - offset < TAM_ENCABEZADO {
+                break;
+            }
+            let largo = match Wal::leer_largo(contenido, offset) {
+                Some(largo) => largo,
+                None if Wal::hay_registro_valido(contenido, offset + 1) => {
+                    return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                        "Log corrupto: largo invalido en el registro del offset {}",
+                        offset
+                    ))))
+                }
+                None => break,
+            };
+            let crc = Wal::leer_u32(contenido, offset + 8);
+            let inicio = offset + TAM_ENCABEZADO;
+            if largo > contenido.len() - inicio {
+                break;
+            }
+            let fin = inicio + largo;
+            let datos = &contenido[inicio..fin];
+            if crc32fast::hash(datos) != crc {
+                if fin == contenido.len() {
+                    break;
+                }
+                return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                    "Log corrupto: CRC invalido en el registro del offset {}",
+                    offset
+                ))));
+            }
+            registros.push((offset as u64, datos.to_vec()));
+            offset = fin;
+        }
+
+        Ok((registros, offset.min(contenido.len())))
+    }
+
+    /// Devuelve el largo del registro cuyo encabezado comienza en el offset
+    /// dado, o None si no coincide con su CRC.
+    fn leer_largo(contenido: &[u8], offset: usize) -> Option<usize> {
+        let largo = &contenido[offset..offset + 4];
+        if crc32fast::hash(largo) == Wal::leer_u32(contenido, offset + 4) {
+            Some(Wal::leer_u32(contenido, offset) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Devuelve verdadero si algún registro completo y válido comienza a
+    /// partir del offset dado.
+    fn hay_registro_valido(contenido: &[u8], desde: usize) -> bool {
+        (desde..contenido.len().saturating_sub(TAM_ENCABEZADO - 1)).any(|offset| {
+            let inicio = offset + TAM_ENCABEZADO;
+            Wal::leer_largo(contenido, offset).is_some_and(|largo| {
+                largo <= contenido.len() - inicio
+                    && crc32fast::hash(&contenido[inicio..inicio + largo])
+                        == Wal::leer_u32(contenido, offset + 8)
+            })
+        })
+    }
+
+    /// Devuelve el u32 little endian que comienza en el offset dado.
+    fn leer_u32(contenido: &[u8], offset: usize) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&contenido[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    }
+}
@@ -0,0 +1,103 @@
+use alglobo::model::almacenamiento::Almacenamiento;
+use alglobo::model::almacenamiento_memoria::AlmacenamientoMemoria;
+use alglobo::model::cadena::HASH_INICIAL;
+use alglobo::model::config::Config;
+use alglobo::model::estado_transaccion::EstadoTransaccion;
+use alglobo::model::log::{Log, Transaccion};
+use alglobo::model::pago::Pago;
+use common::id_transaccion::IdTransaccion;
+use common::monto::{Moneda, Monto};
+use common::motivo_aborto::{CausaAborto, MotivoAborto};
+
+fn pago(id: usize) -> Pago {
+    Pago::new(
+        id,
+        Monto::new(1000, Moneda::POR_DEFECTO),
+        Monto::new(500, Moneda::POR_DEFECTO),
+    )
+}
+
+fn transaccion(secuencia: u32, id_pago: usize, estado: EstadoTransaccion) -> Transaccion {
+    Transaccion::new(
+        IdTransaccion::new(1, secuencia),
+        id_pago,
+        id_pago + 1,
+        estado,
+    )
+}
+
+fn config_memoria() -> Config {
+    serde_json::from_str(r#"{"almacenamiento": "memoria"}"#).unwrap()
+}
+
+#[test]
+fn insertar_y_obtener_conserva_el_pago_del_prepare() {
+    let mut almacenamiento = AlmacenamientoMemoria::new();
+    almacenamiento.recuperar().unwrap();
+    let mut prepare = transaccion(1, 7, EstadoTransaccion::Prepare);
+    prepare.pago = Some(pago(7));
+    almacenamiento.insertar(&prepare).unwrap();
+    almacenamiento
+        .insertar(&transaccion(1, 7, EstadoTransaccion::Commit))
+        .unwrap();
+
+    let obtenida = almacenamiento
+        .obtener(IdTransaccion::new(1, 1))
+        .unwrap()
+        .unwrap();
+    assert_eq!(obtenida.estado(), EstadoTransaccion::Commit);
+    let pago = obtenida.get_pago().unwrap();
+    assert_eq!(pago.get_monto_aerolinea().centavos(), 1000);
+    assert_eq!(pago.get_monto_hotel().centavos(), 500);
+    assert!(almacenamiento
+        .obtener(IdTransaccion::new(1, 2))
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn pago_confirmado_solo_luego_del_commit() {
+    let mut almacenamiento = AlmacenamientoMemoria::new();
+    almacenamiento
+        .insertar(&transaccion(1, 7, EstadoTransaccion::Prepare))
+        .unwrap();
+    assert!(!almacenamiento.pago_confirmado(7).unwrap());
+    almacenamiento
+        .insertar(&transaccion(1, 7, EstadoTransaccion::Commit))
+        .unwrap();
+    assert!(almacenamiento.pago_confirmado(7).unwrap());
+    assert!(!almacenamiento.pago_confirmado(8).unwrap());
+}
+
+#[test]
+fn log_rechaza_transiciones_invalidas() {
+    let mut log = Log::new(&config_memoria(), 1).unwrap();
+    let commit = transaccion(1, 7, EstadoTransaccion::Commit);
+    assert!(log.insertar(&commit).is_err());
+
+    log.insertar(&transaccion(1, 7, EstadoTransaccion::Prepare))
+        .unwrap();
+    log.insertar(&transaccion(1, 7, EstadoTransaccion::Abort))
+        .unwrap();
+    assert!(log.insertar(&commit).is_err());
+    assert_eq!(
+        log.obtener(&IdTransaccion::new(1, 1))
+            .unwrap()
+            .unwrap()
+            .estado(),
+        EstadoTransaccion::Abort
+    );
+}
+
+#[test]
+fn registro_del_abort_conserva_el_motivo_y_el_lote() {
+    let mut abort = transaccion(1, 7, EstadoTransaccion::Abort);
+    abort.motivo_aborto = Some(MotivoAborto::new(
+        CausaAborto::FondosInsuficientes,
+        "Sin fondos",
+    ));
+    abort.lote = 4;
+    let decodificada = Transaccion::decodificar(&abort.codificar(&HASH_INICIAL)).unwrap();
+    assert_eq!(decodificada.motivo_aborto, abort.motivo_aborto);
+    assert_eq!(decodificada.lote, 4);
+}
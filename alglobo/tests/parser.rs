@@ -0,0 +1,109 @@
+use alglobo::model::formato_entrada::TipoFormato;
+use alglobo::model::parser::Parser;
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+/// Crea un directorio temporal único para el test de nombre dado y devuelve
+/// la ruta de un archivo de entrada con el contenido dado dentro del mismo.
+fn archivo_temporal(nombre: &str, contenido: &str) -> PathBuf {
+    let directorio =
+        std::env::temp_dir().join(format!("alglobo-parser-{}-{}", nombre, std::process::id()));
+    let _ = fs::remove_dir_all(&directorio);
+    fs::create_dir_all(&directorio).unwrap();
+    let ruta = directorio.join("pagos.csv");
+    fs::write(&ruta, contenido).unwrap();
+    ruta
+}
+
+/// Parsea los pagos restantes del archivo y devuelve sus ids.
+fn ids_restantes(parser: &mut Parser) -> Vec<usize> {
+    let mut ids = Vec::new();
+    while let Some(pago) = parser.parsear().unwrap() {
+        ids.push(pago.get_id());
+    }
+    ids
+}
+
+#[test]
+fn indice_se_reconstruye_si_el_archivo_cambia_con_igual_tamano_y_fecha() {
+    let ruta = archivo_temporal("reindexar", "1,10.00,10.00\n2,20.00,20.00\n3,30.00,30.00\n");
+    let mut parser = Parser::new(&ruta, TipoFormato::Csv).unwrap();
+    assert_eq!(ids_restantes(&mut parser), vec![1, 2, 3]);
+
+    let modificacion = fs::metadata(&ruta).unwrap().modified().unwrap();
+    fs::write(&ruta, "1,10.00,10.00\n5,20.00,20.00\nx,30.00,30.00\n").unwrap();
+    File::options()
+        .write(true)
+        .open(&ruta)
+        .unwrap()
+        .set_modified(modificacion)
+        .unwrap();
+
+    // El índice guardado se reutiliza hasta que una línea no le corresponde
+    let mut parser = Parser::new(&ruta, TipoFormato::Csv).unwrap();
+    assert_eq!(parser.ids(), vec![1, 2, 3]);
+    assert_eq!(ids_restantes(&mut parser), vec![1, 5]);
+    assert_eq!(parser.ids(), vec![1, 5]);
+    assert_eq!(parser.rechazados(), 1);
+    fs::remove_dir_all(ruta.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn reanudar_en_pago_agregado_luego_del_final() {
+    let ruta = archivo_temporal("agregado", "1,10.00,10.00\n2,20.00,20.00\n");
+    let mut parser = Parser::new(&ruta, TipoFormato::Csv).unwrap();
+    assert_eq!(ids_restantes(&mut parser), vec![1, 2]);
+    let fin = parser.offset();
+
+    fs::write(&ruta, "1,10.00,10.00\n2,20.00,20.00\n9,30.00,30.00\n").unwrap();
+    let mut parser = Parser::new(&ruta, TipoFormato::Csv).unwrap();
+    parser.reanudar(3, Some(fin)).unwrap();
+    assert_eq!(ids_restantes(&mut parser), vec![9]);
+    fs::remove_dir_all(ruta.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn reanudar_sin_offset_a_continuacion_del_pago_anterior() {
+    let ruta = archivo_temporal("sin-offset", "1,10.00,10.00\n2,20.00,20.00\n");
+    let mut parser = Parser::new(&ruta, TipoFormato::Csv).unwrap();
+    parser.reanudar(2, None).unwrap();
+    assert_eq!(ids_restantes(&mut parser), vec![2]);
+
+    parser.reanudar(3, None).unwrap();
+    assert!(ids_restantes(&mut parser).is_empty());
+
+    assert!(parser.reanudar(7, None).is_err());
+    fs::remove_dir_all(ruta.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn ids_desde_2_a_la_32_se_rechazan() {
+    let ruta = archivo_temporal(
+        "fuera-de-rango",
+        "4294967295,10.00,10.00\n4294967296,20.00,20.00\n",
+    );
+    let mut parser = Parser::new(&ruta, TipoFormato::Csv).unwrap();
+    assert_eq!(ids_restantes(&mut parser), vec![4294967295]);
+    assert_eq!(parser.rechazados(), 1);
+    fs::remove_dir_all(ruta.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn ids_del_lote_no_coinciden_con_los_del_archivo() {
+    let ruta = archivo_temporal("lote", "1,10.00,10.00\n2,20.00,20.00\n");
+    let base = 3 * 4294967296;
+    let mut parser = Parser::new(&ruta, TipoFormato::Csv).unwrap().con_lote(3);
+    assert_eq!(parser.ids(), vec![base + 1, base + 2]);
+    assert!(parser.buscar(1).unwrap().is_none());
+    assert_eq!(parser.buscar(base + 2).unwrap().unwrap().get_id(), base + 2);
+
+    parser.reanudar(base + 2, None).unwrap();
+    assert_eq!(ids_restantes(&mut parser), vec![base + 2]);
+    parser.rechazar_pago(base + 1, "ya confirmado").unwrap();
+    assert_eq!(parser.rechazados(), 1);
+    assert_eq!(
+        fs::read_to_string(parser.ruta_rechazados()).unwrap(),
+        "1,ya confirmado,1,10.00,10.00\n"
+    );
+    fs::remove_dir_all(ruta.parent().unwrap()).unwrap();
+}
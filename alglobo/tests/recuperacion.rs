@@ -0,0 +1,201 @@
+use alglobo::model::almacenamiento::{self, Almacenamiento};
+use alglobo::model::config::Config;
+use alglobo::model::estado_transaccion::EstadoTransaccion;
+use alglobo::model::log::Transaccion;
+use alglobo::model::pago::Pago;
+use common::id_transaccion::IdTransaccion;
+use common::monto::{Moneda, Monto};
+use common::motivo_aborto::{CausaAborto, MotivoAborto};
+use std::env;
+use std::fs;
+use std::sync::Mutex;
+
+/// Las rutas del almacenamiento son relativas al directorio de trabajo, que
+/// es uno solo para todo el proceso, por lo que los tests de este archivo no
+/// pueden correr en paralelo.
+static DIRECTORIO: Mutex<()> = Mutex::new(());
+
+/// Crea un directorio temporal vacío para el test de nombre dado, con el
+/// subdirectorio de los archivos del almacenamiento, y lo vuelve el
+/// directorio de trabajo.
+fn directorio_temporal(nombre: &str) {
+    let ruta = env::temp_dir().join(format!(
+        "alglobo-recuperacion-{}-{}",
+        nombre,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&ruta);
+    fs::create_dir_all(ruta.join("files")).unwrap();
+    env::set_current_dir(&ruta).unwrap();
+}
+
+fn config(almacenamiento: &str) -> Config {
+    serde_json::from_str(&format!(r#"{{"almacenamiento": "{}"}}"#, almacenamiento)).unwrap()
+}
+
+fn config_compactando(registros_por_snapshot: usize) -> Config {
+    serde_json::from_str(&format!(
+        r#"{{"almacenamiento": "archivo", "registros_por_snapshot": {}}}"#,
+        registros_por_snapshot
+    ))
+    .unwrap()
+}
+
+fn abrir(config: &Config) -> Box<dyn Almacenamiento> {
+    let mut almacenamiento = almacenamiento::crear(config).unwrap();
+    almacenamiento.recuperar().unwrap();
+    almacenamiento
+}
+
+fn pago(id: usize) -> Pago {
+    Pago::new(
+        id,
+        Monto::new(1000, Moneda::POR_DEFECTO),
+        Monto::new(500, Moneda::POR_DEFECTO),
+    )
+}
+
+fn transaccion(secuencia: u32, id_pago: usize, estado: EstadoTransaccion) -> Transaccion {
+    let mut transaccion = Transaccion::new(
+        IdTransaccion::new(1, secuencia),
+        id_pago,
+        id_pago + 1,
+        estado,
+    );
+    transaccion.pago = Some(pago(id_pago));
+    transaccion
+}
+
+/// Persiste una transacción confirmada, una abortada con su pago fallido y
+/// una pendiente, y la época, y verifica que al reabrir el almacenamiento se
+/// recupere todo.
+fn verificar_recuperacion(config: &Config) {
+    {
+        let mut almacenamiento = abrir(config);
+        almacenamiento.registrar_epoca(5).unwrap();
+        for estado in &[
+            EstadoTransaccion::Prepare,
+            EstadoTransaccion::Commit,
+            EstadoTransaccion::Finalize,
+        ] {
+            almacenamiento
+                .insertar(&transaccion(1, 7, *estado))
+                .unwrap();
+        }
+
+        let motivo = MotivoAborto::new(CausaAborto::FondosInsuficientes, "Sin fondos");
+        let mut abort = transaccion(2, 8, EstadoTransaccion::Abort);
+        abort.motivo_aborto = Some(motivo.clone());
+        almacenamiento
+            .insertar(&transaccion(2, 8, EstadoTransaccion::Prepare))
+            .unwrap();
+        almacenamiento.insertar(&abort).unwrap();
+        almacenamiento
+            .insertar(&transaccion(2, 8, EstadoTransaccion::Finalize))
+            .unwrap();
+        almacenamiento
+            .escribir_fallido(&pago(8), Some(&motivo))
+            .unwrap();
+
+        almacenamiento
+            .insertar(&transaccion(3, 9, EstadoTransaccion::Prepare))
+            .unwrap();
+        almacenamiento.sincronizar().unwrap();
+    }
+
+    let mut almacenamiento = abrir(config);
+    assert_eq!(almacenamiento.epoca_maxima().unwrap(), 5);
+    assert_eq!(
+        almacenamiento.id_maximo().unwrap(),
+        IdTransaccion::new(1, 3)
+    );
+
+    let pendientes = almacenamiento.transacciones_pendientes().unwrap();
+    assert_eq!(pendientes.len(), 1);
+    assert_eq!(pendientes[0].id, IdTransaccion::new(1, 3));
+    assert_eq!(pendientes[0].estado(), EstadoTransaccion::Prepare);
+    assert_eq!(
+        pendientes[0]
+            .get_pago()
+            .unwrap()
+            .get_monto_aerolinea()
+            .centavos(),
+        1000
+    );
+
+    assert!(almacenamiento.pago_confirmado(7).unwrap());
+    assert!(!almacenamiento.pago_confirmado(8).unwrap());
+    assert!(!almacenamiento.pago_confirmado(9).unwrap());
+    assert_eq!(
+        almacenamiento
+            .obtener(IdTransaccion::new(1, 2))
+            .unwrap()
+            .unwrap()
+            .estado(),
+        EstadoTransaccion::Finalize
+    );
+
+    let fallidos = almacenamiento.listar_fallidos().unwrap();
+    assert_eq!(fallidos.len(), 1);
+    assert_eq!(fallidos[0].0.get_id(), 8);
+    assert_eq!(
+        fallidos[0].1.as_ref().map(|m| m.causa),
+        Some(CausaAborto::FondosInsuficientes)
+    );
+    assert_eq!(
+        almacenamiento.tomar_fallido(8).unwrap().map(|p| p.get_id()),
+        Some(8)
+    );
+    assert!(almacenamiento.tomar_fallido(8).unwrap().is_none());
+}
+
+#[test]
+fn almacenamiento_en_archivo_recupera_el_estado_al_reabrirse() {
+    let _guarda = DIRECTORIO.lock().unwrap();
+    directorio_temporal("archivo");
+    verificar_recuperacion(&config("archivo"));
+}
+
+#[test]
+fn almacenamiento_sqlite_recupera_el_estado_al_reabrirse() {
+    let _guarda = DIRECTORIO.lock().unwrap();
+    directorio_temporal("sqlite");
+    verificar_recuperacion(&config("sqlite"));
+}
+
+#[test]
+fn almacenamiento_en_archivo_recuerda_los_pagos_confirmados_luego_de_compactar() {
+    let _guarda = DIRECTORIO.lock().unwrap();
+    directorio_temporal("compactacion");
+    let config = config_compactando(4);
+    {
+        let mut almacenamiento = abrir(&config);
+        // El pago 3 se aborta y luego se reintenta, uniendo los rangos vecinos
+        for (secuencia, id_pago) in [1, 2, 3, 5, 6, 1000, 3].iter().enumerate() {
+            let resultado = if secuencia == 2 {
+                EstadoTransaccion::Abort
+            } else {
+                EstadoTransaccion::Commit
+            };
+            for estado in &[
+                EstadoTransaccion::Prepare,
+                resultado,
+                EstadoTransaccion::Finalize,
+            ] {
+                almacenamiento
+                    .insertar(&transaccion(secuencia as u32 + 1, *id_pago, *estado))
+                    .unwrap();
+            }
+        }
+        almacenamiento.sincronizar().unwrap();
+    }
+    assert!(fs::metadata("files/estado.snapshot").unwrap().len() > 0);
+
+    let almacenamiento = abrir(&config);
+    for id_pago in &[1, 2, 3, 5, 6, 1000] {
+        assert!(almacenamiento.pago_confirmado(*id_pago).unwrap());
+    }
+    for id_pago in &[0, 4, 7, 999, 1001] {
+        assert!(!almacenamiento.pago_confirmado(*id_pago).unwrap());
+    }
+}
@@ -0,0 +1,127 @@
+use alglobo::model::wal::{PoliticaDurabilidad, Wal};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Variable de entorno con la ruta del WAL con la que el test de escritura
+/// fallida se vuelve a ejecutar con un límite de tamaño de archivo.
+const RUTA_ESCRITURA_LIMITADA: &str = "ALGLOBO_TEST_WAL_LIMITADO";
+
+/// Devuelve una ruta temporal única para el test de nombre dado, sin archivo.
+fn ruta_temporal(nombre: &str) -> PathBuf {
+    let ruta = std::env::temp_dir().join(format!("alglobo-wal-{}-{}", nombre, std::process::id()));
+    let _ = fs::remove_file(&ruta);
+    ruta
+}
+
+/// Escribe los registros dados en un WAL nuevo en la ruta dada y devuelve el
+/// offset en el que comienza cada uno.
+fn escribir(ruta: &Path, registros: &[&[u8]]) -> Vec<u64> {
+    let (mut wal, _) = Wal::abrir(ruta, PoliticaDurabilidad::PorRegistro).unwrap();
+    registros.iter().map(|r| wal.escribir(r).unwrap()).collect()
+}
+
+fn invertir_byte(ruta: &Path, posicion: u64) {
+    let mut contenido = fs::read(ruta).unwrap();
+    contenido[posicion as usize] ^= 0xff;
+    fs::write(ruta, contenido).unwrap();
+}
+
+#[test]
+fn registro_incompleto_al_final_se_trunca() {
+    let ruta = ruta_temporal("incompleto");
+    let offsets = escribir(&ruta, &[b"uno", b"dos", b"tres"]);
+    let largo = fs::metadata(&ruta).unwrap().len();
+    fs::OpenOptions::new()
+        .write(true)
+        .open(&ruta)
+        .unwrap()
+        .set_len(largo - 2)
+        .unwrap();
+
+    let (_, registros) = Wal::abrir(&ruta, PoliticaDurabilidad::PorRegistro).unwrap();
+    assert_eq!(
+        registros
+            .into_iter()
+            .map(|(_, r)| r)
+            .collect::<Vec<Vec<u8>>>(),
+        vec![b"uno".to_vec(), b"dos".to_vec()]
+    );
+    assert_eq!(fs::metadata(&ruta).unwrap().len(), offsets[2]);
+    fs::remove_file(&ruta).unwrap();
+}
+
+#[test]
+fn largo_corrupto_al_final_se_trunca() {
+    let ruta = ruta_temporal("largo-final");
+    let offsets = escribir(&ruta, &[b"uno", b"dos"]);
+    invertir_byte(&ruta, offsets[1]);
+
+    let (_, registros) = Wal::abrir(&ruta, PoliticaDurabilidad::PorRegistro).unwrap();
+    assert_eq!(registros.len(), 1);
+    assert_eq!(fs::metadata(&ruta).unwrap().len(), offsets[1]);
+    fs::remove_file(&ruta).unwrap();
+}
+
+#[test]
+fn largo_corrupto_en_medio_del_archivo_es_error_sin_truncar() {
+    let ruta = ruta_temporal("largo-medio");
+    let offsets = escribir(&ruta, &[b"uno", b"dos", b"tres"]);
+    invertir_byte(&ruta, offsets[1]);
+    let largo = fs::metadata(&ruta).unwrap().len();
+
+    assert!(Wal::abrir(&ruta, PoliticaDurabilidad::PorRegistro).is_err());
+    assert!(Wal::leer(&ruta).is_err());
+    assert_eq!(fs::metadata(&ruta).unwrap().len(), largo);
+    fs::remove_file(&ruta).unwrap();
+}
+
+#[test]
+fn contenido_corrupto_en_medio_del_archivo_es_error_sin_truncar() {
+    let ruta = ruta_temporal("contenido-medio");
+    let offsets = escribir(&ruta, &[b"uno", b"dos", b"tres"]);
+    invertir_byte(&ruta, offsets[1] - 1);
+    let largo = fs::metadata(&ruta).unwrap().len();
+
+    assert!(Wal::abrir(&ruta, PoliticaDurabilidad::PorRegistro).is_err());
+    assert_eq!(fs::metadata(&ruta).unwrap().len(), largo);
+    fs::remove_file(&ruta).unwrap();
+}
+
+/// La escritura fallida se provoca con un límite de tamaño de archivo de un
+/// bloque, por lo que el test se vuelve a ejecutar en un proceso aparte con
+/// el límite y con la señal que lo acompaña ignorada. El registro que lo
+/// supera se escribe en parte y luego falla.
+#[cfg(unix)]
+#[test]
+fn escritura_fallida_no_deja_un_registro_incompleto() {
+    if let Ok(ruta) = env::var(RUTA_ESCRITURA_LIMITADA) {
+        let (mut wal, _) = Wal::abrir(Path::new(&ruta), PoliticaDurabilidad::PorRegistro).unwrap();
+        wal.escribir(&[1; 100]).unwrap();
+        assert!(wal.escribir(&[2; 2000]).is_err());
+        wal.escribir(&[3; 100]).unwrap();
+        return;
+    }
+
+    let ruta = ruta_temporal("escritura-fallida");
+    let estado = Command::new("sh")
+        .arg("-c")
+        .arg("trap '' XFSZ; ulimit -f 1; exec \"$0\" \"$1\" --exact")
+        .arg(env::current_exe().unwrap())
+        .arg("escritura_fallida_no_deja_un_registro_incompleto")
+        .env(RUTA_ESCRITURA_LIMITADA, &ruta)
+        .status()
+        .unwrap();
+    assert!(estado.success());
+
+    let (_, registros) = Wal::abrir(&ruta, PoliticaDurabilidad::PorRegistro).unwrap();
+    assert_eq!(
+        registros
+            .into_iter()
+            .map(|(_, r)| r)
+            .collect::<Vec<Vec<u8>>>(),
+        vec![vec![1; 100], vec![3; 100]]
+    );
+    fs::remove_file(&ruta).unwrap();
+}
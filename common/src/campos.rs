@@ -0,0 +1,75 @@
+use super::error::{ErrorApp, ErrorInterno, Resultado};
+use std::collections::BTreeMap;
+
+/// Separador de los campos codificados
+const SEPARADOR: char = ';';
+/// Separador de la clave y el valor de un campo
+const ASIGNACION: char = '=';
+
+/// Convierte los campos dados a texto con el formato `clave=valor;clave=valor`
+/// y lo devuelve. Los caracteres que no son alfanuméricos ni `.`, `-`, `_` o
+/// `:` se escapan como `%XX`, con los bytes de su representación UTF-8, de
+/// forma que el texto no contiene separadores de otros formatos como
+/// espacios, comas o `|`.
+pub fn codificar(campos: &BTreeMap<String, String>) -> String {
+    campos
+        .iter()
+        .map(|(clave, valor)| format!("{}{}{}", escapar(clave), ASIGNACION, escapar(valor)))
+        .collect::<Vec<String>>()
+        .join(&SEPARADOR.to_string())
+}
+
+/// Convierte el texto generado por `codificar` a campos y los devuelve. Un
+/// texto vacío no tiene campos.
+/// Devuelve error si el texto no tiene el formato esperado.
+pub fn decodificar(codificado: &str) -> Resultado<BTreeMap<String, String>> {
+    codificado
+        .split(SEPARADOR)
+        .filter(|c| !c.is_empty())
+        .map(|campo| {
+            let (clave, valor) = campo.split_once(ASIGNACION).ok_or_else(|| {
+                ErrorApp::Interno(ErrorInterno::new(&format!("Campo erroneo: {}", campo)))
+            })?;
+            Ok((desescapar(clave)?, desescapar(valor)?))
+        })
+        .collect()
+}
+
+/// Escapa los caracteres del texto que no se mantienen al codificar, de
+/// forma que pueda incluirse en otros formatos de texto.
+pub fn escapar(texto: &str) -> String {
+    let mut escapado = String::with_capacity(texto.len());
+    for byte in texto.bytes() {
+        if byte.is_ascii_alphanumeric() || b".-_:".contains(&byte) {
+            escapado.push(byte as char);
+        } else {
+            escapado.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    escapado
+}
+
+/// Revierte el escape de `escapar`.
+/// Devuelve error si alguna secuencia de escape es inválida.
+pub fn desescapar(texto: &str) -> Resultado<String> {
+    let error = || {
+        ErrorApp::Interno(ErrorInterno::new(&format!(
+            "Escape erroneo en campo: {}",
+            texto
+        )))
+    };
+    let mut bytes = Vec::with_capacity(texto.len());
+    let mut resto = texto.as_bytes();
+    while let Some((byte, siguiente)) = resto.split_first() {
+        if *byte == b'%' {
+            let hexa = siguiente.get(..2).ok_or_else(error)?;
+            let hexa = std::str::from_utf8(hexa).map_err(|_| error())?;
+            bytes.push(u8::from_str_radix(hexa, 16).map_err(|_| error())?);
+            resto = &siguiente[2..];
+        } else {
+            bytes.push(*byte);
+            resto = siguiente;
+        }
+    }
+    Ok(String::from_utf8(bytes)?)
+}
@@ -1,3 +1,7 @@
+/// Cantidad maxima de nodos de alglobo. Los ids de los nodos van de 0 a
+/// NODOS_ALGLOBO - 1.
+pub const NODOS_ALGLOBO: usize = 7;
+
 /// DNS implementa la traduccion de direcciones segun el id y el tipo de
 /// proceso.
 pub struct DNS {}
@@ -22,4 +26,11 @@ impl DNS {
     pub fn direccion_lider(id: &usize) -> String {
         format!("127.0.0.1:700{}", *id)
     }
+
+    /// Devuelve la dirección asociada al nodo de alglobo correspondiente al id
+    /// pasado por parámetro. Esta dirección es la utilizada por los clientes
+    /// para enviar pagos.
+    pub fn direccion_pagos(id: &usize) -> String {
+        format!("127.0.0.1:800{}", *id)
+    }
 }
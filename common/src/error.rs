@@ -2,7 +2,7 @@ use regex::Error as RegexError;
 use std::error::Error;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::io::Error as IOError;
-use std::num::{ParseIntError, ParseFloatError};
+use std::num::{ParseFloatError, ParseIntError};
 use std::string::FromUtf8Error;
 
 /// Clase utilizada para manejar error internos del sistema
@@ -41,11 +41,10 @@ pub enum ErrorApp {
     ErrorRegex(RegexError),
     ErrorUtf8(FromUtf8Error),
     ErrorParseoInt(ParseIntError),
-    ErrorParseoFloat(ParseFloatError)
+    ErrorParseoFloat(ParseFloatError),
 }
 
 /// Tipo de resultado
-
 pub type Resultado<T> = std::result::Result<T, ErrorApp>;
 
 //Conversion de errores a ErrorApp
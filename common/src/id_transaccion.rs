@@ -0,0 +1,69 @@
+use super::error::{ErrorApp, ErrorInterno, Resultado};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// IdTransaccion identifica una transacción de forma única entre todos los
+/// líderes. Se compone de la época de liderazgo en la que se creó la
+/// transacción y de una secuencia dentro de esa época. Como cada época
+/// pertenece a un único nodo, dos líderes nunca generan el mismo id.
+/// Las transacciones creadas por versiones anteriores, identificadas solo
+/// por un número, corresponden a la época 0.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct IdTransaccion {
+    pub epoca: u32,
+    pub secuencia: u32,
+}
+
+impl IdTransaccion {
+    /// Devuelve una instancia de IdTransaccion.
+    /// Recibe la época de liderazgo y la secuencia dentro de la misma.
+    pub fn new(epoca: u32, secuencia: u32) -> Self {
+        IdTransaccion { epoca, secuencia }
+    }
+
+    /// Devuelve el id siguiente dentro de la misma época.
+    pub fn siguiente(&self) -> Self {
+        IdTransaccion::new(self.epoca, self.secuencia + 1)
+    }
+
+    /// Convierte el IdTransaccion a un número, con la época en los 32 bits
+    /// más significativos, y lo devuelve. El orden de los números coincide
+    /// con el de los ids, y los ids de la época 0 coinciden con los números
+    /// de las versiones anteriores.
+    pub fn a_numero(&self) -> u64 {
+        ((self.epoca as u64) << 32) | self.secuencia as u64
+    }
+
+    /// Convierte un número generado por `a_numero` a IdTransaccion y lo
+    /// devuelve.
+    pub fn desde_numero(numero: u64) -> Self {
+        IdTransaccion::new((numero >> 32) as u32, numero as u32)
+    }
+
+    /// Convierte el texto `epoca.secuencia` a IdTransaccion y lo devuelve.
+    /// Un número sin época corresponde a la época 0.
+    /// Devuelve error si el texto no es un id válido.
+    pub fn decodificar(codificado: &str) -> Resultado<Self> {
+        let error = || {
+            ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Id de transaccion erroneo: {}",
+                codificado
+            )))
+        };
+        match codificado.split_once('.') {
+            Some((epoca, secuencia)) => Ok(IdTransaccion::new(
+                epoca.parse::<u32>().map_err(|_| error())?,
+                secuencia.parse::<u32>().map_err(|_| error())?,
+            )),
+            None => Ok(IdTransaccion::new(
+                0,
+                codificado.parse::<u32>().map_err(|_| error())?,
+            )),
+        }
+    }
+}
+
+impl Display for IdTransaccion {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.pad(&format!("{}.{}", self.epoca, self.secuencia))
+    }
+}
@@ -1,4 +1,10 @@
+pub mod campos;
+pub mod dns;
 pub mod error;
-pub mod protocolo_transaccion;
+pub mod id_transaccion;
+pub mod monto;
+pub mod motivo_aborto;
 pub mod protocolo_lider;
-pub mod dns;
+pub mod protocolo_pagos;
+pub mod protocolo_transaccion;
+pub mod reloj;
@@ -0,0 +1,139 @@
+use super::error::{ErrorApp, ErrorInterno, Resultado};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Moneda identifica la moneda de un monto con su código ISO 4217 de tres
+/// letras mayúsculas.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Moneda([u8; 3]);
+
+impl Moneda {
+    /// Moneda de los montos que no indican una, como los de los archivos de
+    /// entrada y los registros de versiones anteriores.
+    pub const POR_DEFECTO: Moneda = Moneda(*b"ARS");
+
+    /// Convierte el código de tres letras a Moneda y lo devuelve.
+    /// Devuelve error si el código no tiene tres letras mayúsculas.
+    pub fn decodificar(codigo: &str) -> Resultado<Moneda> {
+        match codigo.as_bytes() {
+            [a, b, c] if codigo.bytes().all(|l| l.is_ascii_uppercase()) => {
+                Ok(Moneda([*a, *b, *c]))
+            }
+            _ => Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Moneda erronea: {}",
+                codigo
+            )))),
+        }
+    }
+
+    /// Devuelve el código de la moneda.
+    pub fn codigo(&self) -> &str {
+        //Se construye solo con letras ASCII, por lo que siempre es UTF-8.
+        std::str::from_utf8(&self.0).expect("Codigo de moneda invalido")
+    }
+}
+
+impl Display for Moneda {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.pad(self.codigo())
+    }
+}
+
+/// Monto representa una cantidad de dinero no negativa en una moneda, como
+/// un número entero de centavos, de forma que las operaciones sobre montos
+/// son exactas.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Monto {
+    centavos: u64,
+    moneda: Moneda,
+}
+
+impl Monto {
+    /// Devuelve una instancia de Monto.
+    /// Recibe la cantidad de centavos y la moneda.
+    pub fn new(centavos: u64, moneda: Moneda) -> Self {
+        Monto { centavos, moneda }
+    }
+
+    /// Devuelve la cantidad de centavos del monto.
+    pub fn centavos(&self) -> u64 {
+        self.centavos
+    }
+
+    /// Devuelve la moneda del monto.
+    pub fn moneda(&self) -> Moneda {
+        self.moneda
+    }
+
+    /// Convierte un número decimal no negativo con a lo sumo dos decimales,
+    /// como `12`, `12.5` o `12.50`, a Monto en la moneda dada y lo devuelve.
+    /// Devuelve error si el texto no es un número de ese tipo.
+    pub fn decodificar_decimal(decimal: &str, moneda: Moneda) -> Resultado<Monto> {
+        let error = || {
+            ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Monto erroneo: {}",
+                decimal
+            )))
+        };
+        let (entero, fraccion) = decimal.split_once('.').unwrap_or((decimal, "00"));
+        let digitos = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if !digitos(entero) || !digitos(fraccion) || fraccion.len() > 2 {
+            return Err(error());
+        }
+        let centavos = format!("{:0<2}", fraccion).parse::<u64>()?;
+        entero
+            .parse::<u64>()
+            .ok()
+            .and_then(|e| e.checked_mul(100))
+            .and_then(|e| e.checked_add(centavos))
+            .map(|centavos| Monto::new(centavos, moneda))
+            .ok_or_else(error)
+    }
+
+    /// Convierte el texto `decimal moneda`, generado por `Display`, a Monto y
+    /// lo devuelve. Un decimal sin moneda corresponde a la moneda por
+    /// defecto.
+    /// Devuelve error si el texto no es un monto válido.
+    pub fn decodificar(codificado: &str) -> Resultado<Monto> {
+        match codificado.split_once(' ') {
+            Some((decimal, moneda)) => {
+                Monto::decodificar_decimal(decimal, Moneda::decodificar(moneda)?)
+            }
+            None => Monto::decodificar_decimal(codificado, Moneda::POR_DEFECTO),
+        }
+    }
+
+    /// Convierte un monto guardado como número de punto flotante por una
+    /// versión anterior, redondeado a centavos, a Monto en la moneda por
+    /// defecto y lo devuelve.
+    pub fn desde_f64(valor: f64) -> Monto {
+        Monto::new((valor * 100.0).round().max(0.0) as u64, Moneda::POR_DEFECTO)
+    }
+
+    /// Devuelve el monto como número decimal con dos decimales, sin la
+    /// moneda.
+    pub fn decimal(&self) -> String {
+        format!("{}.{:02}", self.centavos / 100, self.centavos % 100)
+    }
+
+    /// Devuelve la suma de los montos.
+    /// Devuelve error si los montos están en distintas monedas o si la suma
+    /// excede el máximo representable.
+    pub fn sumar(&self, otro: &Monto) -> Resultado<Monto> {
+        if self.moneda != otro.moneda {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "No se pueden sumar montos en {} y {}",
+                self.moneda, otro.moneda
+            ))));
+        }
+        self.centavos
+            .checked_add(otro.centavos)
+            .map(|centavos| Monto::new(centavos, self.moneda))
+            .ok_or_else(|| ErrorApp::Interno(ErrorInterno::new("Monto fuera de rango")))
+    }
+}
+
+impl Display for Monto {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.pad(&format!("{} {}", self.decimal(), self.moneda))
+    }
+}
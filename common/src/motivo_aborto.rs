@@ -0,0 +1,97 @@
+use super::error::{ErrorApp, ErrorInterno, Resultado};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// CausaAborto representa la causa por la cual una transacción fue abortada.
+/// # Variantes
+/// FondosInsuficientes: el banco no dispone de fondos para el monto pedido.
+/// SinDisponibilidad: la aerolínea o el hotel no disponen de lugar.
+/// Timeout: la transacción superó su deadline.
+/// OperacionEnCurso: otra operación del mismo pago se encuentra en curso.
+/// MonedaNoSoportada: no se pudo convertir algún monto del pago a la moneda
+/// de liquidación de un participante.
+/// Desconocida: no se conoce la causa del abort.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CausaAborto {
+    FondosInsuficientes,
+    SinDisponibilidad,
+    Timeout,
+    OperacionEnCurso,
+    MonedaNoSoportada,
+    Desconocida,
+}
+
+impl CausaAborto {
+    /// Convierte la CausaAborto a su código y lo devuelve.
+    pub fn codificar(&self) -> &'static str {
+        match self {
+            CausaAborto::FondosInsuficientes => "FONDOS_INSUFICIENTES",
+            CausaAborto::SinDisponibilidad => "SIN_DISPONIBILIDAD",
+            CausaAborto::Timeout => "TIMEOUT",
+            CausaAborto::OperacionEnCurso => "OPERACION_EN_CURSO",
+            CausaAborto::MonedaNoSoportada => "MONEDA_NO_SOPORTADA",
+            CausaAborto::Desconocida => "DESCONOCIDA",
+        }
+    }
+
+    /// Convierte el código a CausaAborto y lo devuelve.
+    /// Devuelve error si el código no matchea con ninguna causa.
+    pub fn decodificar(codigo: &str) -> Resultado<CausaAborto> {
+        match codigo {
+            "FONDOS_INSUFICIENTES" => Ok(CausaAborto::FondosInsuficientes),
+            "SIN_DISPONIBILIDAD" => Ok(CausaAborto::SinDisponibilidad),
+            "TIMEOUT" => Ok(CausaAborto::Timeout),
+            "OPERACION_EN_CURSO" => Ok(CausaAborto::OperacionEnCurso),
+            "MONEDA_NO_SOPORTADA" => Ok(CausaAborto::MonedaNoSoportada),
+            "DESCONOCIDA" => Ok(CausaAborto::Desconocida),
+            _ => Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Causa de abort erronea: {}",
+                codigo
+            )))),
+        }
+    }
+}
+
+/// MotivoAborto representa el motivo de un abort. Contiene la causa, que puede
+/// ser interpretada por el sistema, y una descripción para los operadores.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MotivoAborto {
+    pub causa: CausaAborto,
+    pub descripcion: String,
+}
+
+impl MotivoAborto {
+    /// Devuelve una instancia de MotivoAborto.
+    /// Recibe la causa del abort y su descripción.
+    pub fn new(causa: CausaAborto, descripcion: &str) -> Self {
+        Self {
+            causa,
+            descripcion: descripcion.to_string(),
+        }
+    }
+
+    /// Convierte el MotivoAborto a String y lo devuelve. La descripción no
+    /// puede contener el separador indicado, por lo que se reemplaza.
+    pub fn codificar(&self, separador: char) -> String {
+        format!(
+            "{} {}",
+            self.causa.codificar(),
+            self.descripcion.replace(separador, " ")
+        )
+    }
+
+    /// Convierte el String a MotivoAborto y lo devuelve.
+    /// Devuelve error si la causa no matchea con ninguna conocida.
+    pub fn decodificar(codificado: &str) -> Resultado<MotivoAborto> {
+        let (causa, descripcion) = codificado.split_once(' ').unwrap_or((codificado, ""));
+        Ok(MotivoAborto::new(
+            CausaAborto::decodificar(causa)?,
+            descripcion,
+        ))
+    }
+}
+
+impl Display for MotivoAborto {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} ({})", self.descripcion, self.causa.codificar())
+    }
+}
@@ -14,7 +14,7 @@ pub enum CodigoLider {
     OK,
     ELECCION,
     COORDINADOR,
-    VERIFICAR
+    VERIFICAR,
 }
 
 /// MensajeLider representa un mensaje utilizado para la comunicación en el
@@ -22,14 +22,14 @@ pub enum CodigoLider {
 #[derive(Clone, Debug)]
 pub struct MensajeLider {
     pub codigo: CodigoLider,
-    pub id_emisor: usize
+    pub id_emisor: usize,
 }
 
 impl MensajeLider {
     /// Devuelve una instancia de MensajeLider.
     /// Recibe el codigo del mensaje y el id del emisor.
-    pub fn new(codigo: CodigoLider, id_emisor: usize) -> Self { 
-        Self { codigo, id_emisor } 
+    pub fn new(codigo: CodigoLider, id_emisor: usize) -> Self {
+        Self { codigo, id_emisor }
     }
 
     /// Convierte el CodigoLider a String y lo devuelve.
@@ -44,20 +44,22 @@ impl MensajeLider {
 
     /// Convierte el String a CodigoLider y lo devuelve.
     /// Devuelve error si el String no matchea con algún código.
-    pub fn decodificar(mensaje_codificado: &String) -> Resultado<MensajeLider> {
+    pub fn decodificar(mensaje_codificado: &str) -> Resultado<MensajeLider> {
         let parseado = mensaje_codificado.split(' ').collect::<Vec<&str>>();
         let codigo = match parseado[0] {
             "OK" => CodigoLider::OK,
             "ELECCION" => CodigoLider::ELECCION,
             "COORDINADOR" => CodigoLider::COORDINADOR,
             "VERIFICAR" => CodigoLider::VERIFICAR,
-            _ => return Err(ErrorApp::Interno(ErrorInterno::new(&format!("Mensaje erroneo: {}", parseado[0])))),
+            _ => {
+                return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                    "Mensaje erroneo: {}",
+                    parseado[0]
+                ))))
+            }
         };
 
-        Ok(MensajeLider::new(
-            codigo, 
-            parseado[1].parse::<usize>()?, 
-        ))
+        Ok(MensajeLider::new(codigo, parseado[1].parse::<usize>()?))
     }
 }
 
@@ -99,7 +101,9 @@ impl ProtocoloLider {
     pub fn recibir(&mut self, timeout: Option<Duration>) -> Resultado<MensajeLider> {
         let mut buffer = vec![0; TAM_BUFFER];
         if self.skt.set_read_timeout(timeout).is_err() {
-            return Err(ErrorApp::Interno(ErrorInterno::new("Error al setear timeout")));
+            return Err(ErrorApp::Interno(ErrorInterno::new(
+                "Error al setear timeout",
+            )));
         };
         let (recibido, _src) = self.skt.recv_from(&mut buffer)?;
 
@@ -110,9 +114,12 @@ impl ProtocoloLider {
     }
 
     /// Devuelve una copia de ProtocoloLider
-    pub fn clone(&self) -> Self {
+    pub fn try_clone(&self) -> Self {
         ProtocoloLider {
-            skt: self.skt.try_clone().expect("Error al intentar clonar el socket en ProtocoloLider"),
+            skt: self
+                .skt
+                .try_clone()
+                .expect("Error al intentar clonar el socket en ProtocoloLider"),
         }
     }
 }
@@ -0,0 +1,300 @@
+use super::campos;
+use super::error::{ErrorApp, ErrorInterno, Resultado};
+use super::monto::{Moneda, Monto};
+use super::motivo_aborto::MotivoAborto;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// MensajePago representa un mensaje del protocolo de envío de pagos a un
+/// nodo de alglobo. Cada mensaje ocupa una línea.
+/// # Variantes
+/// PAGAR: utilizado por el cliente para enviar un pago. Contiene los montos
+/// de la aerolínea y del hotel con su moneda, y los datos de la reserva por
+/// nombre, que se envían solo si hay alguno.
+/// REINTENTAR: utilizado por el cliente para reintentar el pago fallido del
+/// id que contiene.
+/// ESTADO: utilizado por el cliente para consultar el estado del pago del id
+/// que contiene.
+/// LISTAR: utilizado por el cliente para pedir los pagos fallidos.
+/// ACEPTADO: utilizado por el líder para avisar que aceptó el pago, o su
+/// reintento, con el id que contiene, antes de ejecutar la transacción.
+/// CONFIRMADO: utilizado por el líder para avisar que el pago del id que
+/// contiene se commiteó.
+/// FALLIDO: utilizado por el líder para avisar que el pago del id que
+/// contiene se abortó, junto al motivo del abort.
+/// PENDIENTE: utilizado por el líder para avisar que la transacción del pago
+/// del id que contiene no terminó.
+/// DESCONOCIDO: utilizado por el líder para avisar que no conoce el pago del
+/// id que contiene.
+/// PAGO_FALLIDO: utilizado por el líder para enviar cada pago fallido, con
+/// sus montos, sus datos de la reserva y el motivo del abort, si se conoce.
+/// FIN: utilizado por el líder para avisar que terminó de enviar los pagos
+/// fallidos.
+/// RECHAZADO: utilizado por el líder para avisar que el pedido no es válido,
+/// junto al motivo.
+/// LIDER: utilizado por un nodo que no es el líder para redirigir al cliente
+/// al líder del id que contiene.
+/// SIN_LIDER: utilizado por un nodo que no conoce al líder porque hay una
+/// elección en curso.
+#[derive(Clone, PartialEq, Debug)]
+pub enum MensajePago {
+    PAGAR {
+        monto_aerolinea: Monto,
+        monto_hotel: Monto,
+        datos: BTreeMap<String, String>,
+    },
+    REINTENTAR {
+        id_pago: usize,
+    },
+    ESTADO {
+        id_pago: usize,
+    },
+    LISTAR,
+    ACEPTADO {
+        id_pago: usize,
+    },
+    CONFIRMADO {
+        id_pago: usize,
+    },
+    FALLIDO {
+        id_pago: usize,
+        motivo: MotivoAborto,
+    },
+    PENDIENTE {
+        id_pago: usize,
+    },
+    DESCONOCIDO {
+        id_pago: usize,
+    },
+    #[allow(non_camel_case_types)]
+    PAGO_FALLIDO {
+        id_pago: usize,
+        monto_aerolinea: Monto,
+        monto_hotel: Monto,
+        datos: BTreeMap<String, String>,
+        motivo: Option<MotivoAborto>,
+    },
+    FIN,
+    RECHAZADO {
+        motivo: String,
+    },
+    LIDER {
+        id_lider: usize,
+    },
+    #[allow(non_camel_case_types)]
+    SIN_LIDER,
+}
+
+impl MensajePago {
+    /// Convierte el MensajePago a String y lo devuelve.
+    pub fn codificar(&self) -> String {
+        match self {
+            MensajePago::PAGAR {
+                monto_aerolinea,
+                monto_hotel,
+                datos,
+            } => {
+                let mut codificado = format!("PAGAR {} {}", monto_aerolinea, monto_hotel);
+                if !datos.is_empty() {
+                    codificado.push_str(&format!(" {}", campos::codificar(datos)));
+                }
+                codificado
+            }
+            MensajePago::REINTENTAR { id_pago } => format!("REINTENTAR {}", id_pago),
+            MensajePago::ESTADO { id_pago } => format!("ESTADO {}", id_pago),
+            MensajePago::LISTAR => "LISTAR".to_string(),
+            MensajePago::ACEPTADO { id_pago } => format!("ACEPTADO {}", id_pago),
+            MensajePago::CONFIRMADO { id_pago } => format!("CONFIRMADO {}", id_pago),
+            MensajePago::FALLIDO { id_pago, motivo } => {
+                format!("FALLIDO {} {}", id_pago, motivo.codificar('\n'))
+            }
+            MensajePago::PENDIENTE { id_pago } => format!("PENDIENTE {}", id_pago),
+            MensajePago::DESCONOCIDO { id_pago } => format!("DESCONOCIDO {}", id_pago),
+            MensajePago::PAGO_FALLIDO {
+                id_pago,
+                monto_aerolinea,
+                monto_hotel,
+                datos,
+                motivo,
+            } => {
+                let mut codificado = format!(
+                    "PAGO_FALLIDO {} {} {} +{}",
+                    id_pago,
+                    monto_aerolinea,
+                    monto_hotel,
+                    campos::codificar(datos)
+                );
+                if let Some(motivo) = motivo {
+                    codificado.push_str(&format!(" {}", motivo.codificar('\n')));
+                }
+                codificado
+            }
+            MensajePago::FIN => "FIN".to_string(),
+            MensajePago::RECHAZADO { motivo } => {
+                format!("RECHAZADO {}", motivo.replace('\n', " "))
+            }
+            MensajePago::LIDER { id_lider } => format!("LIDER {}", id_lider),
+            MensajePago::SIN_LIDER => "SIN_LIDER".to_string(),
+        }
+    }
+
+    /// Convierte el String a MensajePago y lo devuelve.
+    /// Devuelve error si el String no matchea con algún mensaje.
+    pub fn decodificar(mensaje_codificado: &str) -> Resultado<MensajePago> {
+        let error = || {
+            ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Mensaje erroneo: {}",
+                mensaje_codificado
+            )))
+        };
+        let (codigo, argumentos) = mensaje_codificado
+            .split_once(' ')
+            .unwrap_or((mensaje_codificado, ""));
+        let id_pago = || -> Resultado<usize> {
+            Ok(argumentos
+                .split(' ')
+                .next()
+                .unwrap_or_default()
+                .parse::<usize>()?)
+        };
+        Ok(match codigo {
+            "PAGAR" => {
+                let argumentos = argumentos.split(' ').collect::<Vec<&str>>();
+                if argumentos.len() != 4 && argumentos.len() != 5 {
+                    return Err(error());
+                }
+                MensajePago::PAGAR {
+                    monto_aerolinea: Monto::decodificar_decimal(
+                        argumentos[0],
+                        Moneda::decodificar(argumentos[1])?,
+                    )?,
+                    monto_hotel: Monto::decodificar_decimal(
+                        argumentos[2],
+                        Moneda::decodificar(argumentos[3])?,
+                    )?,
+                    datos: argumentos
+                        .get(4)
+                        .map(|d| campos::decodificar(d))
+                        .transpose()?
+                        .unwrap_or_default(),
+                }
+            }
+            "REINTENTAR" => MensajePago::REINTENTAR {
+                id_pago: id_pago()?,
+            },
+            "ESTADO" => MensajePago::ESTADO {
+                id_pago: id_pago()?,
+            },
+            "LISTAR" => MensajePago::LISTAR,
+            "ACEPTADO" => MensajePago::ACEPTADO {
+                id_pago: id_pago()?,
+            },
+            "CONFIRMADO" => MensajePago::CONFIRMADO {
+                id_pago: id_pago()?,
+            },
+            "FALLIDO" => {
+                let (_, motivo) = argumentos.split_once(' ').ok_or_else(error)?;
+                MensajePago::FALLIDO {
+                    id_pago: id_pago()?,
+                    motivo: MotivoAborto::decodificar(motivo)?,
+                }
+            }
+            "PENDIENTE" => MensajePago::PENDIENTE {
+                id_pago: id_pago()?,
+            },
+            "DESCONOCIDO" => MensajePago::DESCONOCIDO {
+                id_pago: id_pago()?,
+            },
+            "PAGO_FALLIDO" => {
+                let argumentos = argumentos.splitn(7, ' ').collect::<Vec<&str>>();
+                if argumentos.len() < 6 {
+                    return Err(error());
+                }
+                let datos = argumentos[5].strip_prefix('+').ok_or_else(error)?;
+                MensajePago::PAGO_FALLIDO {
+                    id_pago: argumentos[0].parse::<usize>()?,
+                    monto_aerolinea: Monto::decodificar_decimal(
+                        argumentos[1],
+                        Moneda::decodificar(argumentos[2])?,
+                    )?,
+                    monto_hotel: Monto::decodificar_decimal(
+                        argumentos[3],
+                        Moneda::decodificar(argumentos[4])?,
+                    )?,
+                    datos: campos::decodificar(datos)?,
+                    motivo: argumentos
+                        .get(6)
+                        .map(|m| MotivoAborto::decodificar(m))
+                        .transpose()?,
+                }
+            }
+            "FIN" => MensajePago::FIN,
+            "RECHAZADO" => MensajePago::RECHAZADO {
+                motivo: argumentos.to_string(),
+            },
+            "LIDER" => MensajePago::LIDER {
+                id_lider: argumentos.parse::<usize>()?,
+            },
+            "SIN_LIDER" => MensajePago::SIN_LIDER,
+            _ => return Err(error()),
+        })
+    }
+
+    /// Devuelve verdadero si el mensaje termina la respuesta a un pedido, es
+    /// decir, si no es el aviso de que un pago se aceptó ni uno de los pagos
+    /// fallidos listados.
+    pub fn es_final(&self) -> bool {
+        !matches!(
+            self,
+            MensajePago::ACEPTADO { .. } | MensajePago::PAGO_FALLIDO { .. }
+        )
+    }
+}
+
+/// ProtocoloPagos encapsula la comunicación por socket TCP entre un cliente
+/// y un nodo de alglobo para el envío de pagos. Implementa el envío y la
+/// recepción de mensajes, de a una línea, encapsulando la codificación y
+/// decodificación de estos.
+pub struct ProtocoloPagos {
+    skt: TcpStream,
+    lector: BufReader<TcpStream>,
+}
+
+impl ProtocoloPagos {
+    /// Devuelve una instancia de ProtocoloPagos sobre una conexión aceptada.
+    pub fn new(skt: TcpStream) -> Resultado<ProtocoloPagos> {
+        Ok(ProtocoloPagos {
+            lector: BufReader::new(skt.try_clone()?),
+            skt,
+        })
+    }
+
+    /// Devuelve una instancia de ProtocoloPagos conectada a la dirección
+    /// dada.
+    pub fn conectar(direccion: String) -> Resultado<ProtocoloPagos> {
+        ProtocoloPagos::new(TcpStream::connect(direccion)?)
+    }
+
+    /// Codifica el mensaje y lo envía.
+    pub fn enviar(&mut self, mensaje: &MensajePago) -> Resultado<()> {
+        self.skt
+            .write_all(format!("{}\n", mensaje.codificar()).as_bytes())?;
+        Ok(())
+    }
+
+    /// Recibe un timeout. Si el timeout es None, se bloquea hasta recibir un
+    /// mensaje. Devuelve None si la conexión se cerró, y error si ocurre el
+    /// timeout.
+    pub fn recibir(&mut self, timeout: Option<Duration>) -> Resultado<Option<MensajePago>> {
+        self.skt.set_read_timeout(timeout)?;
+        let mut linea = String::new();
+        if self.lector.read_line(&mut linea)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(MensajePago::decodificar(
+            linea.trim_end_matches(['\n', '\r']),
+        )?))
+    }
+}
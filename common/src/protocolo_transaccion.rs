@@ -1,76 +1,217 @@
+use super::campos;
 use super::error::{ErrorApp, ErrorInterno, Resultado};
+use super::id_transaccion::IdTransaccion;
+use super::monto::{Moneda, Monto};
+use super::motivo_aborto::MotivoAborto;
+use std::collections::BTreeMap;
 use std::net::UdpSocket;
 use std::time::Duration;
 
-static TAM_BUFFER: usize = 256;
+/// Tamaño máximo de un datagrama UDP, necesario para recibir lotes de mensajes
+pub static TAM_BUFFER: usize = 65507;
+/// Prefijo de los datagramas que contienen un lote de mensajes
+static PREFIJO_LOTE: &str = "LOTE";
+/// Separador de los mensajes dentro de un lote
+static SEPARADOR_LOTE: char = '|';
 
 /// CodigoTransaccion representa el codigo del mensaje de transacción.
 /// # Variantes
-/// PREPARE: utilizado para avisar que tomen recursos.
+/// PREPARE: utilizado para avisar que tomen recursos. Contiene el monto con
+/// su moneda, la clave de idempotencia de la operación, derivada del id del
+/// pago, que se mantiene entre los reintentos de un mismo pago, y el deadline
+/// de la transacción en milisegundos desde UNIX_EPOCH. Contiene además los
+/// datos de la reserva relevantes para el participante, por nombre, que se
+/// envían solo si hay alguno.
 /// READY: utilizado para avisar que se tomaron recursos y están listos para
 /// hacer el commit.
 /// COMMIT: utilizado tanto para avisar que se haga el commit como para avisar
 /// que se terminó el commit.
 /// ABORT: utilizado tanto para avisar que se haga el abort como para avisar
-/// que se terminó el abort.
+/// que se terminó el abort. Cuando es utilizado como voto contiene el motivo
+/// del abort.
+/// READ_ONLY: utilizado para avisar que se acepta la transacción pero que no
+/// hay nada para commitear, por lo que el participante no necesita participar
+/// del commit ni del abort.
 #[derive(Clone, PartialEq, Debug)]
 pub enum CodigoTransaccion {
-    PREPARE { monto: f64 },
+    PREPARE {
+        monto: Monto,
+        clave: usize,
+        deadline: u64,
+        datos: BTreeMap<String, String>,
+    },
     READY,
     COMMIT,
-    ABORT
+    ABORT {
+        motivo: Option<MotivoAborto>,
+    },
+    #[allow(non_camel_case_types)]
+    READ_ONLY,
 }
 
 /// MensajeTransaccion representa un mensaje utilizado para la comunicación en
-/// el algoritmo de transaccionalidad.
+/// el algoritmo de transaccionalidad. El id de la operación es el id de la
+/// transacción, único entre todos los líderes.
 #[derive(Clone)]
 pub struct MensajeTransaccion {
     pub codigo: CodigoTransaccion,
     pub id_emisor: usize,
-    pub id_op: usize
+    pub id_op: IdTransaccion,
 }
 
 impl MensajeTransaccion {
     /// Devuelve una instancia de MensajeTransaccion.
     /// Recibe el codigo del mensaje, el id del emisor y el id de la operacion.
-    pub fn new(codigo: CodigoTransaccion, id_emisor: usize, id_op: usize) -> Self { 
-        Self { codigo, id_emisor, id_op } 
+    pub fn new(codigo: CodigoTransaccion, id_emisor: usize, id_op: IdTransaccion) -> Self {
+        Self {
+            codigo,
+            id_emisor,
+            id_op,
+        }
     }
 
     /// Convierte el CodigoTransaccion a String y lo devuelve.
     pub fn codificar(&self) -> String {
         match &self.codigo {
-            CodigoTransaccion::PREPARE { monto } => format!("PREPARE {} {} {}", self.id_emisor, self.id_op, monto),
+            CodigoTransaccion::PREPARE {
+                monto,
+                clave,
+                deadline,
+                datos,
+            } => {
+                let mut codificado = format!(
+                    "PREPARE {} {} {} {} {} {}",
+                    self.id_emisor,
+                    self.id_op,
+                    monto.decimal(),
+                    monto.moneda(),
+                    clave,
+                    deadline
+                );
+                if !datos.is_empty() {
+                    codificado.push_str(&format!(" {}", campos::codificar(datos)));
+                }
+                codificado
+            }
             CodigoTransaccion::COMMIT => format!("COMMIT {} {}", self.id_emisor, self.id_op),
             CodigoTransaccion::READY => format!("READY {} {}", self.id_emisor, self.id_op),
-            CodigoTransaccion::ABORT => format!("ABORT {} {}", self.id_emisor, self.id_op),
+            CodigoTransaccion::ABORT { motivo: None } => {
+                format!("ABORT {} {}", self.id_emisor, self.id_op)
+            }
+            CodigoTransaccion::ABORT {
+                motivo: Some(motivo),
+            } => format!(
+                "ABORT {} {} {}",
+                self.id_emisor,
+                self.id_op,
+                motivo.codificar(SEPARADOR_LOTE)
+            ),
+            CodigoTransaccion::READ_ONLY => format!("READ_ONLY {} {}", self.id_emisor, self.id_op),
         }
     }
 
     /// Convierte el String a CodigoTransaccion y lo devuelve.
-    /// Devuelve error si el String no matchea con algún código.
-    pub fn decodificar(mensaje_codificado: &String) -> Resultado<MensajeTransaccion> {
-        let parseado = mensaje_codificado.split(' ').collect::<Vec<&str>>();
+    /// Devuelve error si el String no matchea con algún código o le faltan
+    /// campos.
+    pub fn decodificar(mensaje_codificado: &str) -> Resultado<MensajeTransaccion> {
+        let parseado = mensaje_codificado.splitn(4, ' ').collect::<Vec<&str>>();
+        if parseado.len() < 3 {
+            return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Mensaje erroneo: {}",
+                mensaje_codificado
+            ))));
+        }
         let codigo = match parseado[0] {
-            "PREPARE" => CodigoTransaccion::PREPARE { monto: parseado[3].parse::<f64>()? },
+            "PREPARE" => {
+                let argumentos = parseado
+                    .get(3)
+                    .map_or_else(Vec::new, |a| a.split(' ').collect::<Vec<&str>>());
+                if argumentos.len() != 4 && argumentos.len() != 5 {
+                    return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                        "Mensaje erroneo: {}",
+                        mensaje_codificado
+                    ))));
+                }
+                CodigoTransaccion::PREPARE {
+                    monto: Monto::decodificar_decimal(
+                        argumentos[0],
+                        Moneda::decodificar(argumentos[1])?,
+                    )?,
+                    clave: argumentos[2].parse::<usize>()?,
+                    deadline: argumentos[3].parse::<u64>()?,
+                    datos: argumentos
+                        .get(4)
+                        .map(|d| campos::decodificar(d))
+                        .transpose()?
+                        .unwrap_or_default(),
+                }
+            }
             "COMMIT" => CodigoTransaccion::COMMIT,
-            "ABORT" => CodigoTransaccion::ABORT,
+            "ABORT" => CodigoTransaccion::ABORT {
+                motivo: parseado
+                    .get(3)
+                    .map(|m| MotivoAborto::decodificar(m))
+                    .transpose()?,
+            },
             "READY" => CodigoTransaccion::READY,
-            _ => return Err(ErrorApp::Interno(ErrorInterno::new(&format!("Mensaje erroneo: {}", parseado[0])))),
+            "READ_ONLY" => CodigoTransaccion::READ_ONLY,
+            _ => {
+                return Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                    "Mensaje erroneo: {}",
+                    parseado[0]
+                ))))
+            }
         };
 
         Ok(MensajeTransaccion::new(
-            codigo, 
-            parseado[1].parse::<usize>()?, 
-            parseado[2].parse::<usize>()?
+            codigo,
+            parseado[1].parse::<usize>()?,
+            IdTransaccion::decodificar(parseado[2])?,
         ))
     }
+
+    /// Devuelve verdadero si el mensaje tiene el mismo codigo que el pasado
+    /// por parámetro, sin considerar el contenido del codigo.
+    pub fn mismo_codigo(&self, codigo: &CodigoTransaccion) -> bool {
+        std::mem::discriminant(&self.codigo) == std::mem::discriminant(codigo)
+    }
+
+    /// Convierte un lote de mensajes a los Strings de los datagramas en los
+    /// que se envía y los devuelve. Los mensajes se agregan en orden a cada
+    /// datagrama mientras no supere el tamaño máximo de un datagrama UDP; un
+    /// mensaje que lo supera por sí solo queda en un datagrama propio.
+    pub fn codificar_lote(mensajes: &[MensajeTransaccion]) -> Vec<String> {
+        let mut datagramas: Vec<String> = Vec::new();
+        for codificado in mensajes.iter().map(|m| m.codificar()) {
+            match datagramas.last_mut() {
+                Some(datagrama) if datagrama.len() + 1 + codificado.len() <= TAM_BUFFER => {
+                    datagrama.push(SEPARADOR_LOTE);
+                    datagrama.push_str(&codificado);
+                }
+                _ => datagramas.push(format!("{} {}", PREFIJO_LOTE, codificado)),
+            }
+        }
+        datagramas
+    }
+
+    /// Convierte el String a un lote de mensajes y lo devuelve. Si el String
+    /// corresponde a un único mensaje devuelve un lote de un elemento.
+    /// Devuelve error si alguno de los mensajes no matchea con algún código.
+    pub fn decodificar_lote(mensaje_codificado: &str) -> Resultado<Vec<MensajeTransaccion>> {
+        match mensaje_codificado.strip_prefix(&format!("{} ", PREFIJO_LOTE)) {
+            Some(lote) => lote
+                .split(SEPARADOR_LOTE)
+                .map(MensajeTransaccion::decodificar)
+                .collect(),
+            None => Ok(vec![MensajeTransaccion::decodificar(mensaje_codificado)?]),
+        }
+    }
 }
 
 impl PartialEq for MensajeTransaccion {
     /// Devuelve verdadero si el codigo y el id_op coinciden
     fn eq(&self, otro: &Self) -> bool {
-        self.codigo == otro.codigo && self.id_op == self.id_op
+        self.codigo == otro.codigo && self.id_op == otro.id_op
     }
 }
 
@@ -98,23 +239,54 @@ impl ProtocoloTransaccion {
         Ok(())
     }
 
+    /// Recibe un lote de mensajes y una direccion. Codifica los mensajes y los
+    /// envía a dicha dirección en tantos datagramas como hagan falta para no
+    /// superar el tamaño máximo de un datagrama UDP.
+    pub fn enviar_lote(
+        &mut self,
+        mensajes: &[MensajeTransaccion],
+        direccion: String,
+    ) -> Resultado<()> {
+        for datagrama in MensajeTransaccion::codificar_lote(mensajes) {
+            self.skt.send_to(datagrama.as_bytes(), &direccion)?;
+        }
+        Ok(())
+    }
+
     /// Recibe un timeout. Si el timeout en None, se bloquea hasta recibir un
     /// mensaje. Sino, devuelve error si hay ocurre timeout.
     pub fn recibir(&mut self, timeout: Option<Duration>) -> Resultado<MensajeTransaccion> {
+        MensajeTransaccion::decodificar(&self.recibir_datagrama(timeout)?)
+    }
+
+    /// Recibe un timeout. Si el timeout en None, se bloquea hasta recibir un
+    /// datagrama. Sino, devuelve error si hay ocurre timeout. Devuelve todos
+    /// los mensajes contenidos en el datagrama recibido.
+    pub fn recibir_lote(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Resultado<Vec<MensajeTransaccion>> {
+        MensajeTransaccion::decodificar_lote(&self.recibir_datagrama(timeout)?)
+    }
+
+    /// Recibe un datagrama y lo devuelve como String.
+    fn recibir_datagrama(&mut self, timeout: Option<Duration>) -> Resultado<String> {
         let mut buffer = vec![0; TAM_BUFFER];
         if self.skt.set_read_timeout(timeout).is_err() {
-            return Err(ErrorApp::Interno(ErrorInterno::new("Error al setear timeout")));
+            return Err(ErrorApp::Interno(ErrorInterno::new(
+                "Error al setear timeout",
+            )));
         };
         let (recibido, _src) = self.skt.recv_from(&mut buffer)?;
 
         if recibido == 0 {
             return Err(ErrorApp::Interno(ErrorInterno::new("Timeout en recepcion")));
         }
-        MensajeTransaccion::decodificar(&String::from_utf8(buffer[..recibido].to_vec())?)
+        Ok(String::from_utf8(buffer[..recibido].to_vec())?)
     }
 
     /// Devuelve una copia de ProtocoloTransaccion
-    pub fn clone(&self) -> Resultado<Self> {
+    pub fn try_clone(&self) -> Resultado<Self> {
         Ok(ProtocoloTransaccion {
             skt: self.skt.try_clone()?,
         })
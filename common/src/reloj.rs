@@ -0,0 +1,15 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Reloj implementa la obtención de la hora del sistema en un formato que
+/// puede compartirse entre procesos.
+pub struct Reloj {}
+
+impl Reloj {
+    /// Devuelve la cantidad de milisegundos transcurridos desde UNIX_EPOCH.
+    pub fn ahora_ms() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
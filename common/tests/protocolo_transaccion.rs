@@ -0,0 +1,89 @@
+use common::id_transaccion::IdTransaccion;
+use common::monto::{Moneda, Monto};
+use common::protocolo_transaccion::{
+    CodigoTransaccion, MensajeTransaccion, ProtocoloTransaccion, TAM_BUFFER,
+};
+use std::collections::BTreeMap;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+fn prepare(secuencia: u32) -> MensajeTransaccion {
+    let mut datos = BTreeMap::new();
+    datos.insert("reserva".to_string(), "x".repeat(200));
+    MensajeTransaccion::new(
+        CodigoTransaccion::PREPARE {
+            monto: Monto::new(1000, Moneda::POR_DEFECTO),
+            clave: secuencia as usize,
+            deadline: u64::MAX,
+            datos,
+        },
+        0,
+        IdTransaccion::new(1, secuencia),
+    )
+}
+
+/// Devuelve mensajes que, codificados en un único datagrama, ocupan más que
+/// el tamaño máximo de un datagrama UDP.
+fn lote_excedido() -> Vec<MensajeTransaccion> {
+    let mut mensajes = Vec::new();
+    let mut largo = 0;
+    while largo <= TAM_BUFFER {
+        let mensaje = prepare(mensajes.len() as u32 + 1);
+        largo += mensaje.codificar().len() + 1;
+        mensajes.push(mensaje);
+    }
+    mensajes
+}
+
+#[test]
+fn lote_que_entra_en_un_datagrama_no_se_divide() {
+    let mut mensajes = lote_excedido();
+    let ultimo = mensajes.pop().unwrap();
+    let datagramas = MensajeTransaccion::codificar_lote(&mensajes);
+    assert_eq!(datagramas.len(), 1);
+    assert!(datagramas[0].len() <= TAM_BUFFER);
+    assert!(datagramas[0].len() + ultimo.codificar().len() + 1 > TAM_BUFFER);
+}
+
+#[test]
+fn lote_que_excede_un_datagrama_se_divide_sin_perder_mensajes() {
+    let mensajes = lote_excedido();
+    let datagramas = MensajeTransaccion::codificar_lote(&mensajes);
+    assert_eq!(datagramas.len(), 2);
+    assert!(datagramas.iter().all(|d| d.len() <= TAM_BUFFER));
+
+    let decodificados = datagramas
+        .iter()
+        .flat_map(|d| MensajeTransaccion::decodificar_lote(d).unwrap())
+        .collect::<Vec<MensajeTransaccion>>();
+    assert!(decodificados == mensajes);
+}
+
+#[test]
+fn enviar_lote_excedido_entrega_todos_los_mensajes() {
+    let receptor = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receptor
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut emisor = ProtocoloTransaccion::new("127.0.0.1:0".to_string()).unwrap();
+    let mensajes = lote_excedido();
+    emisor
+        .enviar_lote(&mensajes, receptor.local_addr().unwrap().to_string())
+        .unwrap();
+
+    let mut buffer = vec![0; TAM_BUFFER];
+    let mut recibidos = Vec::new();
+    while recibidos.len() < mensajes.len() {
+        let (largo, _) = receptor.recv_from(&mut buffer).unwrap();
+        let datagrama = String::from_utf8(buffer[..largo].to_vec()).unwrap();
+        recibidos.extend(MensajeTransaccion::decodificar_lote(&datagrama).unwrap());
+    }
+    assert!(recibidos == mensajes);
+}
+
+#[test]
+fn mensaje_incompleto_devuelve_error() {
+    for mensaje in &["", "COMMIT", "COMMIT 0", "PREPARE 0 1.1", "LOTE READY 0"] {
+        assert!(MensajeTransaccion::decodificar_lote(mensaje).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+mod model;
+use alglobo::model::almacenamiento::TipoAlmacenamiento;
+use alglobo::model::almacenamiento_archivo::AlmacenamientoArchivo;
+use alglobo::model::config::Config;
+use alglobo::model::log::{Log, Transaccion};
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::id_transaccion::IdTransaccion;
+use common::monto::Monto;
+use model::argumentos::{Argumentos, Comando};
+use model::cronologia;
+use model::estadisticas::Estadisticas;
+use model::exportacion;
+use model::resumen::{self, ResumenTransaccion};
+use model::validacion;
+use std::collections::HashSet;
+
+/// Ejecuta el comando indicado sobre el log del nodo de alglobo. Devuelve
+/// falso si la validación o la verificación encontraron inconsistencias.
+fn procesar(argumentos: Argumentos) -> Resultado<bool> {
+    // Las rutas de los archivos del log son relativas al directorio del nodo
+    std::env::set_current_dir(&argumentos.directorio)?;
+    let config = Config::get_config(Config::ruta_config())?;
+
+    if let Comando::Reparar = argumentos.comando {
+        let log = Log::reparar(&config)?;
+        println!(
+            "[Inspector]: Log recuperado, {} transacciones pendientes",
+            log.transacciones_pendientes()?.len()
+        );
+        return Ok(true);
+    }
+
+    if let Comando::Verificar = argumentos.comando {
+        // Solo el almacenamiento en archivo encadena los registros
+        if config.almacenamiento != TipoAlmacenamiento::Archivo {
+            return Err(ErrorApp::Interno(ErrorInterno::new(
+                "La cadena de hashes solo existe con el almacenamiento en archivo",
+            )));
+        }
+        let verificacion = AlmacenamientoArchivo::verificar_cadena(&config)?;
+        println!("{}", verificacion);
+        return Ok(verificacion.valida());
+    }
+
+    let historial = Log::abrir_lectura(&config)?.historial()?;
+    let resumenes = resumen::agrupar(&historial);
+    let filtrados = resumenes
+        .iter()
+        .filter(|r| argumentos.filtro.acepta(r))
+        .collect::<Vec<&ResumenTransaccion>>();
+
+    match argumentos.comando {
+        Comando::Listar => listar(&filtrados),
+        Comando::Cronologia => {
+            let ids = filtrados
+                .iter()
+                .map(|r| r.transaccion.id)
+                .collect::<HashSet<IdTransaccion>>();
+            let registros = historial
+                .iter()
+                .filter(|t| ids.contains(&t.id))
+                .collect::<Vec<&Transaccion>>();
+            for linea in cronologia::formatear(&registros) {
+                println!("{}", linea);
+            }
+            println!("[Inspector]: {} registros", registros.len());
+        }
+        Comando::Estadisticas => {
+            println!("{}", Estadisticas::calcular(historial.len(), &resumenes))
+        }
+        Comando::Validar => {
+            // SQLite solo conserva el último estado de cada transacción
+            let completo = config.almacenamiento != TipoAlmacenamiento::Sqlite;
+            let inconsistencias = validacion::validar(&historial, &resumenes, completo);
+            for inconsistencia in &inconsistencias {
+                println!("[Inspector]: {}", inconsistencia);
+            }
+            println!(
+                "[Inspector]: {} registros validados, {} inconsistencias",
+                historial.len(),
+                inconsistencias.len()
+            );
+            return Ok(inconsistencias.is_empty());
+        }
+        Comando::Exportar { formato, salida } => {
+            let exportado = exportacion::exportar(&filtrados, &formato)?;
+            match salida {
+                Some(ruta) => {
+                    std::fs::write(&ruta, exportado + "\n")?;
+                    println!(
+                        "[Inspector]: {} transacciones exportadas a {}",
+                        filtrados.len(),
+                        ruta
+                    );
+                }
+                None => println!("{}", exportado),
+            }
+        }
+        Comando::Reparar | Comando::Verificar => {}
+    }
+
+    Ok(true)
+}
+
+/// Muestra el último estado de cada transacción.
+fn listar(resumenes: &[&ResumenTransaccion]) {
+    println!(
+        "{:>8} {:>8} {:>8} {:>9} {:>9} {:>13} {:>14} {:>14}",
+        "ID", "PAGO", "PROXIMO", "ESTADO", "RESULTADO", "PARTICIPANTES", "AEROLINEA", "HOTEL"
+    );
+    for resumen in resumenes {
+        let t = &resumen.transaccion;
+        let monto = |m: Option<Monto>| m.map(|m| m.to_string()).unwrap_or_default();
+        println!(
+            "{:>8} {:>8} {:>8} {:>9} {:>9} {:>13} {:>14} {:>14}",
+            t.id,
+            t.id_pago,
+            t.id_pago_prox,
+            t.estado().codificar(),
+            resumen.resultado().map_or("", |e| e.codificar()),
+            t.participantes
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join(";"),
+            monto(t.pago.as_ref().map(|p| p.get_monto_aerolinea())),
+            monto(t.pago.as_ref().map(|p| p.get_monto_hotel()))
+        );
+    }
+    println!("[Inspector]: {} transacciones", resumenes.len());
+}
+
+fn main() {
+    let argumentos = std::env::args().skip(1).collect::<Vec<String>>();
+    let argumentos = match Argumentos::parsear(&argumentos) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    match procesar(argumentos) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(2);
+        }
+    }
+}
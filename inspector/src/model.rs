@@ -0,0 +1,7 @@
+pub mod argumentos;
+pub mod cronologia;
+pub mod estadisticas;
+pub mod exportacion;
+pub mod filtro;
+pub mod resumen;
+pub mod validacion;
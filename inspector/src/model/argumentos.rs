@@ -0,0 +1,100 @@
+use alglobo::model::estado_transaccion::EstadoTransaccion;
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use common::id_transaccion::IdTransaccion;
+
+use super::exportacion::FormatoExportacion;
+use super::filtro::Filtro;
+
+/// Uso de la línea de comandos del inspector.
+pub const USO: &str = "Uso: inspector [--dir <ruta>] <comando> [opciones]
+Comandos:
+  listar                 Muestra el último estado de cada transaccion
+  cronologia             Muestra los registros en orden con su hora, nodo y epoca
+  estadisticas           Muestra la cantidad de transacciones por resultado
+  validar                Busca inconsistencias en el historial del log
+  verificar              Verifica la cadena de hashes y los checkpoints firmados
+  exportar <json|csv>    Exporta las transacciones
+  reparar                Recupera el log, truncando los registros incompletos
+Opciones:
+  --dir <ruta>           Directorio del nodo de alglobo (por defecto el actual)
+  --estado <estado>      PREPARE, COMMIT, ABORT o FINALIZE
+  --pago <id>            Id de pago
+  --desde <id>           Id de transaccion minimo, como epoca.secuencia
+  --hasta <id>           Id de transaccion maximo, como epoca.secuencia
+  --salida <ruta>        Archivo de salida de la exportacion";
+
+/// Comando representa las operaciones del inspector.
+pub enum Comando {
+    Listar,
+    Cronologia,
+    Estadisticas,
+    Validar,
+    Verificar,
+    Exportar {
+        formato: FormatoExportacion,
+        salida: Option<String>,
+    },
+    Reparar,
+}
+
+/// Argumentos contiene el comando a ejecutar, el directorio del nodo de
+/// alglobo cuyo log se inspecciona y el filtro de transacciones.
+pub struct Argumentos {
+    pub directorio: String,
+    pub comando: Comando,
+    pub filtro: Filtro,
+}
+
+impl Argumentos {
+    /// Parsea los argumentos de la línea de comandos, sin el nombre del
+    /// programa. Devuelve error si algún argumento es inválido.
+    pub fn parsear(argumentos: &[String]) -> Resultado<Argumentos> {
+        let mut directorio = ".".to_string();
+        let mut filtro = Filtro::default();
+        let mut salida = None;
+        let mut posicionales = Vec::new();
+
+        let mut iter = argumentos.iter();
+        while let Some(argumento) = iter.next() {
+            let mut valor = || {
+                iter.next().cloned().ok_or_else(|| {
+                    ErrorApp::Interno(ErrorInterno::new(&format!(
+                        "Falta el valor de {}",
+                        argumento
+                    )))
+                })
+            };
+            match argumento.as_str() {
+                "--dir" => directorio = valor()?,
+                "--estado" => {
+                    filtro.estado = Some(EstadoTransaccion::decodificar(&valor()?.to_uppercase())?)
+                }
+                "--pago" => filtro.id_pago = Some(valor()?.parse::<usize>()?),
+                "--desde" => filtro.desde = Some(IdTransaccion::decodificar(&valor()?)?),
+                "--hasta" => filtro.hasta = Some(IdTransaccion::decodificar(&valor()?)?),
+                "--salida" => salida = Some(valor()?),
+                _ => posicionales.push(argumento.as_str()),
+            }
+        }
+
+        let comando = match posicionales.as_slice() {
+            ["listar"] => Comando::Listar,
+            ["cronologia"] => Comando::Cronologia,
+            ["estadisticas"] => Comando::Estadisticas,
+            ["validar"] => Comando::Validar,
+            ["verificar"] => Comando::Verificar,
+            ["exportar", formato] => Comando::Exportar {
+                formato: FormatoExportacion::decodificar(formato)?,
+                salida,
+            },
+            ["reparar"] => Comando::Reparar,
+            _ => return Err(ErrorApp::Interno(ErrorInterno::new(USO))),
+        };
+
+        Ok(Argumentos {
+            directorio,
+            comando,
+            filtro,
+        })
+    }
+}
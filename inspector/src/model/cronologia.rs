@@ -0,0 +1,82 @@
+use alglobo::model::log::{Sello, Transaccion};
+
+/// Milisegundos en un día.
+const MS_POR_DIA: u64 = 24 * 60 * 60 * 1000;
+
+/// Convierte milisegundos desde UNIX_EPOCH a una fecha y hora UTC con el
+/// formato `AAAA-MM-DD HH:MM:SS.mmm` y la devuelve.
+pub fn formatear_hora(ms: u64) -> String {
+    // Conversión de días desde UNIX_EPOCH a fecha del calendario gregoriano
+    let dias = (ms / MS_POR_DIA) as i64 + 719468;
+    let era = dias.div_euclid(146097);
+    let dia_era = dias.rem_euclid(146097);
+    let anio_era = (dia_era - dia_era / 1460 + dia_era / 36524 - dia_era / 146096) / 365;
+    let dia_anio = dia_era - (365 * anio_era + anio_era / 4 - anio_era / 100);
+    let mes_marzo = (5 * dia_anio + 2) / 153;
+    let dia = dia_anio - (153 * mes_marzo + 2) / 5 + 1;
+    let mes = if mes_marzo < 10 {
+        mes_marzo + 3
+    } else {
+        mes_marzo - 9
+    };
+    let anio = anio_era + era * 400 + if mes <= 2 { 1 } else { 0 };
+
+    let ms_dia = ms % MS_POR_DIA;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        anio,
+        mes,
+        dia,
+        ms_dia / 3_600_000,
+        ms_dia / 60_000 % 60,
+        ms_dia / 1000 % 60,
+        ms_dia % 1000
+    )
+}
+
+/// Devuelve las líneas de la cronología de los registros dados, en el orden
+/// en que se escribieron. Antes del primer registro de cada época de
+/// liderazgo se agrega una línea que indica la época y el nodo líder. Los
+/// registros migrados del log de texto anterior, sin sello, se muestran sin
+/// hora.
+pub fn formatear(registros: &[&Transaccion]) -> Vec<String> {
+    let mut lineas = vec![format!(
+        "{:>23} {:>10} {:>8} {:>8} {:>9} {:>5} {:>6}",
+        "ESCRITURA", "DESDE_PREP", "ID", "PAGO", "ESTADO", "NODO", "EPOCA"
+    )];
+    let mut epoca_actual: Option<Option<u32>> = None;
+
+    for registro in registros {
+        let epoca = registro.sello.map(|s| s.epoca);
+        if epoca_actual != Some(epoca) {
+            lineas.push(match registro.sello {
+                Some(Sello { epoca, nodo, .. }) => {
+                    format!("--- Epoca {}, lider {} ---", epoca, nodo)
+                }
+                None => "--- Registros sin sello ---".to_string(),
+            });
+            epoca_actual = Some(epoca);
+        }
+        let (escritura, desde_prepare, nodo, epoca) = match registro.sello {
+            Some(s) => (
+                formatear_hora(s.escritura),
+                format!("{} ms", s.desde_prepare),
+                s.nodo.to_string(),
+                s.epoca.to_string(),
+            ),
+            None => Default::default(),
+        };
+        lineas.push(format!(
+            "{:>23} {:>10} {:>8} {:>8} {:>9} {:>5} {:>6}",
+            escritura,
+            desde_prepare,
+            registro.id,
+            registro.id_pago,
+            registro.estado().codificar(),
+            nodo,
+            epoca
+        ));
+    }
+
+    lineas
+}
@@ -0,0 +1,51 @@
+use alglobo::model::estado_transaccion::EstadoTransaccion;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::resumen::ResumenTransaccion;
+
+/// Estadisticas resume el estado de las transacciones del log.
+pub struct Estadisticas {
+    pub registros: usize,
+    pub transacciones: usize,
+    pub pagos: usize,
+    pub commiteadas: usize,
+    pub abortadas: usize,
+    pub en_prepare: usize,
+    pub sin_finalizar: usize,
+}
+
+impl Estadisticas {
+    /// Calcula las estadísticas de las transacciones dadas, que agrupan la
+    /// cantidad de registros indicada.
+    pub fn calcular(registros: usize, resumenes: &[ResumenTransaccion]) -> Self {
+        let contar =
+            |f: &dyn Fn(&ResumenTransaccion) -> bool| resumenes.iter().filter(|r| f(r)).count();
+
+        Estadisticas {
+            registros,
+            transacciones: resumenes.len(),
+            pagos: resumenes
+                .iter()
+                .map(|r| r.transaccion.id_pago)
+                .collect::<HashSet<usize>>()
+                .len(),
+            commiteadas: contar(&|r| r.resultado() == Some(EstadoTransaccion::Commit)),
+            abortadas: contar(&|r| r.resultado() == Some(EstadoTransaccion::Abort)),
+            en_prepare: contar(&|r| r.en_prepare()),
+            sin_finalizar: contar(&|r| r.resultado().is_some() && !r.finalizada()),
+        }
+    }
+}
+
+impl Display for Estadisticas {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        writeln!(f, "Registros:                {}", self.registros)?;
+        writeln!(f, "Transacciones:            {}", self.transacciones)?;
+        writeln!(f, "Pagos distintos:          {}", self.pagos)?;
+        writeln!(f, "Commiteadas:              {}", self.commiteadas)?;
+        writeln!(f, "Abortadas:                {}", self.abortadas)?;
+        writeln!(f, "Trabadas en prepare:      {}", self.en_prepare)?;
+        write!(f, "Decididas sin finalizar:  {}", self.sin_finalizar)
+    }
+}
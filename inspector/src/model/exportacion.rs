@@ -0,0 +1,148 @@
+use common::campos;
+use common::error::{ErrorApp, ErrorInterno, Resultado};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::cronologia;
+
+use super::resumen::ResumenTransaccion;
+
+/// FormatoExportacion representa los formatos en los que se pueden exportar
+/// las transacciones del log.
+pub enum FormatoExportacion {
+    Json,
+    Csv,
+}
+
+impl FormatoExportacion {
+    /// Convierte el nombre del formato a FormatoExportacion y lo devuelve.
+    /// Devuelve error si el nombre no matchea con ningún formato.
+    pub fn decodificar(nombre: &str) -> Resultado<FormatoExportacion> {
+        match nombre {
+            "json" => Ok(FormatoExportacion::Json),
+            "csv" => Ok(FormatoExportacion::Csv),
+            _ => Err(ErrorApp::Interno(ErrorInterno::new(&format!(
+                "Formato de exportacion desconocido: {}",
+                nombre
+            )))),
+        }
+    }
+}
+
+/// Representa una transacción exportada, con su último estado, su resultado
+/// y el sello del último registro, si lo tiene. Los montos se exportan como
+/// decimales exactos seguidos de su moneda, junto a la versión de la tabla de
+/// cotizaciones con la que se convirtieron. Los datos de la reserva del pago
+/// se exportan por nombre, y codificados en el csv. El archivo de la bandeja
+/// de entrada del pago se exporta escapado en el csv, seguido del lote.
+#[derive(Serialize)]
+struct TransaccionExportada {
+    id: String,
+    id_pago: usize,
+    id_pago_prox: usize,
+    estado: &'static str,
+    resultado: Option<&'static str>,
+    participantes: Vec<usize>,
+    creacion: u64,
+    deadline: u64,
+    monto_aerolinea: Option<String>,
+    monto_hotel: Option<String>,
+    version_cotizaciones: Option<u32>,
+    datos: Option<BTreeMap<String, String>>,
+    archivo: Option<String>,
+    lote: u32,
+    escritura: Option<u64>,
+    desde_prepare_ms: Option<u64>,
+    nodo: Option<usize>,
+    epoca: Option<u32>,
+}
+
+impl TransaccionExportada {
+    fn new(resumen: &ResumenTransaccion) -> Self {
+        let t = &resumen.transaccion;
+        TransaccionExportada {
+            id: t.id.to_string(),
+            id_pago: t.id_pago,
+            id_pago_prox: t.id_pago_prox,
+            estado: t.estado().codificar(),
+            resultado: resumen.resultado().map(|e| e.codificar()),
+            participantes: t.participantes.clone(),
+            creacion: t.creacion,
+            deadline: t.deadline,
+            monto_aerolinea: t.pago.as_ref().map(|p| p.get_monto_aerolinea().to_string()),
+            monto_hotel: t.pago.as_ref().map(|p| p.get_monto_hotel().to_string()),
+            version_cotizaciones: t.version_cotizaciones,
+            datos: t.pago.as_ref().map(|p| p.get_datos()),
+            archivo: t.archivo.clone(),
+            lote: t.lote,
+            escritura: t.sello.map(|s| s.escritura),
+            desde_prepare_ms: t.sello.map(|s| s.desde_prepare),
+            nodo: t.sello.map(|s| s.nodo),
+            epoca: t.sello.map(|s| s.epoca),
+        }
+    }
+
+    /// Devuelve la transacción como una línea csv, con las columnas de
+    /// `ENCABEZADO_CSV`. La escritura se exporta como fecha y hora UTC.
+    fn formatear_csv(&self) -> String {
+        let entero = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or_default();
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.id,
+            self.id_pago,
+            self.id_pago_prox,
+            self.estado,
+            self.resultado.unwrap_or_default(),
+            self.participantes
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join(";"),
+            self.creacion,
+            self.deadline,
+            self.monto_aerolinea.as_deref().unwrap_or_default(),
+            self.monto_hotel.as_deref().unwrap_or_default(),
+            entero(self.version_cotizaciones.map(|v| v as u64)),
+            self.datos
+                .as_ref()
+                .map(campos::codificar)
+                .unwrap_or_default(),
+            self.archivo
+                .as_deref()
+                .map(campos::escapar)
+                .unwrap_or_default(),
+            self.lote,
+            self.escritura
+                .map(cronologia::formatear_hora)
+                .unwrap_or_default(),
+            entero(self.desde_prepare_ms),
+            entero(self.nodo.map(|n| n as u64)),
+            entero(self.epoca.map(|e| e as u64))
+        )
+    }
+}
+
+/// Columnas del csv exportado.
+const ENCABEZADO_CSV: &str = "id,id_pago,id_pago_prox,estado,resultado,participantes,\
+creacion,deadline,monto_aerolinea,monto_hotel,version_cotizaciones,datos,archivo,lote,escritura,desde_prepare_ms,nodo,epoca";
+
+/// Exporta las transacciones en el formato dado y devuelve el resultado.
+pub fn exportar(
+    resumenes: &[&ResumenTransaccion],
+    formato: &FormatoExportacion,
+) -> Resultado<String> {
+    let transacciones = resumenes
+        .iter()
+        .map(|r| TransaccionExportada::new(r))
+        .collect::<Vec<TransaccionExportada>>();
+
+    match formato {
+        FormatoExportacion::Json => serde_json::to_string_pretty(&transacciones)
+            .map_err(|e| ErrorApp::Interno(ErrorInterno::new(&e.to_string()))),
+        FormatoExportacion::Csv => {
+            let mut lineas = vec![ENCABEZADO_CSV.to_string()];
+            lineas.extend(transacciones.iter().map(|t| t.formatear_csv()));
+            Ok(lineas.join("\n"))
+        }
+    }
+}
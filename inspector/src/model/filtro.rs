@@ -0,0 +1,35 @@
+use alglobo::model::estado_transaccion::EstadoTransaccion;
+use common::id_transaccion::IdTransaccion;
+
+use super::resumen::ResumenTransaccion;
+
+/// Filtro indica qué transacciones del log se muestran o exportan. Los
+/// criterios no indicados aceptan cualquier transacción.
+/// El estado se compara contra el resultado de la transacción: PREPARE
+/// selecciona las que quedaron sin decidir, COMMIT y ABORT las que tuvieron
+/// ese resultado, y FINALIZE las finalizadas.
+#[derive(Default)]
+pub struct Filtro {
+    pub estado: Option<EstadoTransaccion>,
+    pub id_pago: Option<usize>,
+    pub desde: Option<IdTransaccion>,
+    pub hasta: Option<IdTransaccion>,
+}
+
+impl Filtro {
+    /// Devuelve verdadero si la transacción cumple todos los criterios.
+    pub fn acepta(&self, resumen: &ResumenTransaccion) -> bool {
+        let transaccion = &resumen.transaccion;
+        let estado = match &self.estado {
+            None => true,
+            Some(EstadoTransaccion::Prepare) => resumen.en_prepare(),
+            Some(EstadoTransaccion::Finalize) => resumen.finalizada(),
+            Some(e) => resumen.resultado().as_ref() == Some(e),
+        };
+
+        estado
+            && self.id_pago.is_none_or(|id| transaccion.id_pago == id)
+            && self.desde.is_none_or(|id| transaccion.id >= id)
+            && self.hasta.is_none_or(|id| transaccion.id <= id)
+    }
+}
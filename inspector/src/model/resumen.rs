@@ -0,0 +1,64 @@
+use alglobo::model::estado_transaccion::EstadoTransaccion;
+use alglobo::model::log::Transaccion;
+use common::id_transaccion::IdTransaccion;
+use std::collections::BTreeMap;
+
+/// ResumenTransaccion agrupa los registros del log de una misma transacción.
+/// Contiene el último registro, con el pago del prepare si se conoce, y la
+/// secuencia de estados registrados.
+pub struct ResumenTransaccion {
+    pub transaccion: Transaccion,
+    pub estados: Vec<EstadoTransaccion>,
+}
+
+impl ResumenTransaccion {
+    /// Devuelve el resultado de la transacción, Commit o Abort, si ya fue
+    /// decidido.
+    pub fn resultado(&self) -> Option<EstadoTransaccion> {
+        self.estados
+            .iter()
+            .rev()
+            .find(|e| **e == EstadoTransaccion::Commit || **e == EstadoTransaccion::Abort)
+            .cloned()
+    }
+
+    /// Devuelve verdadero si la transacción fue finalizada.
+    pub fn finalizada(&self) -> bool {
+        self.transaccion.estado() == EstadoTransaccion::Finalize
+    }
+
+    /// Devuelve verdadero si la transacción quedó en prepare sin decidirse.
+    pub fn en_prepare(&self) -> bool {
+        self.transaccion.estado() == EstadoTransaccion::Prepare
+    }
+}
+
+/// Agrupa los registros del historial por transacción y devuelve los
+/// resúmenes ordenados por id de transacción.
+pub fn agrupar(historial: &[Transaccion]) -> Vec<ResumenTransaccion> {
+    let mut resumenes: BTreeMap<IdTransaccion, ResumenTransaccion> = BTreeMap::new();
+
+    for registro in historial {
+        match resumenes.get_mut(&registro.id) {
+            Some(resumen) => {
+                let pago = resumen.transaccion.pago.take();
+                resumen.transaccion = registro.clone();
+                if resumen.transaccion.pago.is_none() {
+                    resumen.transaccion.pago = pago;
+                }
+                resumen.estados.push(registro.estado());
+            }
+            None => {
+                resumenes.insert(
+                    registro.id,
+                    ResumenTransaccion {
+                        transaccion: registro.clone(),
+                        estados: vec![registro.estado()],
+                    },
+                );
+            }
+        }
+    }
+
+    resumenes.into_values().collect()
+}
@@ -0,0 +1,91 @@
+use alglobo::model::estado_transaccion::EstadoTransaccion;
+use alglobo::model::log::Transaccion;
+use common::id_transaccion::IdTransaccion;
+use std::collections::HashMap;
+
+use super::resumen::ResumenTransaccion;
+
+/// Valida la consistencia del historial del log y devuelve las
+/// inconsistencias encontradas. Se verifica que:
+/// - los estados de cada transacción sigan transiciones válidas, si el
+///   historial incluye todos los registros,
+/// - todos los registros de una transacción correspondan al mismo pago,
+/// - no haya huecos entre los ids de transacción de una misma época, que
+///   comienzan en la secuencia 1,
+/// - ningún pago se haya commiteado en más de una transacción.
+pub fn validar(
+    historial: &[Transaccion],
+    resumenes: &[ResumenTransaccion],
+    historial_completo: bool,
+) -> Vec<String> {
+    let mut inconsistencias = Vec::new();
+    let mut ultimos: HashMap<IdTransaccion, &Transaccion> = HashMap::new();
+
+    for registro in historial {
+        let anterior = ultimos.get(&registro.id);
+        if historial_completo
+            && !EstadoTransaccion::transicion_valida(
+                anterior.map(|t| t.estado()),
+                registro.estado(),
+            )
+        {
+            inconsistencias.push(format!(
+                "Transaccion {}: transicion invalida de {} a {}",
+                registro.id,
+                anterior.map_or("INICIO", |t| t.estado().codificar()),
+                registro.estado().codificar()
+            ));
+        }
+        if let Some(anterior) = anterior.filter(|t| t.id_pago != registro.id_pago) {
+            inconsistencias.push(format!(
+                "Transaccion {}: registrada con los pagos {} y {}",
+                registro.id, anterior.id_pago, registro.id_pago
+            ));
+        }
+        ultimos.insert(registro.id, registro);
+    }
+
+    let mut anterior: Option<IdTransaccion> = None;
+    for resumen in resumenes {
+        let id = resumen.transaccion.id;
+        let esperada = match anterior {
+            Some(a) if a.epoca == id.epoca => a.secuencia + 1,
+            _ => 1,
+        };
+        if id.secuencia > esperada {
+            inconsistencias.push(format!(
+                "Faltan las transacciones {} a {}",
+                IdTransaccion::new(id.epoca, esperada),
+                IdTransaccion::new(id.epoca, id.secuencia - 1)
+            ));
+        }
+        anterior = Some(id);
+    }
+
+    let mut commits: HashMap<usize, Vec<IdTransaccion>> = HashMap::new();
+    for resumen in resumenes {
+        if resumen.resultado() == Some(EstadoTransaccion::Commit) {
+            commits
+                .entry(resumen.transaccion.id_pago)
+                .or_default()
+                .push(resumen.transaccion.id);
+        }
+    }
+    let mut duplicados = commits
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .collect::<Vec<(usize, Vec<IdTransaccion>)>>();
+    duplicados.sort_unstable();
+    for (id_pago, ids) in duplicados {
+        inconsistencias.push(format!(
+            "Pago {}: commiteado por las transacciones {}",
+            id_pago,
+            ids.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+    }
+
+    inconsistencias
+}
@@ -9,7 +9,13 @@ fi
 if [ $2 = "-r" ]
   then
     rm alglobo/files/fallidos.csv
-    rm alglobo/files/estado.log
+    rm -f alglobo/files/estado.log
+    rm -rf alglobo/files/estado
+    rm -f alglobo/files/estado.snapshot
+    rm -f alglobo/files/estado.checkpoints
+    rm -f alglobo/files/estado.epoca
+    rm -rf alglobo/files/archivo
+    rm -f alglobo/files/estado.db*
 fi
 
 chmod +x 1-alglobo.sh
@@ -4,11 +4,13 @@ use std::io::BufReader;
 use std::path::Path;
 
 /// Clase utilizada para la configuracion de variables de entorno.
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Envs {
     pub trabajo_min: u64,
     pub trabajo_max: u64,
     pub probabilidad_fallo: f32,
+    #[serde(default)]
+    pub solo_lectura: Vec<usize>,
 }
 
 impl Envs {
@@ -18,6 +20,7 @@ impl Envs {
             trabajo_min: 1000,
             trabajo_max: 3000,
             probabilidad_fallo: 0.2,
+            solo_lectura: Vec::new(),
         }
     }
 
@@ -1,32 +1,53 @@
 use super::env::Envs;
 use common::dns::DNS;
 use common::error::Resultado;
+use common::id_transaccion::IdTransaccion;
+use common::monto::Monto;
+use common::motivo_aborto::{CausaAborto, MotivoAborto};
 use common::protocolo_transaccion::{CodigoTransaccion, MensajeTransaccion, ProtocoloTransaccion};
+use common::reloj::Reloj;
 use rand::Rng;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::thread;
 use std::time::Duration;
 
+/// Id del webservice de la aerolinea
+const AEROLINEA: usize = 0;
+/// Id del webservice del hotel
+const HOTEL: usize = 1;
+/// Id del webservice del banco
+const BANCO: usize = 2;
+
 /// EstadoServicio representa el estado de la transacción de cierto id.
 /// # Variantes
 /// Ready: simboliza el estado ready luego de obtener los recursos exitosamente.
 /// Commit: simboliza el estado commit luego de recibir un mensaje de commit.
 /// Abort: simboliza el estado abort luego de recibir un mensaje de abort o de
 /// haber fallado al obtener los recursos.
+/// SoloLectura: simboliza que se aceptó la transacción sin tomar recursos, por
+/// lo que no hay nada para commitear ni abortar.
+#[derive(Clone, Copy)]
 enum EstadoServicio {
     Ready,
     Commit,
     Abort,
+    SoloLectura,
 }
 
 /// WebService implementa el flujo principal del WebService. Realiza la
 /// comunicación con el nodo lider de alglobo y simula trabajo y el éxito o
-/// fracaso al intentar obtener los recursos en un prepare.
+/// fracaso al intentar obtener los recursos en un prepare. El estado de cada
+/// transacción se guarda por su id, compuesto por la época del líder que la
+/// creó, por lo que las transacciones de distintos líderes no se confunden.
 pub struct WebService {
     id: usize,
     protocolo: ProtocoloTransaccion,
-    log: HashMap<usize, EstadoServicio>,
+    log: HashMap<IdTransaccion, EstadoServicio>,
+    motivos: HashMap<IdTransaccion, MotivoAborto>,
+    claves: HashMap<usize, IdTransaccion>,
     envs: Envs,
+    respuestas: HashMap<usize, Vec<MensajeTransaccion>>,
+    trabajo_simulado: bool,
 }
 
 impl WebService {
@@ -38,31 +59,73 @@ impl WebService {
     pub fn new(id: usize) -> Resultado<Self> {
         Ok(WebService {
             log: HashMap::new(),
+            motivos: HashMap::new(),
+            claves: HashMap::new(),
             protocolo: ProtocoloTransaccion::new(DNS::direccion_webservice(&id))?,
             id,
             envs: Envs::get_envs("./files/env.json"),
+            respuestas: HashMap::new(),
+            trabajo_simulado: false,
         })
     }
 
-    /// Corre el flujo principal del programa cíclicamente.
+    /// Corre el flujo principal del programa cíclicamente. Los mensajes de
+    /// un mismo datagrama se procesan juntos y sus respuestas se envían en un
+    /// único datagrama por emisor.
     pub fn run(&mut self) {
         loop {
-            if let Ok(mensaje) = self.protocolo.recibir(None) {
-                match mensaje.codigo {
-                    CodigoTransaccion::PREPARE { monto } => self.responder_prepare(mensaje, monto),
-                    CodigoTransaccion::COMMIT => self.responder_commit(mensaje),
-                    CodigoTransaccion::ABORT => self.responder_abort(mensaje),
-                    _ => println!(
-                        "[WebService] Recibí algo que no puedo interpretar de {}",
-                        mensaje.id_emisor
-                    ),
+            if let Ok(mensajes) = self.protocolo.recibir_lote(None) {
+                self.trabajo_simulado = false;
+                for mensaje in mensajes {
+                    match mensaje.codigo {
+                        CodigoTransaccion::PREPARE {
+                            monto,
+                            clave,
+                            deadline,
+                            ref datos,
+                        } => {
+                            self.informar_reserva(&mensaje, datos);
+                            self.responder_prepare(mensaje, monto, clave, deadline)
+                        }
+                        CodigoTransaccion::COMMIT => self.responder_commit(mensaje),
+                        CodigoTransaccion::ABORT { .. } => self.responder_abort(mensaje),
+                        _ => println!(
+                            "[WebService] Recibí algo que no puedo interpretar de {}",
+                            mensaje.id_emisor
+                        ),
+                    }
                 }
+                self.enviar_respuestas();
             }
         }
     }
 
-    /// Responde un prepare segun el estado de la transaccion
-    fn responder_prepare(&mut self, mensaje: MensajeTransaccion, monto: f64) {
+    /// Informa los datos de la reserva recibidos en un prepare, si los hay.
+    fn informar_reserva(&self, mensaje: &MensajeTransaccion, datos: &BTreeMap<String, String>) {
+        if datos.is_empty() {
+            return;
+        }
+        let datos = datos
+            .iter()
+            .map(|(clave, valor)| format!("{}={}", clave, valor))
+            .collect::<Vec<String>>()
+            .join(", ");
+        println!(
+            "[WebService] Datos de la reserva de la transaccion {}: {}",
+            mensaje.id_op, datos
+        );
+    }
+
+    /// Responde un prepare segun el estado de la transaccion, el de las
+    /// operaciones previas con la misma clave de idempotencia y el deadline de
+    /// la transaccion
+    fn responder_prepare(
+        &mut self,
+        mensaje: MensajeTransaccion,
+        monto: Monto,
+        clave: usize,
+        deadline: u64,
+    ) {
         println!(
             "[WebService] Recibí PREPARE de {} para la transaccion {} con monto {}",
             mensaje.id_emisor, mensaje.id_op, monto
@@ -71,8 +134,8 @@ impl WebService {
             MensajeTransaccion::new(CodigoTransaccion::READY, self.id, mensaje.id_op);
         let respuesta_commit =
             MensajeTransaccion::new(CodigoTransaccion::COMMIT, self.id, mensaje.id_op);
-        let respuesta_abort =
-            MensajeTransaccion::new(CodigoTransaccion::ABORT, self.id, mensaje.id_op);
+        let respuesta_solo_lectura =
+            MensajeTransaccion::new(CodigoTransaccion::READ_ONLY, self.id, mensaje.id_op);
 
         if let Some(estado) = self.log.get(&mensaje.id_op) {
             match estado {
@@ -86,9 +149,19 @@ impl WebService {
                     respuesta_commit,
                     mensaje.id_emisor,
                 ),
-                EstadoServicio::Abort => self.insertar_y_enviar(
-                    EstadoServicio::Abort,
-                    respuesta_abort,
+                EstadoServicio::Abort => {
+                    let motivo = self
+                        .motivos
+                        .get(&mensaje.id_op)
+                        .cloned()
+                        .unwrap_or_else(|| {
+                            MotivoAborto::new(CausaAborto::Desconocida, "Transaccion abortada")
+                        });
+                    self.votar_abort(&mensaje, motivo)
+                }
+                EstadoServicio::SoloLectura => self.insertar_y_enviar(
+                    EstadoServicio::SoloLectura,
+                    respuesta_solo_lectura,
                     mensaje.id_emisor,
                 ),
             }
@@ -96,18 +169,87 @@ impl WebService {
             return;
         };
 
+        if Reloj::ahora_ms() > deadline {
+            println!(
+                "[WebService] La transaccion {} superó su deadline, no se toman recursos",
+                mensaje.id_op
+            );
+            let motivo = MotivoAborto::new(
+                CausaAborto::Timeout,
+                "La transaccion superó su deadline antes del prepare",
+            );
+            self.votar_abort(&mensaje, motivo);
+            return;
+        }
+
+        // Otra operacion con la misma clave ya tomó los recursos del pago
+        let estado_clave = self
+            .claves
+            .get(&clave)
+            .and_then(|id_op| self.log.get(id_op))
+            .copied();
+        match estado_clave {
+            Some(EstadoServicio::Commit) => {
+                println!(
+                    "[WebService] El pago de clave {} ya fue commiteado, no se vuelve a procesar",
+                    clave
+                );
+                self.insertar_y_enviar(
+                    EstadoServicio::SoloLectura,
+                    respuesta_solo_lectura,
+                    mensaje.id_emisor,
+                );
+                return;
+            }
+            Some(EstadoServicio::Ready) => {
+                println!(
+                    "[WebService] El pago de clave {} tiene otra operacion en curso",
+                    clave
+                );
+                let motivo = MotivoAborto::new(
+                    CausaAborto::OperacionEnCurso,
+                    "El pago tiene otra operacion en curso",
+                );
+                self.votar_abort(&mensaje, motivo);
+                return;
+            }
+            _ => {}
+        }
+
         self.simular_trabajo();
 
-        match self.simular_resultado() {
+        match self.simular_resultado(monto) {
+            Ok(_) if self.es_solo_lectura() => self.insertar_y_enviar(
+                EstadoServicio::SoloLectura,
+                respuesta_solo_lectura,
+                mensaje.id_emisor,
+            ),
             Ok(_) => {
+                self.claves.insert(clave, mensaje.id_op);
                 self.insertar_y_enviar(EstadoServicio::Ready, respuesta_ready, mensaje.id_emisor)
             }
-            Err(_) => {
-                self.insertar_y_enviar(EstadoServicio::Abort, respuesta_abort, mensaje.id_emisor)
-            }
+            Err(motivo) => self.votar_abort(&mensaje, motivo),
         };
     }
 
+    /// Vota abort para la transaccion del mensaje indicando el motivo, que se
+    /// guarda para responder los prepare repetidos
+    fn votar_abort(&mut self, mensaje: &MensajeTransaccion, motivo: MotivoAborto) {
+        println!(
+            "[WebService] Se aborta la transaccion {}: {}",
+            mensaje.id_op, motivo
+        );
+        let respuesta = MensajeTransaccion::new(
+            CodigoTransaccion::ABORT {
+                motivo: Some(motivo.clone()),
+            },
+            self.id,
+            mensaje.id_op,
+        );
+        self.motivos.insert(mensaje.id_op, motivo);
+        self.insertar_y_enviar(EstadoServicio::Abort, respuesta, mensaje.id_emisor);
+    }
+
     /// Responde un commit segun el estado de la transaccion
     fn responder_commit(&mut self, mensaje: MensajeTransaccion) {
         println!(
@@ -129,6 +271,11 @@ impl WebService {
                 EstadoServicio::Abort => {
                     println!("[WebService] Error inesperado: llego commit con estado abort")
                 }
+                EstadoServicio::SoloLectura => self.insertar_y_enviar(
+                    EstadoServicio::SoloLectura,
+                    respuesta,
+                    mensaje.id_emisor,
+                ),
             }
         };
     }
@@ -140,7 +287,11 @@ impl WebService {
             mensaje.id_emisor, mensaje.id_op
         );
 
-        let respuesta = MensajeTransaccion::new(CodigoTransaccion::ABORT, self.id, mensaje.id_op);
+        let respuesta = MensajeTransaccion::new(
+            CodigoTransaccion::ABORT { motivo: None },
+            self.id,
+            mensaje.id_op,
+        );
 
         if let Some(estado) = self.log.get(&mensaje.id_op) {
             match estado {
@@ -154,6 +305,11 @@ impl WebService {
                 EstadoServicio::Abort => {
                     self.insertar_y_enviar(EstadoServicio::Abort, respuesta, mensaje.id_emisor)
                 }
+                EstadoServicio::SoloLectura => self.insertar_y_enviar(
+                    EstadoServicio::SoloLectura,
+                    respuesta,
+                    mensaje.id_emisor,
+                ),
             }
 
             return;
@@ -163,7 +319,7 @@ impl WebService {
         self.insertar_y_enviar(EstadoServicio::Abort, respuesta, mensaje.id_emisor);
     }
 
-    /// Actualiza el log de transacciones y envia mensaje
+    /// Actualiza el log de transacciones y encola el mensaje para su envío
     fn insertar_y_enviar(
         &mut self,
         estado: EstadoServicio,
@@ -171,31 +327,70 @@ impl WebService {
         id_emisor: usize,
     ) {
         self.log.insert(mensaje.id_op, estado);
-        let direccion = DNS::direccion_alglobo(&id_emisor);
+        self.respuestas.entry(id_emisor).or_default().push(mensaje);
+    }
+
+    /// Envia las respuestas encoladas, un lote por emisor
+    fn enviar_respuestas(&mut self) {
+        for (id_emisor, mensajes) in self.respuestas.drain() {
+            let direccion = DNS::direccion_alglobo(&id_emisor);
 
-        println!("[WebService] Envío {:?} a {}", mensaje.codigo, id_emisor);
-        let enviado = self.protocolo.enviar(&mensaje, direccion);
-        if enviado.is_err() {
-            println!("[WebService] Error: Fallo al enviar mensaje")
+            mensajes.iter().for_each(|mensaje| {
+                println!("[WebService] Envío {:?} a {}", mensaje.codigo, id_emisor)
+            });
+            let enviado = match mensajes.as_slice() {
+                [mensaje] => self.protocolo.enviar(mensaje, direccion),
+                _ => self.protocolo.enviar_lote(&mensajes, direccion),
+            };
+            if enviado.is_err() {
+                println!("[WebService] Error: Fallo al enviar mensaje")
+            }
         }
     }
 
-    /// Simula trabajo por un tiempo random
-    fn simular_trabajo(&self) {
+    /// Simula trabajo por un tiempo random. El trabajo se simula una única
+    /// vez por datagrama recibido, por lo que un lote amortiza su costo.
+    fn simular_trabajo(&mut self) {
+        if self.trabajo_simulado {
+            return;
+        }
+        self.trabajo_simulado = true;
+
         let mut rng = rand::thread_rng();
         let tiempo_trabajo = rng.gen_range(self.envs.trabajo_min..self.envs.trabajo_max);
         thread::sleep(Duration::from_millis(tiempo_trabajo));
     }
 
-    /// Simula un resultado segun una probabilidad de fallo
-    fn simular_resultado(&self) -> Result<(), ()> {
+    /// Devuelve verdadero si el servicio está configurado como de solo lectura,
+    /// es decir, si no modifica su estado al commitear.
+    fn es_solo_lectura(&self) -> bool {
+        self.envs.solo_lectura.contains(&self.id)
+    }
+
+    /// Simula un resultado segun una probabilidad de fallo. En caso de fallo
+    /// devuelve el motivo correspondiente al servicio.
+    fn simular_resultado(&self, monto: Monto) -> Result<(), MotivoAborto> {
         let mut rng = rand::thread_rng();
         let ok = rng.gen::<f32>() >= self.envs.probabilidad_fallo;
 
         if ok {
-            Ok(())
-        } else {
-            Err(())
+            return Ok(());
         }
+
+        Err(match self.id {
+            AEROLINEA => MotivoAborto::new(
+                CausaAborto::SinDisponibilidad,
+                "No hay asientos disponibles en el vuelo",
+            ),
+            HOTEL => MotivoAborto::new(
+                CausaAborto::SinDisponibilidad,
+                "No hay habitaciones disponibles en el hotel",
+            ),
+            BANCO => MotivoAborto::new(
+                CausaAborto::FondosInsuficientes,
+                &format!("Fondos insuficientes para debitar {}", monto),
+            ),
+            _ => MotivoAborto::new(CausaAborto::Desconocida, "Error al tomar los recursos"),
+        })
     }
 }